@@ -0,0 +1,433 @@
+use anyhow::{anyhow, Result};
+use openssl::base64::encode_block;
+use openssl::rand::rand_bytes;
+use openssl::sha::sha256;
+use reqwest::{Client, Url};
+use rocket::serde::json::Value;
+use rocket::serde::{Deserialize, Serialize};
+use rocket::tokio::sync::OnceCell;
+use std::env;
+use utoipa::ToSchema;
+
+/// A user as reported by an identity provider.
+pub struct ProviderUser {
+    /// Id of the user at the provider
+    pub subject: String,
+    pub name: String,
+    pub avatar: String,
+    /// UUID a new user is created with, a random one is used if not set
+    pub user_uuid: Option<String>,
+}
+
+/// A service users log in through with the OAuth authorization code flow.
+#[rocket::async_trait]
+pub trait IdentityProvider: Send + Sync {
+    /// Id the provider is selected with and its identities are stored under.
+    fn id(&self) -> &str;
+
+    /// Name shown on the login page.
+    fn name(&self) -> &str;
+
+    /// Returns the URL of the provider's login page, which redirects to `/account/oauth/callback` with the given state.
+    async fn authorization_url(&self, state: &str, pkce_verifier: &str) -> Result<String>;
+
+    /// Exchanges the code of the callback for the user that logged in.
+    async fn fetch_user(&self, code: &str, pkce_verifier: &str) -> Result<ProviderUser>;
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    refresh_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DiscordUser {
+    id: String,
+    username: String,
+    avatar: Option<String>,
+}
+
+/// Settings of the Discord application.
+pub struct DiscordConfig {
+    pub client_id: String,
+    pub client_secret: String,
+    pub redirect_uri: String,
+    /// Discord itself, or a stand-in serving the same paths
+    pub base_url: String,
+}
+
+impl DiscordConfig {
+    /// Reads `DISCORD_CLIENT_ID`, `DISCORD_CLIENT_SECRET`, `DISCORD_REDIRECT_URI` and the optional `DISCORD_BASE_URL`.
+    pub fn from_env() -> Result<Self> {
+        Ok(Self {
+            client_id: required_var("DISCORD_CLIENT_ID")?,
+            client_secret: required_var("DISCORD_CLIENT_SECRET")?,
+            redirect_uri: required_url_var("DISCORD_REDIRECT_URI")?,
+            base_url: check_url(
+                "DISCORD_BASE_URL",
+                env::var("DISCORD_BASE_URL").unwrap_or_else(|_| "https://discord.com".to_string()),
+            )?,
+        })
+    }
+
+    fn endpoint(&self, path: &str) -> String {
+        format!("{}{}", self.base_url.trim_end_matches('/'), path)
+    }
+}
+
+/// Login through Discord.
+pub struct DiscordProvider {
+    client: Client,
+    config: DiscordConfig,
+}
+
+impl DiscordProvider {
+    pub fn new(config: DiscordConfig, client: Client) -> Self {
+        Self { client, config }
+    }
+}
+
+#[rocket::async_trait]
+impl IdentityProvider for DiscordProvider {
+    fn id(&self) -> &str {
+        "discord"
+    }
+
+    fn name(&self) -> &str {
+        "Discord"
+    }
+
+    async fn authorization_url(&self, state: &str, _pkce_verifier: &str) -> Result<String> {
+        let url = Url::parse_with_params(
+            &self.config.endpoint("/oauth2/authorize"),
+            &[
+                ("client_id", self.config.client_id.as_str()),
+                ("redirect_uri", self.config.redirect_uri.as_str()),
+                ("response_type", "code"),
+                ("scope", "identify"),
+                ("state", state),
+            ],
+        )?;
+        Ok(url.to_string())
+    }
+
+    async fn fetch_user(&self, code: &str, _pkce_verifier: &str) -> Result<ProviderUser> {
+        let params = [
+            ("client_id", self.config.client_id.as_str()),
+            ("client_secret", self.config.client_secret.as_str()),
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", self.config.redirect_uri.as_str()),
+        ];
+
+        // Exchange Authorization Codes for Token
+        let token_response = self
+            .client
+            .post(self.config.endpoint("/api/oauth2/token"))
+            .form(&params)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<TokenResponse>()
+            .await?;
+
+        // Get Userinformation with Access Token
+        let user_response = self
+            .client
+            .get(self.config.endpoint("/api/users/@me"))
+            .bearer_auth(&token_response.access_token)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<DiscordUser>()
+            .await?;
+
+        // revoke refresh token
+        if let Some(refresh_token) = &token_response.refresh_token {
+            let params = [
+                ("client_id", self.config.client_id.as_str()),
+                ("client_secret", self.config.client_secret.as_str()),
+                ("token", refresh_token.as_str()),
+                ("token_type_hint", "access_token"),
+            ];
+            let _response = self
+                .client
+                .post(self.config.endpoint("/api/oauth2/token/revoke"))
+                .form(&params)
+                .send()
+                .await?;
+        }
+
+        Ok(ProviderUser {
+            // the frontend builds the avatar URL from the Discord id
+            user_uuid: Some(user_response.id.clone()),
+            subject: user_response.id,
+            name: user_response.username,
+            avatar: user_response.avatar.unwrap_or_default(),
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct OidcDiscovery {
+    authorization_endpoint: String,
+    token_endpoint: String,
+    userinfo_endpoint: String,
+}
+
+/// Settings of a client at an OpenID Connect server.
+pub struct OidcConfig {
+    pub id: String,
+    /// Shown on the login page
+    pub name: String,
+    /// The discovery document is read from `<issuer_url>/.well-known/openid-configuration`
+    pub issuer_url: String,
+    pub client_id: String,
+    /// Not needed for public clients
+    pub client_secret: Option<String>,
+    pub redirect_uri: String,
+    pub scopes: String,
+    pub name_claim: String,
+    pub avatar_claim: String,
+}
+
+impl OidcConfig {
+    /// Reads `OIDC_<ID>_ISSUER_URL`, `OIDC_<ID>_CLIENT_ID`, `OIDC_<ID>_REDIRECT_URI` and the optional
+    /// `OIDC_<ID>_CLIENT_SECRET`, `OIDC_<ID>_NAME`, `OIDC_<ID>_SCOPES`, `OIDC_<ID>_NAME_CLAIM` and `OIDC_<ID>_AVATAR_CLAIM`.
+    pub fn from_env(id: &str) -> Result<Self> {
+        let prefix = format!("OIDC_{}_", id.to_uppercase().replace('-', "_"));
+        let name = |suffix: &str| format!("{}{}", prefix, suffix);
+        let var = |suffix: &str| env::var(name(suffix)).ok();
+        Ok(Self {
+            id: id.to_string(),
+            name: var("NAME").unwrap_or_else(|| id.to_string()),
+            issuer_url: required_url_var(&name("ISSUER_URL"))?,
+            client_id: required_var(&name("CLIENT_ID"))?,
+            client_secret: var("CLIENT_SECRET"),
+            redirect_uri: required_url_var(&name("REDIRECT_URI"))?,
+            scopes: var("SCOPES").unwrap_or_else(|| "openid profile".to_string()),
+            name_claim: var("NAME_CLAIM").unwrap_or_else(|| "preferred_username".to_string()),
+            avatar_claim: var("AVATAR_CLAIM").unwrap_or_else(|| "picture".to_string()),
+        })
+    }
+}
+
+/// Login through an OpenID Connect server such as Keycloak or Authentik, using PKCE.
+pub struct OidcProvider {
+    client: Client,
+    config: OidcConfig,
+    discovery: OnceCell<OidcDiscovery>,
+}
+
+impl OidcProvider {
+    pub fn new(config: OidcConfig, client: Client) -> Self {
+        Self {
+            client,
+            config,
+            discovery: OnceCell::new(),
+        }
+    }
+
+    /// Fetches the discovery document of the issuer once.
+    async fn discovery(&self) -> Result<&OidcDiscovery> {
+        self.discovery
+            .get_or_try_init(|| async {
+                let url = format!(
+                    "{}/.well-known/openid-configuration",
+                    self.config.issuer_url.trim_end_matches('/')
+                );
+                let discovery = self
+                    .client
+                    .get(url)
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json::<OidcDiscovery>()
+                    .await?;
+                Ok(discovery)
+            })
+            .await
+    }
+}
+
+#[rocket::async_trait]
+impl IdentityProvider for OidcProvider {
+    fn id(&self) -> &str {
+        &self.config.id
+    }
+
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    async fn authorization_url(&self, state: &str, pkce_verifier: &str) -> Result<String> {
+        let discovery = self.discovery().await?;
+        let url = Url::parse_with_params(
+            &discovery.authorization_endpoint,
+            &[
+                ("client_id", self.config.client_id.as_str()),
+                ("redirect_uri", self.config.redirect_uri.as_str()),
+                ("response_type", "code"),
+                ("scope", self.config.scopes.as_str()),
+                ("state", state),
+                ("code_challenge", &pkce_challenge(pkce_verifier)),
+                ("code_challenge_method", "S256"),
+            ],
+        )?;
+        Ok(url.to_string())
+    }
+
+    async fn fetch_user(&self, code: &str, pkce_verifier: &str) -> Result<ProviderUser> {
+        let discovery = self.discovery().await?;
+        let mut params = vec![
+            ("client_id", self.config.client_id.as_str()),
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", self.config.redirect_uri.as_str()),
+            ("code_verifier", pkce_verifier),
+        ];
+        if let Some(client_secret) = &self.config.client_secret {
+            params.push(("client_secret", client_secret.as_str()));
+        }
+        let token_response = self
+            .client
+            .post(&discovery.token_endpoint)
+            .form(&params)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<TokenResponse>()
+            .await?;
+
+        let claims = self
+            .client
+            .get(&discovery.userinfo_endpoint)
+            .bearer_auth(&token_response.access_token)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Value>()
+            .await?;
+        let claim = |name: &str| claims.get(name).and_then(Value::as_str).map(str::to_string);
+        let subject = claim("sub").ok_or_else(|| anyhow!("the provider returned no subject"))?;
+        Ok(ProviderUser {
+            name: claim(&self.config.name_claim).unwrap_or_else(|| subject.clone()),
+            avatar: claim(&self.config.avatar_claim).unwrap_or_default(),
+            subject,
+            user_uuid: None,
+        })
+    }
+}
+
+/// A configured identity provider as listed on the login page.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ProviderInfo {
+    id: String,
+    name: String,
+}
+
+/// The identity providers users can log in through.
+pub struct IdentityProviders {
+    providers: Vec<Box<dyn IdentityProvider>>,
+}
+
+impl IdentityProviders {
+    /// Offers the given providers, the first one is used if a login names none.
+    pub fn new(providers: Vec<Box<dyn IdentityProvider>>) -> Result<Self> {
+        for (i, provider) in providers.iter().enumerate() {
+            if providers[..i].iter().any(|p| p.id() == provider.id()) {
+                return Err(anyhow!(
+                    "the identity provider {} is configured twice",
+                    provider.id()
+                ));
+            }
+        }
+        Ok(Self { providers })
+    }
+
+    /// Reads the comma separated provider ids from `IDENTITY_PROVIDERS`, `discord` selects Discord and any other id an OpenID Connect server.
+    /// Defaults to Discord if `DISCORD_CLIENT_ID` is set, otherwise only local accounts can log in.
+    /// All providers send their requests through the given client. Returns an error if a setting is missing or invalid.
+    pub fn from_env(client: Client) -> Result<Self> {
+        let ids = env::var("IDENTITY_PROVIDERS").unwrap_or_else(|_| {
+            if env::var("DISCORD_CLIENT_ID").is_ok() {
+                "discord".to_string()
+            } else {
+                String::new()
+            }
+        });
+        let providers = ids
+            .split(',')
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(|id| -> Result<Box<dyn IdentityProvider>> {
+                Ok(match id {
+                    "discord" => Box::new(DiscordProvider::new(
+                        DiscordConfig::from_env()?,
+                        client.clone(),
+                    )),
+                    id => Box::new(OidcProvider::new(OidcConfig::from_env(id)?, client.clone())),
+                })
+            })
+            .collect::<Result<_>>()?;
+        Self::new(providers)
+    }
+
+    /// Returns the provider with the given id, or the first configured provider if no id is given.
+    pub fn get(&self, id: Option<&str>) -> Option<&dyn IdentityProvider> {
+        match id {
+            Some(id) => self.providers.iter().find(|p| p.id() == id),
+            None => self.providers.first(),
+        }
+        .map(|p| p.as_ref())
+    }
+
+    /// Lists the configured providers in the configured order.
+    pub fn list(&self) -> Vec<ProviderInfo> {
+        self.providers
+            .iter()
+            .map(|p| ProviderInfo {
+                id: p.id().to_string(),
+                name: p.name().to_string(),
+            })
+            .collect()
+    }
+}
+
+/// Reads an environment variable that has to be set.
+fn required_var(name: &str) -> Result<String> {
+    env::var(name).map_err(|_| anyhow!("{} not set", name))
+}
+
+/// Reads an environment variable that has to be set to an absolute URL.
+fn required_url_var(name: &str) -> Result<String> {
+    check_url(name, required_var(name)?)
+}
+
+/// Checks that the value of a setting is an absolute URL.
+fn check_url(name: &str, value: String) -> Result<String> {
+    Url::parse(&value).map_err(|e| anyhow!("{} is not a valid URL: {}", name, e))?;
+    Ok(value)
+}
+
+/// Returns a random URL safe token for the OAuth state or the PKCE verifier.
+pub fn random_token() -> Result<String> {
+    let mut bytes = [0u8; 32];
+    rand_bytes(&mut bytes)?;
+    Ok(base64_url(&bytes))
+}
+
+/// Derives the S256 PKCE challenge from a verifier.
+fn pkce_challenge(pkce_verifier: &str) -> String {
+    base64_url(&sha256(pkce_verifier.as_bytes()))
+}
+
+/// Encodes bytes as unpadded URL safe base64.
+fn base64_url(bytes: &[u8]) -> String {
+    encode_block(bytes)
+        .replace('+', "-")
+        .replace('/', "_")
+        .trim_end_matches('=')
+        .to_string()
+}
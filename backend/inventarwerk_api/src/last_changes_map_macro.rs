@@ -1,42 +1,52 @@
-use std::collections::HashMap;
-use std::sync::Mutex;
-
 use lazy_static::lazy_static;
+use repos::model::{InventoryChange, InventoryChangeKind};
+use rocket::tokio::sync::broadcast;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-lazy_static! {
-    pub static ref GLOBAL_MAP: Mutex<HashMap<String, u128>> = Mutex::new(HashMap::new());
+/// Number of change events buffered for each subscriber before it starts lagging behind.
+const CHANGE_CHANNEL_CAPACITY: usize = 256;
+
+/// A single change on an inventory, as pushed to the subscribers of the change channel.
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct InventoryChangeEvent {
+    pub inventory_uuid: String,
+    /// Milliseconds since UNIX epoch
+    pub timestamp: i64,
+    pub kind: InventoryChangeKind,
 }
 
-/// Macro to report a change on an inventory by updating its last change timestamp to the current time (in milliseconds since UNIX epoch).
-#[macro_export]
-macro_rules! report_change_on_inventory {
-    ($id:expr) => {
-        {
-            use crate::last_changes_map_macro::GLOBAL_MAP;
-            use std::time::{SystemTime, UNIX_EPOCH};
-            let start = SystemTime::now();
-            let duration = start
-                .duration_since(UNIX_EPOCH)
-                .expect("Time went backwards");
-            let timestamp_in_milliseconds = duration.as_millis();
-            GLOBAL_MAP
-                .lock()
-                .unwrap()
-                .insert($id.to_string(), timestamp_in_milliseconds);
+impl From<InventoryChange> for InventoryChangeEvent {
+    fn from(change: InventoryChange) -> Self {
+        InventoryChangeEvent {
+            inventory_uuid: change.inventory_uuid,
+            timestamp: change.changed_at,
+            kind: change.change_kind,
         }
-    };
+    }
 }
 
-/// Macro to get the last change timestamp for an inventory by UUID.
+lazy_static! {
+    pub static ref GLOBAL_CHANGE_CHANNEL: broadcast::Sender<InventoryChangeEvent> =
+        broadcast::channel(CHANGE_CHANNEL_CAPACITY).0;
+}
+
+/// Macro to report a change on an inventory.
 ///
-/// Returns `0` if no timestamp is set for the given inventory.
+/// The change is persisted through the given `ChangeLogRepository` and broadcast to all subscribers of `GLOBAL_CHANGE_CHANNEL`.
+/// Optionally takes the affected item and the old and new value of the changed entity.
 #[macro_export]
-macro_rules! get_last_inventory_change {
-    ($id:expr) => {
-        *crate::last_changes_map_macro::GLOBAL_MAP
-            .lock()
-            .unwrap()
-            .get($id)
-            .unwrap_or(&0)
+macro_rules! report_change_on_inventory {
+    ($chg_rep:expr, $user_id:expr, $id:expr, $kind:expr) => {
+        $crate::report_change_on_inventory!($chg_rep, $user_id, $id, $kind, None, None, None)
     };
+    ($chg_rep:expr, $user_id:expr, $id:expr, $kind:expr, $item:expr, $old:expr, $new:expr) => {{
+        use $crate::last_changes_map_macro::GLOBAL_CHANGE_CHANNEL;
+        let change = $chg_rep
+            .record_change($id, $user_id, $kind, $item, $old, $new)
+            .await?;
+        // Sending only fails if nobody is subscribed, which is fine
+        let _ = GLOBAL_CHANGE_CHANNEL.send(change.into());
+    }};
 }
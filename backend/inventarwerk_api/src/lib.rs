@@ -0,0 +1,175 @@
+#[macro_use]
+extern crate rocket;
+
+pub mod identity_provider;
+mod last_changes_map_macro;
+pub mod routers;
+
+use identity_provider::IdentityProviders;
+use openssl::rand::rand_bytes;
+use repos::repos::api_token_repository::ApiTokenRepository;
+use repos::repos::campaign_repository::CampaignRepository;
+use repos::repos::change_log_repository::ChangeLogRepository;
+use repos::repos::currency_repository::CurrencyRepository;
+use repos::repos::identity_repository::IdentityRepository;
+use repos::repos::inventory_repository::InventoryRepository;
+use repos::repos::invite_repository::InviteRepository;
+use repos::repos::item_preset_repository::ItemPresetRepository;
+use repos::repos::item_transfer_repository::ItemTransferRepository;
+use repos::repos::local_account_repository::LocalAccountRepository;
+use repos::repos::loot_repository::LootRepository;
+use repos::repos::money_repository::MoneyRepository;
+use repos::repos::role_change_repository::RoleChangeRepository;
+use repos::repos::session_repository::SessionRepository;
+use repos::repos::setting_repository::SettingRepository;
+use repos::repos::undo_repository::UndoRepository;
+use repos::repos::user_repository::UserRepository;
+use repos::DbPool;
+use rocket::config::Config;
+use rocket::{Build, Rocket};
+use std::env;
+
+use routers::account_router::AccountApiDoc;
+use routers::api_token_router::ApiTokenApiDoc;
+use routers::campaign_router::CampaignApiDoc;
+use routers::currency_router::CurrencyApiDoc;
+use routers::encumbrance_router::{encumbrance_rule_from_env, EncumbranceApiDoc};
+use routers::inventory_router::InventoryApiDoc;
+use routers::invite_router::InviteApiDoc;
+use routers::item_preset_router::ItemPresetApiDoc;
+use routers::last_changes_router::LastChangesApiDoc;
+use routers::local_account_router::LocalAccountApiDoc;
+use routers::money_router::MoneyApiDoc;
+use routers::session_router::{SessionApiDoc, SessionConfig};
+use routers::transfer_router::{ItemTransferMode, TransferApiDoc};
+
+use utoipa::OpenApi;
+
+use utoipa_swagger_ui::SwaggerUi;
+
+/// Builds the API on a migrated database, users log in through the given identity providers.
+///
+/// Prepares the database for the start on the way, the static frontend files are not served.
+pub async fn build_rocket(dbconn: DbPool, identity_providers: IdentityProviders) -> Rocket<Build> {
+    let inv_rep = InventoryRepository::new(dbconn.clone());
+    let usr_rep = UserRepository::new(dbconn.clone());
+    let ipr_rep = ItemPresetRepository::new(dbconn.clone());
+    let chg_rep = ChangeLogRepository::new(dbconn.clone());
+    let und_rep = UndoRepository::new(dbconn.clone());
+    let mon_rep = MoneyRepository::new(dbconn.clone());
+    let cur_rep = CurrencyRepository::new(dbconn.clone());
+    let itr_rep = ItemTransferRepository::new(dbconn.clone());
+    let lot_rep = LootRepository::new(dbconn.clone());
+    let cmp_rep = CampaignRepository::new(dbconn.clone());
+    let ivt_rep = InviteRepository::new(dbconn.clone());
+    let set_rep = SettingRepository::new(dbconn.clone());
+    let rch_rep = RoleChangeRepository::new(dbconn.clone());
+    let lac_rep = LocalAccountRepository::new(dbconn.clone());
+    let idn_rep = IdentityRepository::new(dbconn.clone());
+    let tok_rep = ApiTokenRepository::new(dbconn.clone());
+    let ses_rep = SessionRepository::new(dbconn.clone());
+
+    // keep the cookie secret across restarts, so logins survive them
+    let secret_key = match env::var("COOKIE_SECRET") {
+        Ok(secret) => secret,
+        Err(_) => {
+            let mut bytes = [0u8; 32];
+            rand_bytes(&mut bytes).expect("Couldn't generate a cookie secret");
+            let secret: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+            set_rep
+                .get_or_init_cookie_secret(&secret)
+                .await
+                .expect("DB failed during startup, can not recover from this.")
+        }
+    };
+
+    ses_rep
+        .delete_expired_sessions()
+        .await
+        .expect("DB failed during startup, can not recover from this.");
+
+    if !usr_rep
+        .any_user_exists()
+        .await
+        .expect("DB failed during startup, can not recover from this.")
+    {
+        set_rep
+            .set_locked(false)
+            .await
+            .expect("DB failed during startup, can not recover from this.");
+    }
+
+    let figment = Config::figment().merge(("secret_key", secret_key));
+    let config = Config::from(figment);
+    rocket::build()
+        .configure(config)
+        .manage(inv_rep)
+        .manage(usr_rep)
+        .manage(ipr_rep)
+        .manage(chg_rep)
+        .manage(und_rep)
+        .manage(mon_rep)
+        .manage(cur_rep)
+        .manage(itr_rep)
+        .manage(lot_rep)
+        .manage(cmp_rep)
+        .manage(ivt_rep)
+        .manage(set_rep)
+        .manage(rch_rep)
+        .manage(lac_rep)
+        .manage(idn_rep)
+        .manage(tok_rep)
+        .manage(ses_rep)
+        .manage(SessionConfig::from_env())
+        .manage(identity_providers)
+        .manage(ItemTransferMode::from_env())
+        .manage(encumbrance_rule_from_env())
+        .mount("/", routers::get_account_routes())
+        .mount("/", routers::get_inventory_routes())
+        .mount("/", routers::get_item_preset_routes())
+        .mount("/", routers::get_last_changes_routes())
+        .mount("/", routers::get_money_routes())
+        .mount("/", routers::get_transfer_routes())
+        .mount("/", routers::get_encumbrance_routes())
+        .mount("/", routers::get_currency_routes())
+        .mount("/", routers::get_campaign_routes())
+        .mount("/", routers::get_invite_routes())
+        .mount("/", routers::get_local_account_routes())
+        .mount("/", routers::get_api_token_routes())
+        .mount("/", routers::get_session_routes())
+        .mount(
+            "/",
+            SwaggerUi::new("/swagger-ui/<_..>")
+                .url(
+                    "/api-docs/openapi_inventory.json",
+                    InventoryApiDoc::openapi(),
+                )
+                .url(
+                    "/api-docs/openapi_item_preset.json",
+                    ItemPresetApiDoc::openapi(),
+                )
+                .url("/api-docs/openapi_account.json", AccountApiDoc::openapi())
+                .url(
+                    "/api-docs/openapi_last_changes.json",
+                    LastChangesApiDoc::openapi(),
+                )
+                .url("/api-docs/openapi_money.json", MoneyApiDoc::openapi())
+                .url("/api-docs/openapi_transfer.json", TransferApiDoc::openapi())
+                .url(
+                    "/api-docs/openapi_encumbrance.json",
+                    EncumbranceApiDoc::openapi(),
+                )
+                .url("/api-docs/openapi_currency.json", CurrencyApiDoc::openapi())
+                .url("/api-docs/openapi_campaign.json", CampaignApiDoc::openapi())
+                .url("/api-docs/openapi_invite.json", InviteApiDoc::openapi())
+                .url(
+                    "/api-docs/openapi_local_account.json",
+                    LocalAccountApiDoc::openapi(),
+                )
+                .url(
+                    "/api-docs/openapi_api_token.json",
+                    ApiTokenApiDoc::openapi(),
+                )
+                .url("/api-docs/openapi_session.json", SessionApiDoc::openapi()),
+        )
+}
@@ -1,25 +1,29 @@
-use reqwest::Client;
 use rocket::form::FromForm;
 use rocket::http::{Cookie, CookieJar, Status};
 use rocket::response::Redirect;
-use rocket::serde::json::Json;
+use rocket::serde::json::{from_str, to_string, Json};
 use rocket::serde::{Deserialize, Serialize};
 use rocket::State;
-use std::env;
 
 use utoipa::IntoParams;
 use utoipa::OpenApi;
 use utoipa::ToSchema;
 
-use repos::model::User;
+use repos::model::{RoleChange, User, UserIdentity};
+use repos::repos::identity_repository::IdentityRepository;
+use repos::repos::invite_repository::InviteRepository;
+use repos::repos::role_change_repository::RoleChangeRepository;
+use repos::repos::session_repository::SessionRepository;
+use repos::repos::setting_repository::SettingRepository;
 use repos::repos::user_repository::UserRepository;
 
 use rocket_errors::anyhow::Result;
 
-use crate::routers::router_utility::user_is_dm;
-use crate::{lock_toggle, locked_status};
+use crate::identity_provider::{random_token, IdentityProviders, ProviderInfo};
+use crate::routers::router_utility::{registration_allowed, user_is_dm, ACCESS_DENIAL_MESSAGE};
 
-use super::create_error;
+use super::session_router::{start_session, SessionConfig, SESSION_COOKIE};
+use super::{create_error, BearerAuthAddon};
 
 #[derive(FromForm, ToSchema, IntoParams)]
 pub struct AccountUUIDParams {
@@ -84,150 +88,279 @@ pub async fn is_account_dm(
     }))
 }
 
+#[derive(FromForm, ToSchema, IntoParams)]
+pub struct LoginParams {
+    /// Invitation code, lets a new user register while the server is locked
+    invite: Option<String>,
+    /// Id of the identity provider, see `/account/providers`. The first configured provider if not set
+    provider: Option<String>,
+}
+
+/// State of a login at an identity provider, kept in a private cookie until the callback.
+#[derive(Serialize, Deserialize)]
+struct OAuthLogin {
+    provider: String,
+    state: String,
+    pkce_verifier: String,
+    invite: Option<String>,
+    /// Link the identity to the logged in user instead of logging in
+    link: bool,
+}
+
+/// Redirects to the login page of a provider and remembers the login in a cookie.
+async fn start_oauth_login(
+    cookies: &CookieJar<'_>,
+    idp: &IdentityProviders,
+    provider: Option<&str>,
+    invite: Option<String>,
+    link: bool,
+) -> Result<Redirect> {
+    let Some(provider) = idp.get(provider) else {
+        return Err(create_error("unknown identity provider"));
+    };
+    let login = OAuthLogin {
+        provider: provider.id().to_string(),
+        state: random_token()?,
+        pkce_verifier: random_token()?,
+        invite,
+        link,
+    };
+    let url = provider
+        .authorization_url(&login.state, &login.pkce_verifier)
+        .await?;
+    cookies.add_private(Cookie::build(("oauth_login", to_string(&login)?)).http_only(true));
+    Ok(Redirect::to(url))
+}
+
 #[utoipa::path(
     get,
     path = "/account/login",
-    summary = "Redirect to the Discord login page",
-    description = r#"Redirects the user to the Discord OAuth login page."#,
+    params(LoginParams),
+    summary = "Redirect to the login page of an identity provider",
+    description = r#"Redirects the user to the login page of Discord or an OpenID Connect server. An invitation code is kept until the callback.
+Returns an error if the provider is not configured."#,
     responses(
-        (status = 307, description = "Redirecting to Discord login")
+        (status = 303, description = "Redirecting to the login page")
     ),
     tag = "Accounts"
 )]
-#[get("/account/login")]
-pub async fn login() -> Redirect {
-    let client_id = env::var("DISCORD_CLIENT_ID").expect("DISCORD_CLIENT_ID not set");
-    let redirect_uri = env::var("DISCORD_REDIRECT_URI").expect("DISCORD_REDIRECT_URI not set");
-    let url = format!(
-        "https://discord.com/oauth2/authorize?client_id={}&redirect_uri={}&response_type=code&scope=identify",
-        client_id, redirect_uri
-    );
-    Redirect::to(url)
+#[get("/account/login?<params..>")]
+pub async fn login(
+    params: LoginParams,
+    cookies: &CookieJar<'_>,
+    idp: &State<IdentityProviders>,
+) -> Result<Redirect> {
+    start_oauth_login(
+        cookies,
+        idp.inner(),
+        params.provider.as_deref(),
+        params.invite,
+        false,
+    )
+    .await
 }
 
 #[derive(FromForm, ToSchema, IntoParams)]
-pub struct CodeParams {
-    code: String,
+pub struct ProviderParams {
+    /// Id of the identity provider, see `/account/providers`
+    provider: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, ToSchema, IntoParams)]
-pub struct TokenResponse {
-    access_token: String,
-    token_type: String,
-    expires_in: u64,
-    refresh_token: String,
-    scope: String,
+#[utoipa::path(
+    get,
+    path = "/account/link",
+    params(ProviderParams),
+    summary = "Link another identity provider to the own account",
+    description = r#"Redirects the user to the login page of the provider, the callback links the identity to the logged in user, who can log in through it afterwards.
+Requires a login session, API tokens are refused. The callback returns an error if the identity belongs to another user."#,
+    responses(
+        (status = 303, description = "Redirecting to the login page")
+    ),
+    tag = "Accounts"
+)]
+#[get("/account/link?<params..>")]
+pub async fn link_identity(
+    params: ProviderParams,
+    _user: super::SessionUser,
+    cookies: &CookieJar<'_>,
+    idp: &State<IdentityProviders>,
+) -> Result<Redirect> {
+    start_oauth_login(cookies, idp.inner(), Some(&params.provider), None, true).await
 }
 
-#[derive(Debug, Serialize, Deserialize, ToSchema, IntoParams)]
-pub struct DiscordUser {
-    id: String,
-    username: String,
-    discriminator: String,
-    avatar: Option<String>,
+#[derive(FromForm, ToSchema, IntoParams)]
+pub struct CodeParams {
+    code: String,
+    state: String,
 }
 
 #[utoipa::path(
     get,
     path = "/account/oauth/callback",
     params(CodeParams),
-    summary = "Handle OAuth callback from Discord",
-    description = r#"Enters the OAuth flow, exchanging the code for a token. Retrieves user info from Discord and creates/updates a user in the DB."#,
+    summary = "Handle the OAuth callback of an identity provider",
+    description = r#"Enters the OAuth flow, exchanging the code for the user at the provider the login was started with. Creates or updates the user linked to the identity in the DB.
+New users are only accepted while the server is locked if they logged in with a valid invitation code, which is redeemed. Existing users redeem the code as well, an invalid one is ignored for them.
+Returns an error if the state does not match the login."#,
     responses(
-        (status = 307, description = "Redirects to the base URL after processing the OAuth callback")
+        (status = 303, description = "Redirects to the base URL after processing the OAuth callback")
     ),
-    security(("bearer_auth" = [])),
     tag = "Accounts"
 )]
 #[get("/account/oauth/callback?<params..>")]
+#[allow(clippy::too_many_arguments)]
 pub async fn callback(
     params: CodeParams,
     cookies: &CookieJar<'_>,
+    idp: &State<IdentityProviders>,
     usr_rep: &State<UserRepository>,
+    idn_rep: &State<IdentityRepository>,
+    ivt_rep: &State<InviteRepository>,
+    set_rep: &State<SettingRepository>,
+    ses_rep: &State<SessionRepository>,
+    ses_cfg: &State<SessionConfig>,
+    user: Option<super::AuthenticatedUser>,
+    user_agent: super::UserAgent,
 ) -> Result<Redirect> {
-    let client_id = env::var("DISCORD_CLIENT_ID").expect("DISCORD_CLIENT_ID not set");
-    let client_secret = env::var("DISCORD_CLIENT_SECRET").expect("DISCORD_CLIENT_SECRET not set");
-    let redirect_uri = env::var("DISCORD_REDIRECT_URI").expect("DISCORD_REDIRECT_URI not set");
-
-    let token_url = "https://discord.com/api/oauth2/token";
-    let client = Client::new();
-    let params = [
-        ("client_id", client_id.as_str()),
-        ("client_secret", client_secret.as_str()),
-        ("grant_type", "authorization_code"),
-        ("code", params.code.as_str()),
-        ("redirect_uri", redirect_uri.as_str()),
-    ];
-
-    // Exchange Authorization Codes for Token
-    let token_response = client
-        .post(token_url)
-        .form(&params)
-        .send()
-        .await?
-        .json::<TokenResponse>()
-        .await?;
-
-    // Get Userinformation with Access Token
-    let user_response = client
-        .get("https://discord.com/api/users/@me")
-        .header(
-            "Authorization",
-            format!("Bearer {}", token_response.access_token),
-        )
-        .send()
-        .await?
-        .json::<DiscordUser>()
+    let login = cookies
+        .get_private("oauth_login")
+        .and_then(|cookie| from_str::<OAuthLogin>(cookie.value()).ok());
+    cookies.remove_private("oauth_login");
+    let Some(login) = login.filter(|login| login.state == params.state) else {
+        return Err(create_error("the login expired, please try again"));
+    };
+    let Some(provider) = idp.get(Some(&login.provider)) else {
+        return Err(create_error("unknown identity provider"));
+    };
+    let provider_user = provider
+        .fetch_user(&params.code, &login.pkce_verifier)
         .await?;
 
-    // revoke refresh token
-    let revoke_url = "https://discord.com/api/oauth2/token/revoke";
-    let params = [
-        ("client_id", client_id.as_str()),
-        ("client_secret", client_secret.as_str()),
-        ("token", token_response.refresh_token.as_str()),
-        ("token_type_hint", "access_token"),
-    ];
-
-    let _response = client.post(revoke_url).form(&params).send().await?;
-
-    let avatar_unpacked = user_response.avatar.unwrap_or("".to_string());
-    let has_user = usr_rep.user_exists(&user_response.id.clone()).await?;
-    
-    if !has_user {
-        if locked_status!() {
-            return Err(create_error("No new Users allowed"));
-        }
-        let _res = usr_rep
-            .create_user(&user_response.id, &user_response.username, &avatar_unpacked)
+    if login.link {
+        let Some(user) = user else {
+            return Err(create_error(ACCESS_DENIAL_MESSAGE));
+        };
+        idn_rep
+            .link_identity(provider.id(), &provider_user.subject, &user.user_id)
             .await?;
-    } else {
-        let user = usr_rep.get_user(&user_response.id.clone()).await?;
-        if user.name != user_response.username || user.avatar != avatar_unpacked {
-            usr_rep
-                .update_user(
-                    &user_response.id,
-                    &user_response.username,
-                    &avatar_unpacked,
-                    user.dm,
+        return Ok(after_login_redirect());
+    }
+
+    let user_id = match idn_rep
+        .get_user_uuid(provider.id(), &provider_user.subject)
+        .await?
+    {
+        None => {
+            if !registration_allowed(set_rep, ivt_rep, login.invite.as_deref()).await? {
+                return Err(create_error("No new Users allowed"));
+            }
+            idn_rep
+                .register(
+                    provider.id(),
+                    &provider_user.subject,
+                    provider_user.user_uuid.as_deref(),
+                    &provider_user.name,
+                    &provider_user.avatar,
+                    login.invite.as_deref(),
                 )
-                .await?;
+                .await?
+                .uuid
         }
-    }
-    // Save Cookie
-    let new_cookie = Cookie::build(("user_id", user_response.id.clone())).http_only(false);
-    cookies.add_private(new_cookie);
+        Some(user_id) => {
+            if let Some(code) = &login.invite {
+                // A stale invite must not keep an existing user from logging in
+                if let Err(e) = ivt_rep.redeem_invite(code, &user_id).await {
+                    println!("Ignoring invite {} of user {}: {}", code, user_id, e);
+                }
+            }
+            let user = usr_rep.get_user(&user_id).await?;
+            if user.name != provider_user.name || user.avatar != provider_user.avatar {
+                usr_rep
+                    .update_user(
+                        &user_id,
+                        &provider_user.name,
+                        &provider_user.avatar,
+                        user.dm,
+                    )
+                    .await?;
+            }
+            user_id
+        }
+    };
+    start_session(cookies, ses_rep, ses_cfg, &user_id, &user_agent).await?;
+    Ok(after_login_redirect())
+}
 
+/// Redirects to the frontend after a login.
+fn after_login_redirect() -> Redirect {
     #[cfg(feature = "dev")]
     {
-        return Ok(Redirect::to(uri!("http://localhost:5173")));
+        Redirect::to(uri!("http://localhost:5173"))
     }
     #[cfg(not(feature = "dev"))]
     {
-        return Ok(Redirect::to(uri!("/")));
+        Redirect::to(uri!("/"))
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/account/providers",
+    summary = "Retrieve the identity providers",
+    description = r#"Returns the identity providers users can log in through, the first one is used by default."#,
+    responses(
+        (status = 200, description = "The configured identity providers", body = Vec<ProviderInfo>)
+    ),
+    tag = "Accounts"
+)]
+#[get("/account/providers")]
+pub async fn get_providers(idp: &State<IdentityProviders>) -> Json<Vec<ProviderInfo>> {
+    Json(idp.list())
+}
+
+#[utoipa::path(
+    get,
+    path = "/account/identities",
+    summary = "Retrieve the own identities",
+    description = r#"Returns the identity providers linked to the own account, the oldest first. Requires authentication."#,
+    responses(
+        (status = 200, description = "The linked identities", body = Vec<UserIdentity>)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Accounts"
+)]
+#[get("/account/identities")]
+pub async fn get_identities(
+    user: super::AuthenticatedUser,
+    idn_rep: &State<IdentityRepository>,
+) -> Result<Json<Vec<UserIdentity>>> {
+    Ok(Json(idn_rep.get_identities(&user.user_id).await?))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/account/unlink",
+    params(ProviderParams),
+    summary = "Unlink an identity provider from the own account",
+    description = r#"Removes the identity of the provider from the own account.
+Requires a login session, API tokens are refused. Returns an error if it is the last way to log in, a local account counts as one."#,
+    responses(
+        (status = 204, description = "Identity unlinked successfully")
+    ),
+    tag = "Accounts"
+)]
+#[patch("/account/unlink?<params..>")]
+pub async fn unlink_identity(
+    params: ProviderParams,
+    user: super::SessionUser,
+    idn_rep: &State<IdentityRepository>,
+) -> Result<Status> {
+    idn_rep
+        .unlink_identity(&params.provider, &user.user_id)
+        .await?;
+    Ok(Status::NoContent)
+}
+
 #[allow(non_snake_case)]
 #[derive(Debug, Serialize, Deserialize, ToSchema, IntoParams)]
 pub struct InfoResponse {
@@ -247,9 +380,9 @@ pub struct InfoResponse {
 )]
 #[get("/account/info")]
 pub async fn account_info(user: super::AuthenticatedUser) -> Json<InfoResponse> {
-    return Json(InfoResponse {
+    Json(InfoResponse {
         userUUID: user.user_id,
-    });
+    })
 }
 
 #[allow(non_snake_case)]
@@ -262,24 +395,24 @@ pub struct LoggedInResponse {
     get,
     path = "/account/isLoggedIn",
     summary = "Check if a user is logged in",
-    description = r#"Returns `true` if the user is currently logged in, or `false` otherwise."#,
+    description = r#"Returns `true` if the user has a session that has not expired or sent a valid API token, or `false` otherwise."#,
     responses(
         (status = 200, description = "Indicates whether the user is logged in", body = LoggedInResponse)
     ),
     tag = "Accounts"
 )]
 #[get("/account/isLoggedIn")]
-pub async fn user_logged_in(cookies: &CookieJar<'_>) -> Json<LoggedInResponse> {
-    return Json(LoggedInResponse {
-        loggedIn: cookies.get_private("user_id") != None,
-    });
+pub async fn user_logged_in(user: Option<super::AuthenticatedUser>) -> Json<LoggedInResponse> {
+    Json(LoggedInResponse {
+        loggedIn: user.is_some(),
+    })
 }
 
 #[utoipa::path(
     get,
     path = "/account/logout",
     summary = "Log out the current user",
-    description = r#"Removes the login cookie for the user. If no cookie is set, returns `BadRequest`."#,
+    description = r#"Ends the current session and removes its cookie, see `/session/revokeAll` to log out everywhere. If no cookie is set, returns `BadRequest`."#,
     responses(
         (status = 204, description = "User successfully logged out"),
         (status = 400, description = "No user was logged in")
@@ -287,13 +420,23 @@ pub async fn user_logged_in(cookies: &CookieJar<'_>) -> Json<LoggedInResponse> {
     tag = "Accounts"
 )]
 #[get("/account/logout")]
-pub async fn logout(cookies: &CookieJar<'_>) -> Status {
-    if let Some(_cookie) = cookies.get_private("user_id") {
-        cookies.remove_private("user_id");
-        Status::NoContent
-    } else {
-        Status::BadRequest
+pub async fn logout(
+    cookies: &CookieJar<'_>,
+    user: Option<super::AuthenticatedUser>,
+    ses_rep: &State<SessionRepository>,
+) -> Result<Status> {
+    if cookies.get_private(SESSION_COOKIE).is_none() {
+        return Ok(Status::BadRequest);
     }
+    if let Some(super::AuthenticatedUser {
+        user_id,
+        session_uuid: Some(session_uuid),
+    }) = user
+    {
+        ses_rep.revoke_session(&session_uuid, &user_id).await?;
+    }
+    cookies.remove_private(SESSION_COOKIE);
+    Ok(Status::NoContent)
 }
 
 #[allow(non_snake_case)]
@@ -313,17 +456,18 @@ pub struct IsLockedResponse {
     tag = "Accounts"
 )]
 #[get("/account/isLocked")]
-pub async fn is_locked() -> Json<IsLockedResponse> {
-    Json(IsLockedResponse {
-        isLocked: locked_status!(),
-    })
+pub async fn is_locked(set_rep: &State<SettingRepository>) -> Result<Json<IsLockedResponse>> {
+    Ok(Json(IsLockedResponse {
+        isLocked: set_rep.is_locked().await?,
+    }))
 }
 
 #[utoipa::path(
     patch,
     path = "/account/toggleLock",
     summary = "Toggle the lock status of the system",
-    description = r#"Only a DM can toggle the system lock. Returns an error if the user is not a DM."#,
+    description = r#"Only a DM can toggle the system lock. Returns an error if the user is not a DM.
+Prefer `/account/setLock` and `/account/unsetLock`, which do not depend on the current state."#,
     responses(
         (status = 204, description = "System lock toggled successfully"),
         (status = 418, description = "User is not a Dungeon Master")
@@ -335,38 +479,228 @@ pub async fn is_locked() -> Json<IsLockedResponse> {
 pub async fn toggle_lock(
     user: super::AuthenticatedUser,
     usr_rep: &State<UserRepository>,
+    set_rep: &State<SettingRepository>,
+) -> Result<Status> {
+    if !user_is_dm(usr_rep.inner(), user.user_id).await? {
+        return Ok(Status::ImATeapot);
+    }
+    set_rep.toggle_locked().await?;
+    Ok(Status::NoContent)
+}
+
+#[utoipa::path(
+    patch,
+    path = "/account/setLock",
+    summary = "Lock the system",
+    description = r#"Only a DM can lock the system, new users can then only register with an invite of a server DM. Returns an error if the user is not a DM."#,
+    responses(
+        (status = 204, description = "System locked successfully"),
+        (status = 418, description = "User is not a Dungeon Master")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Accounts"
+)]
+#[patch("/account/setLock")]
+pub async fn set_lock(
+    user: super::AuthenticatedUser,
+    usr_rep: &State<UserRepository>,
+    set_rep: &State<SettingRepository>,
 ) -> Result<Status> {
     if !user_is_dm(usr_rep.inner(), user.user_id).await? {
         return Ok(Status::ImATeapot);
     }
-    lock_toggle!();
+    set_rep.set_locked(true).await?;
+    Ok(Status::NoContent)
+}
+
+#[utoipa::path(
+    patch,
+    path = "/account/unsetLock",
+    summary = "Unlock the system",
+    description = r#"Only a DM can unlock the system, everyone can then register. Returns an error if the user is not a DM."#,
+    responses(
+        (status = 204, description = "System unlocked successfully"),
+        (status = 418, description = "User is not a Dungeon Master")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Accounts"
+)]
+#[patch("/account/unsetLock")]
+pub async fn unset_lock(
+    user: super::AuthenticatedUser,
+    usr_rep: &State<UserRepository>,
+    set_rep: &State<SettingRepository>,
+) -> Result<Status> {
+    if !user_is_dm(usr_rep.inner(), user.user_id).await? {
+        return Ok(Status::ImATeapot);
+    }
+    set_rep.set_locked(false).await?;
+    Ok(Status::NoContent)
+}
+
+#[utoipa::path(
+    patch,
+    path = "/account/grantDm",
+    params(AccountUUIDParams),
+    summary = "Make a user a server DM",
+    description = r#"Only a DM can grant server DM status. The change is recorded. Returns an error if the user is not a DM."#,
+    responses(
+        (status = 204, description = "DM status granted successfully"),
+        (status = 418, description = "User is not a Dungeon Master")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Accounts"
+)]
+#[patch("/account/grantDm?<params..>")]
+pub async fn grant_dm(
+    params: AccountUUIDParams,
+    user: super::AuthenticatedUser,
+    usr_rep: &State<UserRepository>,
+) -> Result<Status> {
+    if !user_is_dm(usr_rep.inner(), user.user_id.clone()).await? {
+        return Ok(Status::ImATeapot);
+    }
+    usr_rep
+        .set_dm(&params.account_uuid, true, &user.user_id)
+        .await?;
+    Ok(Status::NoContent)
+}
+
+#[utoipa::path(
+    patch,
+    path = "/account/revokeDm",
+    params(AccountUUIDParams),
+    summary = "Revoke the server DM status of a user",
+    description = r#"Only a DM can revoke server DM status, the last DM can not be revoked. The change is recorded. Returns an error if the user is not a DM."#,
+    responses(
+        (status = 204, description = "DM status revoked successfully"),
+        (status = 418, description = "User is not a Dungeon Master")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Accounts"
+)]
+#[patch("/account/revokeDm?<params..>")]
+pub async fn revoke_dm(
+    params: AccountUUIDParams,
+    user: super::AuthenticatedUser,
+    usr_rep: &State<UserRepository>,
+) -> Result<Status> {
+    if !user_is_dm(usr_rep.inner(), user.user_id.clone()).await? {
+        return Ok(Status::ImATeapot);
+    }
+    usr_rep
+        .set_dm(&params.account_uuid, false, &user.user_id)
+        .await?;
+    Ok(Status::NoContent)
+}
+
+#[utoipa::path(
+    get,
+    path = "/account/roleChanges",
+    summary = "Retrieve the server DM role changes",
+    description = r#"Returns who granted or revoked server DM status, the newest first. Returns an error if the user is not a DM."#,
+    responses(
+        (status = 200, description = "The server role changes", body = Vec<RoleChange>)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Accounts"
+)]
+#[get("/account/roleChanges")]
+pub async fn get_role_changes(
+    user: super::AuthenticatedUser,
+    usr_rep: &State<UserRepository>,
+    rch_rep: &State<RoleChangeRepository>,
+) -> Result<Json<Vec<RoleChange>>> {
+    if !user_is_dm(usr_rep.inner(), user.user_id).await? {
+        return Err(create_error(ACCESS_DENIAL_MESSAGE));
+    }
+    Ok(Json(rch_rep.get_role_changes(None).await?))
+}
+
+#[derive(FromForm, ToSchema, IntoParams)]
+pub struct AccountDeleteParams {
+    account_uuid: String,
+    /// User who becomes the owner of the inventories of the deleted user, they are deleted with the user if not set
+    reassign_to: Option<String>,
+}
+
+#[utoipa::path(
+    delete,
+    path = "/account/delete",
+    params(AccountDeleteParams),
+    summary = "Delete a user",
+    description = r#"Deletes a user together with their shares and campaign memberships. Owned inventories are deleted as well unless `reassign_to` names a user,
+who then becomes their owner and has to be a member of the campaign of every owned inventory.
+Users can delete themselves, which also logs them out, only a DM can delete other users. Requires a login session, API tokens are refused.
+Returns an error if access is denied or the user is the last DM of the server or a campaign."#,
+    responses(
+        (status = 204, description = "User deleted successfully"),
+        (status = 404, description = "User does not exist")
+    ),
+    tag = "Accounts"
+)]
+#[delete("/account/delete?<params..>")]
+pub async fn delete_account(
+    params: AccountDeleteParams,
+    user: super::SessionUser,
+    cookies: &CookieJar<'_>,
+    usr_rep: &State<UserRepository>,
+) -> Result<Status> {
+    let own_account = params.account_uuid == user.user_id;
+    if !own_account && !user_is_dm(usr_rep.inner(), user.user_id.clone()).await? {
+        return Err(create_error(ACCESS_DENIAL_MESSAGE));
+    }
+    let deleted = usr_rep
+        .delete_user(&params.account_uuid, params.reassign_to.as_deref())
+        .await?;
+    if deleted == 0 {
+        return Ok(Status::NotFound);
+    }
+    if own_account {
+        cookies.remove_private(SESSION_COOKIE);
+    }
     Ok(Status::NoContent)
 }
 
 #[derive(OpenApi)]
 #[openapi(
+    modifiers(&BearerAuthAddon),
     paths(
         get_accounts,
         is_account_dm,
         login,
+        link_identity,
         callback,
+        get_providers,
+        get_identities,
+        unlink_identity,
         account_info,
         user_logged_in,
         logout,
         is_locked,
-        toggle_lock
+        toggle_lock,
+        set_lock,
+        unset_lock,
+        grant_dm,
+        revoke_dm,
+        get_role_changes,
+        delete_account
     ),
     components(
         schemas(
             DMResponse,
             AccountResponse,
             LoggedInResponse,
-            TokenResponse,
             InfoResponse,
-            DiscordUser,
             CodeParams,
+            LoginParams,
+            ProviderParams,
+            ProviderInfo,
+            UserIdentity,
             AccountUUIDParams,
-            IsLockedResponse
+            AccountDeleteParams,
+            IsLockedResponse,
+            RoleChange
         )
     ),
     tags(
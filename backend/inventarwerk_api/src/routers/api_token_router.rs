@@ -0,0 +1,144 @@
+use repos::model::ApiToken;
+use repos::repos::api_token_repository::ApiTokenRepository;
+use rocket::http::Status;
+use rocket::serde::{Deserialize, Serialize};
+use rocket::{form::FromForm, serde::json::Json, State};
+use rocket_errors::anyhow::Result;
+
+use utoipa::IntoParams;
+use utoipa::OpenApi;
+use utoipa::ToSchema;
+
+use super::router_utility::hash_token;
+use super::{create_error, BearerAuthAddon};
+use crate::identity_provider::random_token;
+
+/// Prefix of every API token, makes leaked tokens easy to spot.
+const API_TOKEN_PREFIX: &str = "iw_";
+
+#[derive(FromForm, ToSchema, IntoParams)]
+pub struct ApiTokenCreateParams {
+    /// Name to recognize the token by, e.g. the script using it
+    name: String,
+    /// Restricts the token to `GET` requests, defaults to false
+    read_only: Option<bool>,
+    /// Days until the token expires, never if not set
+    expires_in_days: Option<i32>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiTokenCreateResponse {
+    /// Sent as `Authorization: Bearer <token>`, it is not shown again
+    token: String,
+    api_token: ApiToken,
+}
+
+#[utoipa::path(
+    put,
+    path = "/apiToken",
+    summary = "Create an API token",
+    description = r#"Creates a token that authenticates requests as the own user when sent as `Authorization: Bearer <token>`, e.g. for bots and scripts.
+Read-only tokens can only be used for `GET` requests. Only a hash is stored, the token is returned once.
+Requires a login session, API tokens are refused."#,
+    params(ApiTokenCreateParams),
+    responses(
+        (status = 200, description = "The created token", body = ApiTokenCreateResponse)
+    ),
+    tag = "API Tokens"
+)]
+#[put("/apiToken?<params..>")]
+pub async fn create_api_token(
+    params: ApiTokenCreateParams,
+    user: super::SessionUser,
+    tok_rep: &State<ApiTokenRepository>,
+) -> Result<Json<ApiTokenCreateResponse>> {
+    if params.name.trim().is_empty() {
+        return Err(create_error("the name must not be empty"));
+    }
+    let token = format!("{}{}", API_TOKEN_PREFIX, random_token()?);
+    let api_token = tok_rep
+        .create_token(
+            &user.user_id,
+            params.name.trim(),
+            &hash_token(&token),
+            params.read_only.unwrap_or(false),
+            params.expires_in_days,
+        )
+        .await?;
+    Ok(Json(ApiTokenCreateResponse { token, api_token }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/apiToken/all",
+    summary = "Retrieve the own API tokens",
+    description = r#"Returns the own API tokens without the tokens themselves, the newest first. Requires authentication."#,
+    responses(
+        (status = 200, description = "The API tokens", body = Vec<ApiToken>)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "API Tokens"
+)]
+#[get("/apiToken/all")]
+pub async fn get_api_tokens(
+    user: super::AuthenticatedUser,
+    tok_rep: &State<ApiTokenRepository>,
+) -> Result<Json<Vec<ApiToken>>> {
+    Ok(Json(tok_rep.get_tokens(&user.user_id).await?))
+}
+
+#[derive(FromForm, ToSchema, IntoParams)]
+pub struct ApiTokenUUIDParams {
+    token_uuid: String,
+}
+
+#[utoipa::path(
+    delete,
+    path = "/apiToken/revoke",
+    summary = "Revoke an API token",
+    description = r#"Deletes an own API token, requests with it are rejected afterwards. Requires a login session, API tokens are refused."#,
+    params(ApiTokenUUIDParams),
+    responses(
+        (status = 204, description = "Token revoked successfully"),
+        (status = 404, description = "The user has no such token")
+    ),
+    tag = "API Tokens"
+)]
+#[delete("/apiToken/revoke?<params..>")]
+pub async fn revoke_api_token(
+    params: ApiTokenUUIDParams,
+    user: super::SessionUser,
+    tok_rep: &State<ApiTokenRepository>,
+) -> Result<Status> {
+    if tok_rep
+        .revoke_token(&params.token_uuid, &user.user_id)
+        .await?
+        == 0
+    {
+        return Ok(Status::NotFound);
+    }
+    Ok(Status::NoContent)
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    modifiers(&BearerAuthAddon),
+    paths(
+        create_api_token,
+        get_api_tokens,
+        revoke_api_token
+    ),
+    components(
+        schemas(
+            ApiTokenCreateParams,
+            ApiTokenCreateResponse,
+            ApiTokenUUIDParams,
+            ApiToken
+        )
+    ),
+    tags(
+        (name = "API Tokens", description = "Endpoints for managing personal API tokens")
+    )
+)]
+pub struct ApiTokenApiDoc;
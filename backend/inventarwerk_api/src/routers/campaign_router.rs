@@ -0,0 +1,446 @@
+use repos::model::{Campaign, CampaignMember, InventoryChangeKind, RoleChange};
+use repos::repos::campaign_repository::CampaignRepository;
+use repos::repos::change_log_repository::ChangeLogRepository;
+use repos::repos::inventory_repository::InventoryRepository;
+use repos::repos::role_change_repository::RoleChangeRepository;
+use rocket::http::Status;
+use rocket::{form::FromForm, serde::json::Json, State};
+use rocket_errors::anyhow::Result;
+
+use utoipa::IntoParams;
+use utoipa::OpenApi;
+use utoipa::ToSchema;
+
+use super::inventory_router::get_shares_value;
+use super::router_utility::{
+    user_is_dm_of_campaign, user_is_member_of_campaign, ACCESS_DENIAL_MESSAGE,
+};
+use super::{create_error, BearerAuthAddon};
+
+#[utoipa::path(
+    get,
+    path = "/campaign/all",
+    summary = "Retrieve the user's campaigns",
+    description = r#"Returns all campaigns the user is a player or DM of.
+Requires authentication."#,
+    responses(
+        (status = 200, description = "The campaigns of the user", body = Vec<Campaign>)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Campaigns"
+)]
+#[get("/campaign/all")]
+pub async fn get_campaigns(
+    user: super::AuthenticatedUser,
+    cmp_rep: &State<CampaignRepository>,
+) -> Result<Json<Vec<Campaign>>> {
+    Ok(Json(cmp_rep.get_campaigns_of_user(&user.user_id).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/campaign/open",
+    summary = "Retrieve all open campaigns",
+    description = r#"Returns all campaigns that are not locked and can be joined.
+Requires authentication."#,
+    responses(
+        (status = 200, description = "The open campaigns", body = Vec<Campaign>)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Campaigns"
+)]
+#[get("/campaign/open")]
+pub async fn get_open_campaigns(
+    _user: super::AuthenticatedUser,
+    cmp_rep: &State<CampaignRepository>,
+) -> Result<Json<Vec<Campaign>>> {
+    Ok(Json(cmp_rep.get_open_campaigns().await?))
+}
+
+#[derive(FromForm, ToSchema, IntoParams)]
+pub struct CampaignCreateParams {
+    name: String,
+}
+
+#[utoipa::path(
+    put,
+    path = "/campaign",
+    summary = "Create a campaign",
+    description = r#"Creates a locked campaign with the default currencies. The creator becomes its DM.
+Requires authentication."#,
+    params(CampaignCreateParams),
+    responses(
+        (status = 200, description = "The created campaign", body = Campaign)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Campaigns"
+)]
+#[put("/campaign?<params..>")]
+pub async fn create_campaign(
+    params: CampaignCreateParams,
+    user: super::AuthenticatedUser,
+    cmp_rep: &State<CampaignRepository>,
+) -> Result<Json<Campaign>> {
+    Ok(Json(
+        cmp_rep.create_campaign(&params.name, &user.user_id).await?,
+    ))
+}
+
+#[derive(FromForm, ToSchema, IntoParams)]
+pub struct CampaignModifyParams {
+    campaign_uuid: String,
+    name: Option<String>,
+    /// Locked campaigns can not be joined by users themselves
+    locked: Option<bool>,
+}
+
+#[utoipa::path(
+    patch,
+    path = "/campaign/modify",
+    summary = "Modify a campaign",
+    description = r#"Changes the name or the lock state of a campaign.
+Requires authentication and DM privileges in the campaign. Returns an error if the user is not a DM of the campaign."#,
+    params(CampaignModifyParams),
+    responses(
+        (status = 204, description = "Campaign successfully modified")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Campaigns"
+)]
+#[patch("/campaign/modify?<params..>")]
+pub async fn modify_campaign(
+    params: CampaignModifyParams,
+    user: super::AuthenticatedUser,
+    cmp_rep: &State<CampaignRepository>,
+) -> Result<Status> {
+    if !user_is_dm_of_campaign(cmp_rep.inner(), &params.campaign_uuid, &user.user_id).await? {
+        return Err(create_error(ACCESS_DENIAL_MESSAGE));
+    }
+    cmp_rep
+        .update_campaign(&params.campaign_uuid, params.name.as_deref(), params.locked)
+        .await?;
+    Ok(Status::NoContent)
+}
+
+#[derive(FromForm, ToSchema, IntoParams)]
+pub struct CampaignUUIDParams {
+    campaign_uuid: String,
+}
+
+#[utoipa::path(
+    delete,
+    path = "/campaign/delete",
+    summary = "Delete a campaign",
+    description = r#"Deletes a campaign together with its inventories, presets and currencies.
+Requires authentication and DM privileges in the campaign. Returns an error if the user is not a DM of the campaign."#,
+    params(CampaignUUIDParams),
+    responses(
+        (status = 204, description = "Campaign successfully deleted")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Campaigns"
+)]
+#[delete("/campaign/delete?<params..>")]
+pub async fn delete_campaign(
+    params: CampaignUUIDParams,
+    user: super::AuthenticatedUser,
+    cmp_rep: &State<CampaignRepository>,
+) -> Result<Status> {
+    if !user_is_dm_of_campaign(cmp_rep.inner(), &params.campaign_uuid, &user.user_id).await? {
+        return Err(create_error(ACCESS_DENIAL_MESSAGE));
+    }
+    cmp_rep.delete_campaign(&params.campaign_uuid).await?;
+    Ok(Status::NoContent)
+}
+
+#[utoipa::path(
+    get,
+    path = "/campaign/members",
+    summary = "Retrieve the members of a campaign",
+    description = r#"Returns all players and DMs of a campaign, DMs first.
+Requires authentication and membership of the campaign."#,
+    params(CampaignUUIDParams),
+    responses(
+        (status = 200, description = "The members of the campaign", body = Vec<CampaignMember>)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Campaigns"
+)]
+#[get("/campaign/members?<params..>")]
+pub async fn get_campaign_members(
+    params: CampaignUUIDParams,
+    user: super::AuthenticatedUser,
+    cmp_rep: &State<CampaignRepository>,
+) -> Result<Json<Vec<CampaignMember>>> {
+    if !user_is_member_of_campaign(cmp_rep.inner(), &params.campaign_uuid, &user.user_id).await? {
+        return Err(create_error(ACCESS_DENIAL_MESSAGE));
+    }
+    Ok(Json(cmp_rep.get_members(&params.campaign_uuid).await?))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/campaign/join",
+    summary = "Join a campaign",
+    description = r#"Adds the user as a player to a campaign.
+Requires authentication. Returns an error if the campaign is locked."#,
+    params(CampaignUUIDParams),
+    responses(
+        (status = 204, description = "Campaign successfully joined")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Campaigns"
+)]
+#[patch("/campaign/join?<params..>")]
+pub async fn join_campaign(
+    params: CampaignUUIDParams,
+    user: super::AuthenticatedUser,
+    cmp_rep: &State<CampaignRepository>,
+) -> Result<Status> {
+    if cmp_rep.get_campaign(&params.campaign_uuid).await?.locked {
+        return Err(create_error("the campaign is locked"));
+    }
+    cmp_rep
+        .add_member(&params.campaign_uuid, &user.user_id)
+        .await?;
+    Ok(Status::NoContent)
+}
+
+#[derive(FromForm, ToSchema, IntoParams)]
+pub struct CampaignMemberParams {
+    campaign_uuid: String,
+    user_uuid: String,
+}
+
+#[utoipa::path(
+    patch,
+    path = "/campaign/addMember",
+    summary = "Add a player to a campaign",
+    description = r#"Adds a user as a player to a campaign, also while it is locked.
+Requires authentication and DM privileges in the campaign. Returns an error if the user is not a DM of the campaign."#,
+    params(CampaignMemberParams),
+    responses(
+        (status = 204, description = "Player successfully added")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Campaigns"
+)]
+#[patch("/campaign/addMember?<params..>")]
+pub async fn add_campaign_member(
+    params: CampaignMemberParams,
+    user: super::AuthenticatedUser,
+    cmp_rep: &State<CampaignRepository>,
+) -> Result<Status> {
+    if !user_is_dm_of_campaign(cmp_rep.inner(), &params.campaign_uuid, &user.user_id).await? {
+        return Err(create_error(ACCESS_DENIAL_MESSAGE));
+    }
+    cmp_rep
+        .add_member(&params.campaign_uuid, &params.user_uuid)
+        .await?;
+    Ok(Status::NoContent)
+}
+
+#[utoipa::path(
+    patch,
+    path = "/campaign/removeMember",
+    summary = "Remove a member from a campaign",
+    description = r#"Removes a member from a campaign and revokes their shares on inventories of the campaign they do not own.
+Requires authentication. DMs of the campaign can remove anyone, everyone else only themselves. Returns an error if the last DM would leave."#,
+    params(CampaignMemberParams),
+    responses(
+        (status = 204, description = "Member successfully removed")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Campaigns"
+)]
+#[patch("/campaign/removeMember?<params..>")]
+pub async fn remove_campaign_member(
+    params: CampaignMemberParams,
+    user: super::AuthenticatedUser,
+    cmp_rep: &State<CampaignRepository>,
+    inv_rep: &State<InventoryRepository>,
+    chg_rep: &State<ChangeLogRepository>,
+) -> Result<Status> {
+    if params.user_uuid != user.user_id
+        && !user_is_dm_of_campaign(cmp_rep.inner(), &params.campaign_uuid, &user.user_id).await?
+    {
+        return Err(create_error(ACCESS_DENIAL_MESSAGE));
+    }
+    let old_shares = get_campaign_shares(inv_rep.inner(), &params.campaign_uuid).await?;
+    cmp_rep
+        .remove_member(&params.campaign_uuid, &params.user_uuid)
+        .await?;
+    report_changed_campaign_shares(inv_rep.inner(), chg_rep.inner(), &user.user_id, old_shares)
+        .await?;
+    Ok(Status::NoContent)
+}
+
+#[utoipa::path(
+    patch,
+    path = "/campaign/grantDm",
+    summary = "Make a member a DM of a campaign",
+    description = r#"Grants DM status to a member, who becomes reader and writer of all inventories of the campaign. The change is recorded.
+Requires authentication and DM privileges in the campaign. Returns an error if the user is not a DM of the campaign or the user to promote is not a member."#,
+    params(CampaignMemberParams),
+    responses(
+        (status = 204, description = "DM status successfully granted")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Campaigns"
+)]
+#[patch("/campaign/grantDm?<params..>")]
+pub async fn grant_campaign_dm(
+    params: CampaignMemberParams,
+    user: super::AuthenticatedUser,
+    cmp_rep: &State<CampaignRepository>,
+    inv_rep: &State<InventoryRepository>,
+    chg_rep: &State<ChangeLogRepository>,
+) -> Result<Status> {
+    set_campaign_dm(params, user, cmp_rep, inv_rep, chg_rep, true).await
+}
+
+#[utoipa::path(
+    patch,
+    path = "/campaign/revokeDm",
+    summary = "Revoke the DM status of a member",
+    description = r#"Revokes the DM status of a member, who loses their shares on inventories of the campaign they do not own. The change is recorded.
+Requires authentication and DM privileges in the campaign. Returns an error if the user is not a DM of the campaign or the member is its last DM."#,
+    params(CampaignMemberParams),
+    responses(
+        (status = 204, description = "DM status successfully revoked")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Campaigns"
+)]
+#[patch("/campaign/revokeDm?<params..>")]
+pub async fn revoke_campaign_dm(
+    params: CampaignMemberParams,
+    user: super::AuthenticatedUser,
+    cmp_rep: &State<CampaignRepository>,
+    inv_rep: &State<InventoryRepository>,
+    chg_rep: &State<ChangeLogRepository>,
+) -> Result<Status> {
+    set_campaign_dm(params, user, cmp_rep, inv_rep, chg_rep, false).await
+}
+
+/// Changes the DM status of a member and reports the changed shares of the campaign's inventories.
+async fn set_campaign_dm(
+    params: CampaignMemberParams,
+    user: super::AuthenticatedUser,
+    cmp_rep: &State<CampaignRepository>,
+    inv_rep: &State<InventoryRepository>,
+    chg_rep: &State<ChangeLogRepository>,
+    dm: bool,
+) -> Result<Status> {
+    if !user_is_dm_of_campaign(cmp_rep.inner(), &params.campaign_uuid, &user.user_id).await? {
+        return Err(create_error(ACCESS_DENIAL_MESSAGE));
+    }
+    let old_shares = get_campaign_shares(inv_rep.inner(), &params.campaign_uuid).await?;
+    cmp_rep
+        .set_dm(&params.campaign_uuid, &params.user_uuid, dm, &user.user_id)
+        .await?;
+    report_changed_campaign_shares(inv_rep.inner(), chg_rep.inner(), &user.user_id, old_shares)
+        .await?;
+    Ok(Status::NoContent)
+}
+
+/// Returns the shares of every inventory of a campaign, see `report_changed_campaign_shares`.
+async fn get_campaign_shares(
+    inv_rep: &InventoryRepository,
+    campaign_uuid: &str,
+) -> Result<Vec<(String, String)>> {
+    let mut shares = Vec::new();
+    for inventory_uuid in inv_rep.get_campaign_inventory_ids(campaign_uuid).await? {
+        let value = get_shares_value(inv_rep, &inventory_uuid).await?;
+        shares.push((inventory_uuid, value));
+    }
+    Ok(shares)
+}
+
+/// Reports a share change for every inventory whose shares differ from the given ones.
+async fn report_changed_campaign_shares(
+    inv_rep: &InventoryRepository,
+    chg_rep: &ChangeLogRepository,
+    user_id: &str,
+    old_shares: Vec<(String, String)>,
+) -> Result<()> {
+    for (inventory_uuid, old_shares) in old_shares {
+        let new_shares = get_shares_value(inv_rep, &inventory_uuid).await?;
+        if new_shares != old_shares {
+            crate::report_change_on_inventory!(
+                chg_rep,
+                user_id,
+                &inventory_uuid,
+                InventoryChangeKind::SharesChanged,
+                None,
+                Some(&old_shares),
+                Some(&new_shares)
+            );
+        }
+    }
+    Ok(())
+}
+
+#[utoipa::path(
+    get,
+    path = "/campaign/roleChanges",
+    summary = "Retrieve the DM role changes of a campaign",
+    description = r#"Returns who granted or revoked DM status in the campaign, the newest first.
+Requires authentication and DM privileges in the campaign. Returns an error if the user is not a DM of the campaign."#,
+    params(CampaignUUIDParams),
+    responses(
+        (status = 200, description = "The role changes of the campaign", body = Vec<RoleChange>)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Campaigns"
+)]
+#[get("/campaign/roleChanges?<params..>")]
+pub async fn get_campaign_role_changes(
+    params: CampaignUUIDParams,
+    user: super::AuthenticatedUser,
+    cmp_rep: &State<CampaignRepository>,
+    rch_rep: &State<RoleChangeRepository>,
+) -> Result<Json<Vec<RoleChange>>> {
+    if !user_is_dm_of_campaign(cmp_rep.inner(), &params.campaign_uuid, &user.user_id).await? {
+        return Err(create_error(ACCESS_DENIAL_MESSAGE));
+    }
+    Ok(Json(
+        rch_rep
+            .get_role_changes(Some(&params.campaign_uuid))
+            .await?,
+    ))
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    modifiers(&BearerAuthAddon),
+    paths(
+        get_campaigns,
+        get_open_campaigns,
+        create_campaign,
+        modify_campaign,
+        delete_campaign,
+        get_campaign_members,
+        join_campaign,
+        add_campaign_member,
+        remove_campaign_member,
+        grant_campaign_dm,
+        revoke_campaign_dm,
+        get_campaign_role_changes
+    ),
+    components(
+        schemas(
+            CampaignCreateParams,
+            CampaignModifyParams,
+            CampaignUUIDParams,
+            CampaignMemberParams,
+            Campaign,
+            CampaignMember,
+            RoleChange
+        )
+    ),
+    tags(
+        (name = "Campaigns", description = "Endpoints for managing campaigns and their members")
+    )
+)]
+pub struct CampaignApiDoc;
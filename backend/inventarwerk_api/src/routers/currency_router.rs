@@ -0,0 +1,199 @@
+use repos::model::Currency;
+use repos::repos::campaign_repository::CampaignRepository;
+use repos::repos::currency_repository::CurrencyRepository;
+use rocket::http::Status;
+use rocket::{form::FromForm, serde::json::Json, State};
+use rocket_errors::anyhow::Result;
+
+use utoipa::IntoParams;
+use utoipa::OpenApi;
+use utoipa::ToSchema;
+
+use super::router_utility::{
+    user_is_dm_of_campaign, user_is_member_of_campaign, ACCESS_DENIAL_MESSAGE,
+};
+use super::{create_error, BearerAuthAddon};
+
+#[derive(FromForm, ToSchema, IntoParams)]
+pub struct CurrencyListParams {
+    campaign_uuid: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/currency/all",
+    summary = "Retrieve all currencies",
+    description = r#"Returns the denominations of the campaign's currency system, the most valuable first. Values are given in the smallest unit.
+Requires authentication and membership of the campaign."#,
+    params(CurrencyListParams),
+    responses(
+        (status = 200, description = "All currencies", body = Vec<Currency>)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Currencies"
+)]
+#[get("/currency/all?<params..>")]
+pub async fn get_all_currencies(
+    params: CurrencyListParams,
+    user: super::AuthenticatedUser,
+    cmp_rep: &State<CampaignRepository>,
+    cur_rep: &State<CurrencyRepository>,
+) -> Result<Json<Vec<Currency>>> {
+    if !user_is_member_of_campaign(cmp_rep.inner(), &params.campaign_uuid, &user.user_id).await? {
+        return Err(create_error(ACCESS_DENIAL_MESSAGE));
+    }
+    Ok(Json(
+        cur_rep.get_all_currencies(&params.campaign_uuid).await?,
+    ))
+}
+
+#[derive(FromForm, ToSchema, IntoParams)]
+pub struct CurrencyCreateParams {
+    campaign_uuid: String,
+    name: String,
+    /// Short name, must be unique within the campaign
+    abbreviation: String,
+    /// Worth of one coin in the smallest unit
+    value: i32,
+    /// Coins may be broken up and handed out as change automatically, defaults to true
+    auto_convert: Option<bool>,
+}
+
+#[utoipa::path(
+    put,
+    path = "/currency",
+    summary = "Create a currency",
+    description = r#"Adds a denomination to the campaign's currency system.
+Requires authentication and DM privileges in the campaign. Returns an error if the user is not a DM of the campaign."#,
+    params(CurrencyCreateParams),
+    responses(
+        (status = 200, description = "The created currency", body = Currency)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Currencies"
+)]
+#[put("/currency?<params..>")]
+pub async fn create_currency(
+    params: CurrencyCreateParams,
+    user: super::AuthenticatedUser,
+    cmp_rep: &State<CampaignRepository>,
+    cur_rep: &State<CurrencyRepository>,
+) -> Result<Json<Currency>> {
+    if !user_is_dm_of_campaign(cmp_rep.inner(), &params.campaign_uuid, &user.user_id).await? {
+        return Err(create_error(ACCESS_DENIAL_MESSAGE));
+    }
+    Ok(Json(
+        cur_rep
+            .create_currency(
+                &params.campaign_uuid,
+                &params.name,
+                &params.abbreviation,
+                params.value,
+                params.auto_convert.unwrap_or(true),
+            )
+            .await?,
+    ))
+}
+
+#[derive(FromForm, ToSchema, IntoParams)]
+pub struct CurrencyModifyParams {
+    currency_uuid: String,
+    name: Option<String>,
+    abbreviation: Option<String>,
+    /// Worth of one coin in the smallest unit
+    value: Option<i32>,
+    auto_convert: Option<bool>,
+}
+
+#[utoipa::path(
+    patch,
+    path = "/currency/modify",
+    summary = "Modify a currency",
+    description = r#"Changes the given fields of a currency. The value can only be changed while no inventory holds coins of the currency.
+Requires authentication and DM privileges in the currency's campaign. Returns an error if the user is not a DM of the campaign."#,
+    params(CurrencyModifyParams),
+    responses(
+        (status = 204, description = "Currency successfully modified")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Currencies"
+)]
+#[patch("/currency/modify?<params..>")]
+pub async fn modify_currency(
+    params: CurrencyModifyParams,
+    user: super::AuthenticatedUser,
+    cmp_rep: &State<CampaignRepository>,
+    cur_rep: &State<CurrencyRepository>,
+) -> Result<Status> {
+    let currency = cur_rep.get_currency(&params.currency_uuid).await?;
+    if !user_is_dm_of_campaign(cmp_rep.inner(), &currency.campaign_uuid, &user.user_id).await? {
+        return Err(create_error(ACCESS_DENIAL_MESSAGE));
+    }
+    cur_rep
+        .update_currency(
+            &params.currency_uuid,
+            params.name.as_deref(),
+            params.abbreviation.as_deref(),
+            params.value,
+            params.auto_convert,
+        )
+        .await?;
+    Ok(Status::NoContent)
+}
+
+#[derive(FromForm, ToSchema, IntoParams)]
+pub struct CurrencyDeleteParams {
+    currency_uuid: String,
+}
+
+#[utoipa::path(
+    delete,
+    path = "/currency/delete",
+    summary = "Delete a currency",
+    description = r#"Removes a denomination from the currency system.
+Requires authentication and DM privileges in the currency's campaign. Returns an error if the user is not a DM of the campaign or an inventory still holds coins of the currency."#,
+    params(CurrencyDeleteParams),
+    responses(
+        (status = 204, description = "Currency successfully deleted")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Currencies"
+)]
+#[delete("/currency/delete?<params..>")]
+pub async fn delete_currency(
+    params: CurrencyDeleteParams,
+    user: super::AuthenticatedUser,
+    cmp_rep: &State<CampaignRepository>,
+    cur_rep: &State<CurrencyRepository>,
+) -> Result<Status> {
+    let currency = cur_rep.get_currency(&params.currency_uuid).await?;
+    if !user_is_dm_of_campaign(cmp_rep.inner(), &currency.campaign_uuid, &user.user_id).await? {
+        return Err(create_error(ACCESS_DENIAL_MESSAGE));
+    }
+    cur_rep.delete_currency(&params.currency_uuid).await?;
+    Ok(Status::NoContent)
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    modifiers(&BearerAuthAddon),
+    paths(
+        get_all_currencies,
+        create_currency,
+        modify_currency,
+        delete_currency
+    ),
+    components(
+        schemas(
+            CurrencyListParams,
+            CurrencyCreateParams,
+            CurrencyModifyParams,
+            CurrencyDeleteParams,
+            Currency
+        )
+    ),
+    tags(
+        (name = "Currencies", description = "Endpoints for managing the currency system")
+    )
+)]
+pub struct CurrencyApiDoc;
@@ -0,0 +1,161 @@
+use repos::model::{
+    Encumbrance, EncumbranceRule, EncumbranceState, InventoryChangeKind, InventoryPermission,
+};
+use repos::repos::change_log_repository::ChangeLogRepository;
+use repos::repos::inventory_repository::InventoryRepository;
+use repos::repos::undo_repository::UndoRepository;
+use rocket::http::Status;
+use rocket::serde::json::to_string;
+use rocket::{form::FromForm, serde::json::Json, State};
+use rocket_errors::anyhow::Result;
+use std::env;
+
+use utoipa::IntoParams;
+use utoipa::OpenApi;
+use utoipa::ToSchema;
+
+use super::inventory_router::report_operation;
+use super::router_utility::{
+    user_has_permission_on_inventory, user_has_read_access_to_inventory, ACCESS_DENIAL_MESSAGE,
+};
+use super::{create_error, BearerAuthAddon};
+
+/// Reads the encumbrance rule from `ENCUMBRANCE_RULE` (`standard` or `variant`), defaults to `standard`.
+pub fn encumbrance_rule_from_env() -> EncumbranceRule {
+    match env::var("ENCUMBRANCE_RULE") {
+        Err(_) => EncumbranceRule::Standard,
+        Ok(rule) => rule.parse().unwrap_or_else(|_| {
+            panic!("ENCUMBRANCE_RULE must be standard or variant, got {}", rule)
+        }),
+    }
+}
+
+#[derive(FromForm, ToSchema, IntoParams)]
+pub struct EncumbranceParams {
+    inventory_uuid: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/inventory/encumbrance",
+    summary = "Retrieve the encumbrance of an inventory",
+    description = r#"Sums up the weight of all items (and coins, if enabled) of the inventory and compares it against its carrying capacity.
+The rule set is configured through `ENCUMBRANCE_RULE`. Capacity and state are `null` if the inventory has neither a strength nor a carrying capacity.
+Requires authentication and read access. Returns an error if access is denied."#,
+    params(EncumbranceParams),
+    responses(
+        (status = 200, description = "Carried weight and encumbrance state", body = Encumbrance)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Encumbrance"
+)]
+#[get("/inventory/encumbrance?<params..>")]
+pub async fn get_encumbrance(
+    params: EncumbranceParams,
+    user: super::AuthenticatedUser,
+    inv_rep: &State<InventoryRepository>,
+    rule: &State<EncumbranceRule>,
+) -> Result<Json<Encumbrance>> {
+    if !user_has_read_access_to_inventory(
+        inv_rep.inner(),
+        params.inventory_uuid.clone(),
+        user.user_id.clone(),
+    )
+    .await?
+    {
+        return Err(create_error(ACCESS_DENIAL_MESSAGE));
+    }
+    Ok(Json(
+        inv_rep
+            .get_encumbrance(&params.inventory_uuid, *rule.inner())
+            .await?,
+    ))
+}
+
+#[derive(FromForm, ToSchema, IntoParams)]
+pub struct EncumbranceConfigParams {
+    inventory_uuid: String,
+    /// Strength score, the carrying capacity is 15 x strength
+    strength: Option<i32>,
+    /// Overrides the carrying capacity derived from the strength
+    carrying_capacity: Option<f32>,
+    /// Count 50 coins as one pound, defaults to false
+    count_money_weight: Option<bool>,
+}
+
+#[utoipa::path(
+    patch,
+    path = "/inventory/encumbrance",
+    summary = "Configure the carrying capacity of an inventory",
+    description = r#"Replaces the encumbrance configuration of the inventory, omitted values are cleared.
+Requires authentication and write access. Returns an error if access is denied."#,
+    params(EncumbranceConfigParams),
+    responses(
+        (status = 204, description = "Configuration changed successfully")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Encumbrance"
+)]
+#[patch("/inventory/encumbrance?<params..>")]
+pub async fn configure_encumbrance(
+    params: EncumbranceConfigParams,
+    user: super::AuthenticatedUser,
+    inv_rep: &State<InventoryRepository>,
+    chg_rep: &State<ChangeLogRepository>,
+    und_rep: &State<UndoRepository>,
+) -> Result<Status> {
+    if !user_has_permission_on_inventory(
+        inv_rep.inner(),
+        &params.inventory_uuid,
+        &user.user_id,
+        InventoryPermission::EditSettings,
+    )
+    .await?
+    {
+        return Err(create_error(ACCESS_DENIAL_MESSAGE));
+    }
+    let old_inventory = inv_rep.get_raw_inventory(&params.inventory_uuid).await?;
+    inv_rep
+        .set_encumbrance_config(
+            &params.inventory_uuid,
+            params.strength,
+            params.carrying_capacity,
+            params.count_money_weight.unwrap_or(false),
+        )
+        .await?;
+    let new_inventory = inv_rep.get_raw_inventory(&params.inventory_uuid).await?;
+    report_operation(
+        chg_rep,
+        und_rep,
+        &user.user_id,
+        &params.inventory_uuid,
+        InventoryChangeKind::InventoryEdited,
+        None,
+        Some(to_string(&old_inventory)?),
+        Some(to_string(&new_inventory)?),
+    )
+    .await?;
+    Ok(Status::NoContent)
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    modifiers(&BearerAuthAddon),
+    paths(
+        get_encumbrance,
+        configure_encumbrance
+    ),
+    components(
+        schemas(
+            EncumbranceParams,
+            EncumbranceConfigParams,
+            Encumbrance,
+            EncumbranceRule,
+            EncumbranceState
+        )
+    ),
+    tags(
+        (name = "Encumbrance", description = "Endpoints for carried weight and carrying capacity")
+    )
+)]
+pub struct EncumbranceApiDoc;
@@ -1,8 +1,16 @@
-use repos::model::{FullFrontendInventory, ItemPreset};
+use repos::model::{
+    FrontendInventoryView, FrontendItem, FrontendItemView, FullFrontendInventory,
+    InventoryChangeKind, InventoryItem, InventoryOperation, InventoryPermission, InventoryRole,
+    InventoryRoleAssignment, ItemDisguise, ItemPreset, PlayerFullFrontendInventory, RawInventory,
+};
+use repos::repos::campaign_repository::CampaignRepository;
+use repos::repos::change_log_repository::ChangeLogRepository;
 use repos::repos::inventory_repository::InventoryRepository;
 use repos::repos::item_preset_repository::ItemPresetRepository;
-use repos::repos::user_repository::UserRepository;
+use repos::repos::money_repository::MoneyRepository;
+use repos::repos::undo_repository::UndoRepository;
 use rocket::http::Status;
+use rocket::serde::json::{from_str, json, to_string, to_value};
 use rocket::{form::FromForm, serde::json::Json, State};
 use rocket_errors::anyhow::Result;
 use serde::{Deserialize, Serialize};
@@ -11,42 +19,102 @@ use utoipa::IntoParams;
 use utoipa::OpenApi;
 use utoipa::ToSchema;
 
-use super::create_error;
 use super::router_utility::{
-    user_has_read_access_to_inventory, user_has_write_access_to_inventory,
-    user_is_creator_of_inventory, user_is_dm, ACCESS_DENIAL_MESSAGE,
+    item_view_for_user, preset_belongs_to_campaign_of_inventory, user_has_permission_on_inventory,
+    user_has_read_access_to_inventory, user_is_dm_of_campaign, user_is_dm_of_inventory,
+    user_is_member_of_campaign, ACCESS_DENIAL_MESSAGE,
 };
+use super::{create_error, BearerAuthAddon};
+
+/// Error returned if an item preset of another campaign is added to an inventory.
+const FOREIGN_PRESET_MESSAGE: &str = "the item preset belongs to another campaign";
+
+/// Error returned if the shares or the role of the owner of an inventory would be changed.
+const OWNER_SHARES_MESSAGE: &str = "the shares of the owner can not be changed";
+
+/// Reports a reversible change on an inventory and records it, so it can be undone.
+#[allow(clippy::too_many_arguments)]
+pub(super) async fn report_operation(
+    chg_rep: &ChangeLogRepository,
+    und_rep: &UndoRepository,
+    user_id: &str,
+    inventory_uuid: &str,
+    kind: InventoryChangeKind,
+    item: Option<&InventoryItem>,
+    old_value: Option<String>,
+    new_value: Option<String>,
+) -> Result<()> {
+    crate::report_change_on_inventory!(
+        chg_rep,
+        user_id,
+        inventory_uuid,
+        kind,
+        item,
+        old_value.as_deref(),
+        new_value.as_deref()
+    );
+    und_rep
+        .record_operation(
+            inventory_uuid,
+            user_id,
+            kind,
+            item,
+            old_value.as_deref(),
+            new_value.as_deref(),
+        )
+        .await?;
+    Ok(())
+}
 
 #[derive(FromForm, ToSchema, IntoParams)]
 pub struct InventoryUUIDParams {
     inventory_uuid: String,
 }
 
+#[derive(FromForm, ToSchema, IntoParams)]
+pub struct InventoryListParams {
+    /// Only return the inventories of this campaign
+    campaign_uuid: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, ToSchema, IntoParams)]
 pub struct GetAllInventoriesReturn {
-    inventories: Vec<FullFrontendInventory>,
+    inventories: Vec<FrontendInventoryView>,
 }
 
 #[utoipa::path(
     get,
     path = "/inventory/all",
     summary = "Retrieve all inventories for the authenticated user",
-    description = r#"Retrieves all inventories associated with the authenticated user.  
+    description = r#"Retrieves all inventories associated with the authenticated user in the campaigns they are a member of.  
+DM notes of items are only included for DMs of the inventory's campaign, other users get `PlayerFullFrontendInventory`.
 Requires authentication. Returns an error if retrieval fails."#,
+    params(InventoryListParams),
     responses(
         (status = 200, description = "All inventories of the user", body = GetAllInventoriesReturn)
     ),
     security(("bearer_auth" = [])),
     tag = "Inventories"
 )]
-#[get("/inventory/all")]
+#[get("/inventory/all?<params..>")]
 pub async fn get_all_inventories(
+    params: InventoryListParams,
     user: super::AuthenticatedUser,
     inv_rep: &State<InventoryRepository>,
+    cmp_rep: &State<CampaignRepository>,
 ) -> Result<Json<GetAllInventoriesReturn>> {
-    let allinvs = inv_rep.get_all_inventories(&user.user_id).await?;
+    let dm_campaigns = cmp_rep.get_dm_campaign_ids(&user.user_id).await?;
+    let allinvs = inv_rep
+        .get_all_inventories(&user.user_id, params.campaign_uuid.as_deref())
+        .await?;
     Ok(Json(GetAllInventoriesReturn {
-        inventories: allinvs,
+        inventories: allinvs
+            .into_iter()
+            .map(|inv| {
+                let is_dm = dm_campaigns.contains(&inv.campaign_uuid);
+                FrontendInventoryView::for_viewer(inv, is_dm)
+            })
+            .collect(),
     }))
 }
 
@@ -55,10 +123,11 @@ pub async fn get_all_inventories(
     path = "/inventory",
     summary = "Retrieve a specific inventory",
     description = r#"Retrieves the detailed inventory by UUID.
-Requires authentication. Returns an error if the user lacks access."#,
+DM notes of items are only included for DMs of the inventory's campaign, other users get `PlayerFullFrontendInventory`.
+Requires authentication and membership in the inventory's campaign. Returns an error if the user lacks access."#,
     params(InventoryUUIDParams),
     responses(
-        (status = 200, description = "Specific inventory details", body = FullFrontendInventory)
+        (status = 200, description = "Specific inventory details", body = FrontendInventoryView)
     ),
     security(("bearer_auth" = [])),
     tag = "Inventories"
@@ -68,28 +137,33 @@ pub async fn get_specific_inventory(
     params: InventoryUUIDParams,
     user: super::AuthenticatedUser,
     inv_rep: &State<InventoryRepository>,
-) -> Result<Json<FullFrontendInventory>> {
+    cmp_rep: &State<CampaignRepository>,
+) -> Result<Json<FrontendInventoryView>> {
     let inv = inv_rep.get_full_inventory(&params.inventory_uuid).await?;
-    if !inv.reader.contains(&user.user_id) && !(inv.owner_uuid == user.user_id) {
+    if (!inv.reader.contains(&user.user_id) && inv.owner_uuid != user.user_id)
+        || !user_is_member_of_campaign(cmp_rep.inner(), &inv.campaign_uuid, &user.user_id).await?
+    {
         return Err(create_error(ACCESS_DENIAL_MESSAGE));
     }
-    Ok(Json(inv))
+    let is_dm = user_is_dm_of_campaign(cmp_rep.inner(), &inv.campaign_uuid, &user.user_id).await?;
+    Ok(Json(FrontendInventoryView::for_viewer(inv, is_dm)))
 }
 
 #[derive(FromForm, ToSchema, IntoParams)]
 pub struct InventoryCreateParams {
     name: String,
+    campaign_uuid: String,
 }
 
 #[utoipa::path(
     put,
     path = "/inventory",
     summary = "Create a new inventory",
-    description = r#"Creates a new inventory entry in the system.
-Requires authentication. Returns an error if creation fails."#,
+    description = r#"Creates a new inventory in a campaign, it is shared with all DMs of the campaign.
+Requires authentication and membership in the campaign. Returns an error if creation fails."#,
     params(InventoryCreateParams),
     responses(
-        (status = 200, description = "The newly created inventory", body = FullFrontendInventory)
+        (status = 200, description = "The newly created inventory", body = FrontendInventoryView)
     ),
     security(("bearer_auth" = [])),
     tag = "Inventories"
@@ -99,12 +173,16 @@ pub async fn create_inventory(
     params: InventoryCreateParams,
     user: super::AuthenticatedUser,
     inv_rep: &State<InventoryRepository>,
-    usr_rep: &State<UserRepository>,
-) -> Result<Json<FullFrontendInventory>> {
+    chg_rep: &State<ChangeLogRepository>,
+    cmp_rep: &State<CampaignRepository>,
+) -> Result<Json<FrontendInventoryView>> {
+    if !user_is_member_of_campaign(cmp_rep.inner(), &params.campaign_uuid, &user.user_id).await? {
+        return Err(create_error(ACCESS_DENIAL_MESSAGE));
+    }
     let inv = inv_rep
-        .create_inventory(&user.user_id, 0, &params.name)
+        .create_inventory(&user.user_id, &params.campaign_uuid, &params.name)
         .await?;
-    let dms = usr_rep.get_all_dm_ids().await?;
+    let dms = cmp_rep.get_dm_ids(&params.campaign_uuid).await?;
     for dm_id in dms {
         if dm_id == user.user_id {
             continue;
@@ -112,13 +190,22 @@ pub async fn create_inventory(
         inv_rep.add_reader(&inv.uuid, &dm_id).await?;
         inv_rep.add_writer(&inv.uuid, &dm_id).await?;
     }
-    crate::report_change_on_inventory!(&inv.uuid);
+    crate::report_change_on_inventory!(
+        chg_rep,
+        &user.user_id,
+        &inv.uuid,
+        InventoryChangeKind::InventoryCreated,
+        None,
+        None,
+        Some(&to_string(&inv)?)
+    );
     get_specific_inventory(
         InventoryUUIDParams {
             inventory_uuid: inv.uuid,
         },
         user,
         inv_rep,
+        cmp_rep,
     )
     .await
 }
@@ -134,11 +221,11 @@ pub struct InventoryAddItemByPresetParams {
     put,
     path = "/inventory/item/addPreset",
     summary = "Add an item to an inventory by preset",
-    description = r#"Adds an item to an inventory by providing a preset UUID.
+    description = r#"Adds a new stack of an item to an inventory by providing a preset UUID, an inventory can hold several stacks of the same preset.
 Requires authentication and write access. Returns an error if access is denied."#,
     params(InventoryAddItemByPresetParams),
     responses(
-        (status = 204, description = "Item added to inventory by preset")
+        (status = 200, description = "The created item, its UUID addresses it in all item endpoints", body = FrontendItemView)
     ),
     security(("bearer_auth" = [])),
     tag = "Inventories"
@@ -148,17 +235,32 @@ pub async fn add_preset_to_inventory(
     params: InventoryAddItemByPresetParams,
     user: super::AuthenticatedUser,
     inv_rep: &State<InventoryRepository>,
-) -> Result<Status> {
-    if !user_has_write_access_to_inventory(
+    ipr_rep: &State<ItemPresetRepository>,
+    chg_rep: &State<ChangeLogRepository>,
+    und_rep: &State<UndoRepository>,
+    cmp_rep: &State<CampaignRepository>,
+) -> Result<Json<FrontendItemView>> {
+    if !user_has_permission_on_inventory(
         inv_rep.inner(),
-        params.inventory_uuid.clone(),
-        user.user_id,
+        &params.inventory_uuid,
+        &user.user_id,
+        InventoryPermission::EditItems,
     )
     .await?
     {
         return Err(create_error(ACCESS_DENIAL_MESSAGE));
     }
-    inv_rep
+    if !preset_belongs_to_campaign_of_inventory(
+        inv_rep.inner(),
+        ipr_rep.inner(),
+        &params.inventory_uuid,
+        &params.preset_uuid,
+    )
+    .await?
+    {
+        return Err(create_error(FOREIGN_PRESET_MESSAGE));
+    }
+    let new_item = inv_rep
         .add_inventory_item(
             &params.inventory_uuid,
             &params.preset_uuid,
@@ -168,8 +270,27 @@ pub async fn add_preset_to_inventory(
             "",
         )
         .await?;
-    crate::report_change_on_inventory!(&params.inventory_uuid);
-    Ok(Status::NoContent)
+    report_operation(
+        chg_rep,
+        und_rep,
+        &user.user_id,
+        &params.inventory_uuid,
+        InventoryChangeKind::ItemAdded,
+        Some(&new_item),
+        None,
+        Some(to_string(&new_item)?),
+    )
+    .await?;
+    Ok(Json(
+        item_view_for_user(
+            inv_rep.inner(),
+            cmp_rep.inner(),
+            &params.inventory_uuid,
+            &new_item.uuid,
+            &user.user_id,
+        )
+        .await?,
+    ))
 }
 
 #[derive(FromForm, ToSchema, IntoParams)]
@@ -197,31 +318,45 @@ pub async fn add_new_item_to_inventory(
     params: InventoryAddItemByNameParams,
     user: super::AuthenticatedUser,
     inv_rep: &State<InventoryRepository>,
+    chg_rep: &State<ChangeLogRepository>,
+    und_rep: &State<UndoRepository>,
     ipr_rep: &State<ItemPresetRepository>,
 ) -> Result<Json<ItemPreset>> {
-    if !user_has_write_access_to_inventory(
+    if !user_has_permission_on_inventory(
         inv_rep.inner(),
-        params.inventory_uuid.clone(),
-        user.user_id.clone(),
+        &params.inventory_uuid,
+        &user.user_id,
+        InventoryPermission::EditItems,
     )
     .await?
     {
         return Err(create_error(ACCESS_DENIAL_MESSAGE));
     }
+    let inventory = inv_rep.get_raw_inventory(&params.inventory_uuid).await?;
     let id = ipr_rep
-        .create_from_name(&params.name, &user.user_id)
+        .create_from_name(&params.name, &user.user_id, &inventory.campaign_uuid)
         .await?;
-    inv_rep
+    let new_item = inv_rep
         .add_inventory_item(&params.inventory_uuid, &id, "", params.amount, 0, "")
         .await?;
-    crate::report_change_on_inventory!(&params.inventory_uuid);
+    report_operation(
+        chg_rep,
+        und_rep,
+        &user.user_id,
+        &params.inventory_uuid,
+        InventoryChangeKind::ItemAdded,
+        Some(&new_item),
+        None,
+        Some(to_string(&new_item)?),
+    )
+    .await?;
     Ok(Json(ipr_rep.get_by_uuid(&id).await?))
 }
 
 #[derive(FromForm, ToSchema, IntoParams)]
 pub struct ItemEditParams {
     inventory_uuid: String,
-    item_preset_uuid: String,
+    item_uuid: String,
     amount: Option<i32>,
     sorting: Option<i32>,
     inventory_item_note: Option<String>,
@@ -245,33 +380,270 @@ pub async fn edit_item(
     params: ItemEditParams,
     user: super::AuthenticatedUser,
     inv_rep: &State<InventoryRepository>,
+    chg_rep: &State<ChangeLogRepository>,
+    und_rep: &State<UndoRepository>,
 ) -> Result<Status> {
-    if !user_has_write_access_to_inventory(
+    if !user_has_permission_on_inventory(
         inv_rep.inner(),
-        params.inventory_uuid.clone(),
-        user.user_id.clone(),
+        &params.inventory_uuid,
+        &user.user_id,
+        InventoryPermission::EditItems,
     )
     .await?
     {
         return Err(create_error(ACCESS_DENIAL_MESSAGE));
     }
+    let old_item = inv_rep
+        .get_inventory_item(&params.inventory_uuid, &params.item_uuid)
+        .await?;
     inv_rep
         .update_inventory_item(
             &params.inventory_uuid,
-            &params.item_preset_uuid,
+            &params.item_uuid,
             None,
             params.amount,
             params.sorting,
             params.inventory_item_note.as_deref(),
         )
         .await?;
-    crate::report_change_on_inventory!(&params.inventory_uuid);
+    let new_item = inv_rep
+        .get_inventory_item(&params.inventory_uuid, &params.item_uuid)
+        .await?;
+    report_operation(
+        chg_rep,
+        und_rep,
+        &user.user_id,
+        &params.inventory_uuid,
+        InventoryChangeKind::ItemEdited,
+        Some(&new_item),
+        Some(to_string(&old_item)?),
+        Some(to_string(&new_item)?),
+    )
+    .await?;
+    Ok(Status::NoContent)
+}
+
+#[derive(FromForm, ToSchema, IntoParams)]
+pub struct SecretItemAddParams {
+    inventory_uuid: String,
+    preset_uuid: String,
+    amount: i32,
+    /// Name shown to players until the item is revealed
+    disguise_name: String,
+    disguise_description: Option<String>,
+    disguise_item_type: Option<String>,
+}
+
+#[utoipa::path(
+    put,
+    path = "/inventory/item/addSecret",
+    summary = "Add an unidentified item to an inventory",
+    description = r#"Adds an item by preset that shows the given disguise to players, while the real preset stays hidden until the item is revealed.
+Requires authentication and DM privileges in the campaign of the inventory. Returns an error if user is not a DM of the campaign."#,
+    params(SecretItemAddParams),
+    responses(
+        (status = 200, description = "The created unidentified item as seen by DMs", body = FrontendItem)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Inventories"
+)]
+#[put("/inventory/item/addSecret?<params..>")]
+pub async fn add_secret_item_to_inventory(
+    params: SecretItemAddParams,
+    user: super::AuthenticatedUser,
+    inv_rep: &State<InventoryRepository>,
+    chg_rep: &State<ChangeLogRepository>,
+    und_rep: &State<UndoRepository>,
+    cmp_rep: &State<CampaignRepository>,
+    ipr_rep: &State<ItemPresetRepository>,
+) -> Result<Json<FrontendItemView>> {
+    if !user_is_dm_of_inventory(
+        inv_rep.inner(),
+        cmp_rep.inner(),
+        &params.inventory_uuid,
+        &user.user_id,
+    )
+    .await?
+    {
+        return Err(create_error(ACCESS_DENIAL_MESSAGE));
+    }
+    if !preset_belongs_to_campaign_of_inventory(
+        inv_rep.inner(),
+        ipr_rep.inner(),
+        &params.inventory_uuid,
+        &params.preset_uuid,
+    )
+    .await?
+    {
+        return Err(create_error(FOREIGN_PRESET_MESSAGE));
+    }
+    let disguise = ItemDisguise {
+        name: params.disguise_name,
+        description: params.disguise_description.unwrap_or_default(),
+        item_type: params.disguise_item_type.unwrap_or_default(),
+    };
+    let new_item = inv_rep
+        .add_disguised_inventory_item(
+            &params.inventory_uuid,
+            &params.preset_uuid,
+            params.amount,
+            0,
+            &disguise,
+        )
+        .await?;
+    report_operation(
+        chg_rep,
+        und_rep,
+        &user.user_id,
+        &params.inventory_uuid,
+        InventoryChangeKind::ItemAdded,
+        Some(&new_item),
+        None,
+        Some(to_string(&new_item)?),
+    )
+    .await?;
+    Ok(Json(
+        item_view_for_user(
+            inv_rep.inner(),
+            cmp_rep.inner(),
+            &params.inventory_uuid,
+            &new_item.uuid,
+            &user.user_id,
+        )
+        .await?,
+    ))
+}
+
+#[derive(FromForm, ToSchema, IntoParams)]
+pub struct ItemRevealParams {
+    inventory_uuid: String,
+    item_uuid: String,
+}
+
+#[utoipa::path(
+    patch,
+    path = "/inventory/item/reveal",
+    summary = "Reveal an unidentified item",
+    description = r#"Removes the disguise of an item, so players see its real preset.
+Requires authentication and DM privileges in the campaign of the inventory. Returns an error if user is not a DM of the campaign."#,
+    params(ItemRevealParams),
+    responses(
+        (status = 204, description = "Item revealed successfully")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Inventories"
+)]
+#[patch("/inventory/item/reveal?<params..>")]
+pub async fn reveal_item(
+    params: ItemRevealParams,
+    user: super::AuthenticatedUser,
+    inv_rep: &State<InventoryRepository>,
+    chg_rep: &State<ChangeLogRepository>,
+    und_rep: &State<UndoRepository>,
+    cmp_rep: &State<CampaignRepository>,
+) -> Result<Status> {
+    if !user_is_dm_of_inventory(
+        inv_rep.inner(),
+        cmp_rep.inner(),
+        &params.inventory_uuid,
+        &user.user_id,
+    )
+    .await?
+    {
+        return Err(create_error(ACCESS_DENIAL_MESSAGE));
+    }
+    let old_item = inv_rep
+        .get_inventory_item(&params.inventory_uuid, &params.item_uuid)
+        .await?;
+    inv_rep
+        .reveal_inventory_item(&params.inventory_uuid, &params.item_uuid)
+        .await?;
+    let new_item = inv_rep
+        .get_inventory_item(&params.inventory_uuid, &params.item_uuid)
+        .await?;
+    report_operation(
+        chg_rep,
+        und_rep,
+        &user.user_id,
+        &params.inventory_uuid,
+        InventoryChangeKind::ItemRevealed,
+        Some(&new_item),
+        Some(to_string(&old_item)?),
+        Some(to_string(&new_item)?),
+    )
+    .await?;
+    Ok(Status::NoContent)
+}
+
+#[derive(FromForm, ToSchema, IntoParams)]
+pub struct ItemMoveParams {
+    inventory_uuid: String,
+    item_uuid: String,
+    /// The container item to put the item into, omit to take it out to the top level
+    container_item_uuid: Option<String>,
+}
+
+#[utoipa::path(
+    patch,
+    path = "/inventory/item/move",
+    summary = "Move an item into or out of a container",
+    description = r#"Puts an item into a container item of the same inventory, or takes it out to the top level.
+Requires authentication and write access. Returns an error if access is denied, the target is no container or lies inside the moved item."#,
+    params(ItemMoveParams),
+    responses(
+        (status = 204, description = "Item moved successfully")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Inventories"
+)]
+#[patch("/inventory/item/move?<params..>")]
+pub async fn move_item(
+    params: ItemMoveParams,
+    user: super::AuthenticatedUser,
+    inv_rep: &State<InventoryRepository>,
+    chg_rep: &State<ChangeLogRepository>,
+    und_rep: &State<UndoRepository>,
+) -> Result<Status> {
+    if !user_has_permission_on_inventory(
+        inv_rep.inner(),
+        &params.inventory_uuid,
+        &user.user_id,
+        InventoryPermission::EditItems,
+    )
+    .await?
+    {
+        return Err(create_error(ACCESS_DENIAL_MESSAGE));
+    }
+    let old_item = inv_rep
+        .get_inventory_item(&params.inventory_uuid, &params.item_uuid)
+        .await?;
+    inv_rep
+        .move_inventory_item(
+            &params.inventory_uuid,
+            &params.item_uuid,
+            params.container_item_uuid.as_deref(),
+        )
+        .await?;
+    let new_item = inv_rep
+        .get_inventory_item(&params.inventory_uuid, &params.item_uuid)
+        .await?;
+    report_operation(
+        chg_rep,
+        und_rep,
+        &user.user_id,
+        &params.inventory_uuid,
+        InventoryChangeKind::ItemEdited,
+        Some(&new_item),
+        Some(to_string(&old_item)?),
+        Some(to_string(&new_item)?),
+    )
+    .await?;
     Ok(Status::NoContent)
 }
 
 #[derive(FromForm, ToSchema, IntoParams)]
 pub struct NoteAddParams {
-    item_preset_uuid: String,
+    item_uuid: String,
     inventory_uuid: String,
     note: String,
 }
@@ -281,7 +653,7 @@ pub struct NoteAddParams {
     path = "/inventory/item/addNote",
     summary = "Add a DM note to an item",
     description = r#"Adds a DM-only note to the specified inventory item.
-Requires authentication and DM privileges. Returns an error if user is not a DM."#,
+Requires authentication and DM privileges in the campaign of the inventory. Returns an error if user is not a DM of the campaign."#,
     params(NoteAddParams),
     responses(
         (status = 204, description = "DM note successfully added")
@@ -294,36 +666,61 @@ pub async fn add_note_to_item(
     params: NoteAddParams,
     user: super::AuthenticatedUser,
     inv_rep: &State<InventoryRepository>,
-    usr_rep: &State<UserRepository>,
+    chg_rep: &State<ChangeLogRepository>,
+    und_rep: &State<UndoRepository>,
+    cmp_rep: &State<CampaignRepository>,
 ) -> Result<Status> {
-    if !user_is_dm(usr_rep.inner(), user.user_id.clone()).await? {
+    if !user_is_dm_of_inventory(
+        inv_rep.inner(),
+        cmp_rep.inner(),
+        &params.inventory_uuid,
+        &user.user_id,
+    )
+    .await?
+    {
         return Err(create_error(ACCESS_DENIAL_MESSAGE));
     }
+    let old_item = inv_rep
+        .get_inventory_item(&params.inventory_uuid, &params.item_uuid)
+        .await?;
     inv_rep
         .update_inventory_item(
             &params.inventory_uuid,
-            &params.item_preset_uuid,
+            &params.item_uuid,
             Some(&params.note),
             None,
             None,
             None,
         )
         .await?;
-    crate::report_change_on_inventory!(&params.inventory_uuid);
+    let new_item = inv_rep
+        .get_inventory_item(&params.inventory_uuid, &params.item_uuid)
+        .await?;
+    report_operation(
+        chg_rep,
+        und_rep,
+        &user.user_id,
+        &params.inventory_uuid,
+        InventoryChangeKind::DmNoteEdited,
+        Some(&new_item),
+        Some(to_string(&old_item)?),
+        Some(to_string(&new_item)?),
+    )
+    .await?;
     Ok(Status::NoContent)
 }
 
 #[derive(FromForm, ToSchema, IntoParams)]
 pub struct ItemDeleteParams {
     inventory_uuid: String,
-    item_preset_uuid: String,
+    item_uuid: String,
 }
 
 #[utoipa::path(
     delete,
     path = "/inventory/item/remove",
     summary = "Remove an item from an inventory",
-    description = r#"Removes the specified item from the inventory.
+    description = r#"Removes the specified item from the inventory, the contents of a container are moved into the container holding it.
 Requires authentication and write access. Returns an error if access is denied."#,
     params(ItemDeleteParams),
     responses(
@@ -337,20 +734,37 @@ pub async fn delete_item_from_inventory(
     params: ItemDeleteParams,
     user: super::AuthenticatedUser,
     inv_rep: &State<InventoryRepository>,
+    chg_rep: &State<ChangeLogRepository>,
+    und_rep: &State<UndoRepository>,
 ) -> Result<Status> {
-    if !user_has_write_access_to_inventory(
+    if !user_has_permission_on_inventory(
         inv_rep.inner(),
-        params.inventory_uuid.clone(),
-        user.user_id.clone(),
+        &params.inventory_uuid,
+        &user.user_id,
+        InventoryPermission::EditItems,
     )
     .await?
     {
         return Err(create_error(ACCESS_DENIAL_MESSAGE));
     }
+    let old_item = inv_rep
+        .get_inventory_item(&params.inventory_uuid, &params.item_uuid)
+        .await?;
     inv_rep
-        .remove_inventory_item(&params.inventory_uuid, &params.item_preset_uuid).await?;
-    crate::report_change_on_inventory!(&params.inventory_uuid);
-        
+        .remove_inventory_item(&params.inventory_uuid, &params.item_uuid)
+        .await?;
+    report_operation(
+        chg_rep,
+        und_rep,
+        &user.user_id,
+        &params.inventory_uuid,
+        InventoryChangeKind::ItemRemoved,
+        Some(&old_item),
+        Some(to_string(&old_item)?),
+        None,
+    )
+    .await?;
+
     Ok(Status::NoContent)
 }
 
@@ -359,6 +773,8 @@ pub struct InventoryEditParams {
     inventory_uuid: String,
     amount: Option<i32>,
     name: Option<String>,
+    /// Reason recorded in the money ledger if the amount changes
+    reason: Option<String>,
 }
 
 #[utoipa::path(
@@ -366,7 +782,8 @@ pub struct InventoryEditParams {
     path = "/inventory/edit",
     summary = "Edit an inventory",
     description = r#"Changes properties of an inventory, such as its name or amount field.
-Requires authentication and write access. Returns an error if access is denied."#,
+A changed amount is booked as a transaction with the difference into the money ledger, see `/money/transaction`.
+Requires authentication, renaming requires the permission to edit the settings, changing the amount the permission to edit money. Returns an error if access is denied or neither name nor amount is given."#,
     params(InventoryEditParams),
     responses(
         (status = 204, description = "Inventory edited successfully")
@@ -379,27 +796,80 @@ pub async fn edit_inventory(
     params: InventoryEditParams,
     user: super::AuthenticatedUser,
     inv_rep: &State<InventoryRepository>,
+    chg_rep: &State<ChangeLogRepository>,
+    und_rep: &State<UndoRepository>,
+    mon_rep: &State<MoneyRepository>,
 ) -> Result<Status> {
-    if !user_has_write_access_to_inventory(
-        inv_rep.inner(),
-        params.inventory_uuid.clone(),
-        user.user_id.clone(),
-    )
-    .await?
-    {
-        return Err(create_error(ACCESS_DENIAL_MESSAGE));
+    if params.name.is_none() && params.amount.is_none() {
+        return Err(create_error("nothing to edit, give a name or an amount"));
+    }
+    let mut permissions = vec![InventoryPermission::View];
+    if params.name.is_some() {
+        permissions.push(InventoryPermission::EditSettings);
+    }
+    if params.amount.is_some() {
+        permissions.push(InventoryPermission::EditMoney);
     }
+    for permission in permissions {
+        if !user_has_permission_on_inventory(
+            inv_rep.inner(),
+            &params.inventory_uuid,
+            &user.user_id,
+            permission,
+        )
+        .await?
+        {
+            return Err(create_error(ACCESS_DENIAL_MESSAGE));
+        }
+    }
+    let old_inventory = inv_rep.get_raw_inventory(&params.inventory_uuid).await?;
     inv_rep
-        .update_inventory(
+        .update_inventory(&params.inventory_uuid, params.name.as_deref())
+        .await?;
+    if let Some(amount) = params.amount {
+        if amount != old_inventory.money {
+            mon_rep
+                .post_transaction(
+                    &params.inventory_uuid,
+                    &user.user_id,
+                    amount - old_inventory.money,
+                    params.reason.as_deref(),
+                )
+                .await?;
+        }
+    }
+    let old_value = to_string(&old_inventory)?;
+    let new_value = to_string(&inv_rep.get_raw_inventory(&params.inventory_uuid).await?)?;
+    // Edits that change nothing must not discard the redo history
+    if new_value != old_value {
+        report_operation(
+            chg_rep,
+            und_rep,
+            &user.user_id,
             &params.inventory_uuid,
-            params.amount,
-            params.name.as_deref(),
-        ).await?;
-    crate::report_change_on_inventory!(&params.inventory_uuid);
-        
+            InventoryChangeKind::InventoryEdited,
+            None,
+            Some(old_value),
+            Some(new_value),
+        )
+        .await?;
+    }
+
     Ok(Status::NoContent)
 }
 
+/// Serializes the current owner, readers, writers and roles of an inventory for the change log.
+pub(super) async fn get_shares_value(
+    inv_rep: &InventoryRepository,
+    inventory_uuid: &str,
+) -> Result<String> {
+    let readers = inv_rep.get_readers(inventory_uuid).await?;
+    let writers = inv_rep.get_writers(inventory_uuid).await?;
+    let roles = inv_rep.get_roles(inventory_uuid).await?;
+    let owner = inv_rep.get_raw_inventory(inventory_uuid).await?.owner_uuid;
+    Ok(json!({ "owner": owner, "reader": readers, "writer": writers, "roles": roles }).to_string())
+}
+
 #[derive(FromForm, ToSchema, IntoParams)]
 pub struct InventoryShareParams {
     inventory_uuid: String,
@@ -411,8 +881,8 @@ pub struct InventoryShareParams {
     patch,
     path = "/inventory/addShare",
     summary = "Add share permissions to an inventory",
-    description = r#"Adds reader or writer permissions for an inventory.
-Requires authentication and creator privileges. Returns an error if user is not the creator."#,
+    description = r#"Adds reader or writer permissions for an inventory. Without a reader and a writer, all members of the inventory's campaign become readers.
+Requires authentication and the permission to share, held by the owner and managers. Returns an error if access is denied, the user to share with is not a member of the campaign or is the owner."#,
     params(InventoryShareParams),
     responses(
         (status = 204, description = "Share permissions added successfully")
@@ -425,15 +895,22 @@ pub async fn add_share_to_inventory(
     params: InventoryShareParams,
     user: super::AuthenticatedUser,
     inv_rep: &State<InventoryRepository>,
-    usr_rep: &State<UserRepository>,
+    chg_rep: &State<ChangeLogRepository>,
+    cmp_rep: &State<CampaignRepository>,
 ) -> Result<Status> {
-    if !user_is_creator_of_inventory(inv_rep.inner(), params.inventory_uuid.clone(), user.user_id)
-        .await?
+    if !user_has_permission_on_inventory(
+        inv_rep.inner(),
+        &params.inventory_uuid,
+        &user.user_id,
+        InventoryPermission::Share,
+    )
+    .await?
     {
         return Err(create_error(ACCESS_DENIAL_MESSAGE));
     }
-    let reader = if params.reader_uuid == None
-        && params.writer_uuid != None
+    let old_shares = get_shares_value(inv_rep.inner(), &params.inventory_uuid).await?;
+    let reader = if params.reader_uuid.is_none()
+        && params.writer_uuid.is_some()
         && user_has_read_access_to_inventory(
             inv_rep.inner(),
             params.inventory_uuid.clone(),
@@ -446,10 +923,24 @@ pub async fn add_share_to_inventory(
         params.reader_uuid
     };
     let writer = params.writer_uuid;
-    if reader == None && writer == None {
-        let users = (usr_rep.get_all_users().await?)
+    let inventory = inv_rep.get_raw_inventory(&params.inventory_uuid).await?;
+    let campaign_uuid = inventory.campaign_uuid;
+    if reader
+        .iter()
+        .chain(writer.iter())
+        .any(|user_id| *user_id == inventory.owner_uuid)
+    {
+        return Err(create_error(OWNER_SHARES_MESSAGE));
+    }
+    for user_id in reader.iter().chain(writer.iter()) {
+        if !user_is_member_of_campaign(cmp_rep.inner(), &campaign_uuid, user_id).await? {
+            return Err(create_error("the user is not a member of the campaign"));
+        }
+    }
+    if reader.is_none() && writer.is_none() {
+        let users = (cmp_rep.get_members(&campaign_uuid).await?)
             .into_iter()
-            .map(|x| x.uuid.clone());
+            .map(|x| x.user_uuid);
         let current_readers = inv_rep.get_readers(&params.inventory_uuid).await?;
         for reader in users {
             if current_readers.contains(&reader) {
@@ -465,7 +956,16 @@ pub async fn add_share_to_inventory(
         inv_rep.add_writer(&params.inventory_uuid, &writer).await?;
     }
 
-    crate::report_change_on_inventory!(&params.inventory_uuid);
+    let new_shares = get_shares_value(inv_rep.inner(), &params.inventory_uuid).await?;
+    crate::report_change_on_inventory!(
+        chg_rep,
+        &user.user_id,
+        &params.inventory_uuid,
+        InventoryChangeKind::SharesChanged,
+        None,
+        Some(&old_shares),
+        Some(&new_shares)
+    );
     Ok(Status::NoContent)
 }
 
@@ -473,8 +973,8 @@ pub async fn add_share_to_inventory(
     patch,
     path = "/inventory/removeShare",
     summary = "Remove share permissions from an inventory",
-    description = r#"Removes reader or writer permissions from an inventory.
-Requires authentication and creator privileges. Returns an error if user is not the creator."#,
+    description = r#"Removes reader or writer permissions from an inventory, removing a reader also removes their role. Users can always remove their own shares.
+Requires authentication and the permission to share, held by the owner and managers. Returns an error if access is denied or the shares of the owner would be removed."#,
     params(InventoryShareParams),
     responses(
         (status = 204, description = "Share permissions removed successfully")
@@ -487,14 +987,16 @@ pub async fn remove_share_from_inventory(
     params: InventoryShareParams,
     user: super::AuthenticatedUser,
     inv_rep: &State<InventoryRepository>,
+    chg_rep: &State<ChangeLogRepository>,
 ) -> Result<Status> {
     let reader = params.reader_uuid;
     let writer = params.writer_uuid;
     let some_own_user = Some(user.user_id.clone());
-    if !user_is_creator_of_inventory(
+    if !user_has_permission_on_inventory(
         inv_rep.inner(),
-        params.inventory_uuid.clone(),
-        user.user_id.clone(),
+        &params.inventory_uuid,
+        &user.user_id,
+        InventoryPermission::Share,
     )
     .await?
         && reader.clone() != some_own_user
@@ -502,6 +1004,18 @@ pub async fn remove_share_from_inventory(
     {
         return Err(create_error(ACCESS_DENIAL_MESSAGE));
     }
+    let owner_uuid = inv_rep
+        .get_raw_inventory(&params.inventory_uuid)
+        .await?
+        .owner_uuid;
+    if reader
+        .iter()
+        .chain(writer.iter())
+        .any(|user_id| *user_id == owner_uuid)
+    {
+        return Err(create_error(OWNER_SHARES_MESSAGE));
+    }
+    let old_shares = get_shares_value(inv_rep.inner(), &params.inventory_uuid).await?;
 
     if let Some(reader) = reader {
         inv_rep
@@ -514,7 +1028,236 @@ pub async fn remove_share_from_inventory(
             .await?;
     }
 
-    crate::report_change_on_inventory!(&params.inventory_uuid);
+    let new_shares = get_shares_value(inv_rep.inner(), &params.inventory_uuid).await?;
+    crate::report_change_on_inventory!(
+        chg_rep,
+        &user.user_id,
+        &params.inventory_uuid,
+        InventoryChangeKind::SharesChanged,
+        None,
+        Some(&old_shares),
+        Some(&new_shares)
+    );
+    Ok(Status::NoContent)
+}
+
+#[utoipa::path(
+    get,
+    path = "/inventory/roles",
+    summary = "Retrieve the roles of an inventory",
+    description = r#"Returns the roles given in an inventory. The owner holds every permission, readers without a role may only view and writers without a role may do everything but share and delete.
+Requires authentication and read access. Returns an error if access is denied."#,
+    params(InventoryUUIDParams),
+    responses(
+        (status = 200, description = "The roles of the inventory", body = Vec<InventoryRoleAssignment>)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Inventories"
+)]
+#[get("/inventory/roles?<params..>")]
+pub async fn get_inventory_roles(
+    params: InventoryUUIDParams,
+    user: super::AuthenticatedUser,
+    inv_rep: &State<InventoryRepository>,
+) -> Result<Json<Vec<InventoryRoleAssignment>>> {
+    if !user_has_permission_on_inventory(
+        inv_rep.inner(),
+        &params.inventory_uuid,
+        &user.user_id,
+        InventoryPermission::View,
+    )
+    .await?
+    {
+        return Err(create_error(ACCESS_DENIAL_MESSAGE));
+    }
+    Ok(Json(inv_rep.get_roles(&params.inventory_uuid).await?))
+}
+
+#[derive(FromForm, ToSchema, IntoParams)]
+pub struct InventoryRoleParams {
+    inventory_uuid: String,
+    user_uuid: String,
+    /// `viewer`, `itemEditor`, `moneyEditor` or `manager`
+    role: String,
+}
+
+#[utoipa::path(
+    patch,
+    path = "/inventory/setRole",
+    summary = "Give a user a role in an inventory",
+    description = r#"Gives a member of the inventory's campaign a role, replacing their previous role, and makes them a reader. The role replaces their writer share.
+Viewers may only view, item editors may also edit items, money editors may also edit money, managers may do everything but delete the inventory.
+Requires authentication and the permission to share. Returns an error if access is denied, the user is not a member of the campaign or is the owner."#,
+    params(InventoryRoleParams),
+    responses(
+        (status = 204, description = "Role set successfully")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Inventories"
+)]
+#[patch("/inventory/setRole?<params..>")]
+pub async fn set_inventory_role(
+    params: InventoryRoleParams,
+    user: super::AuthenticatedUser,
+    inv_rep: &State<InventoryRepository>,
+    cmp_rep: &State<CampaignRepository>,
+    chg_rep: &State<ChangeLogRepository>,
+) -> Result<Status> {
+    if !user_has_permission_on_inventory(
+        inv_rep.inner(),
+        &params.inventory_uuid,
+        &user.user_id,
+        InventoryPermission::Share,
+    )
+    .await?
+    {
+        return Err(create_error(ACCESS_DENIAL_MESSAGE));
+    }
+    let role: InventoryRole = params.role.parse()?;
+    let inventory = inv_rep.get_raw_inventory(&params.inventory_uuid).await?;
+    if inventory.owner_uuid == params.user_uuid {
+        return Err(create_error(OWNER_SHARES_MESSAGE));
+    }
+    if !user_is_member_of_campaign(cmp_rep.inner(), &inventory.campaign_uuid, &params.user_uuid)
+        .await?
+    {
+        return Err(create_error("the user is not a member of the campaign"));
+    }
+    let old_shares = get_shares_value(inv_rep.inner(), &params.inventory_uuid).await?;
+    inv_rep
+        .set_role(&params.inventory_uuid, &params.user_uuid, role)
+        .await?;
+    let new_shares = get_shares_value(inv_rep.inner(), &params.inventory_uuid).await?;
+    crate::report_change_on_inventory!(
+        chg_rep,
+        &user.user_id,
+        &params.inventory_uuid,
+        InventoryChangeKind::SharesChanged,
+        None,
+        Some(&old_shares),
+        Some(&new_shares)
+    );
+    Ok(Status::NoContent)
+}
+
+#[derive(FromForm, ToSchema, IntoParams)]
+pub struct InventoryRoleRemoveParams {
+    inventory_uuid: String,
+    user_uuid: String,
+}
+
+#[utoipa::path(
+    patch,
+    path = "/inventory/removeRole",
+    summary = "Remove the role of a user in an inventory",
+    description = r#"Removes the role of a user, who stays a reader and may only view, see `/inventory/removeShare`.
+Requires authentication and the permission to share. Returns an error if access is denied."#,
+    params(InventoryRoleRemoveParams),
+    responses(
+        (status = 204, description = "Role removed successfully")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Inventories"
+)]
+#[patch("/inventory/removeRole?<params..>")]
+pub async fn remove_inventory_role(
+    params: InventoryRoleRemoveParams,
+    user: super::AuthenticatedUser,
+    inv_rep: &State<InventoryRepository>,
+    chg_rep: &State<ChangeLogRepository>,
+) -> Result<Status> {
+    if !user_has_permission_on_inventory(
+        inv_rep.inner(),
+        &params.inventory_uuid,
+        &user.user_id,
+        InventoryPermission::Share,
+    )
+    .await?
+    {
+        return Err(create_error(ACCESS_DENIAL_MESSAGE));
+    }
+    let old_shares = get_shares_value(inv_rep.inner(), &params.inventory_uuid).await?;
+    inv_rep
+        .remove_role(&params.inventory_uuid, &params.user_uuid)
+        .await?;
+    let new_shares = get_shares_value(inv_rep.inner(), &params.inventory_uuid).await?;
+    crate::report_change_on_inventory!(
+        chg_rep,
+        &user.user_id,
+        &params.inventory_uuid,
+        InventoryChangeKind::SharesChanged,
+        None,
+        Some(&old_shares),
+        Some(&new_shares)
+    );
+    Ok(Status::NoContent)
+}
+
+#[derive(FromForm, ToSchema, IntoParams)]
+pub struct InventoryOwnershipParams {
+    inventory_uuid: String,
+    new_owner_uuid: String,
+    /// Whether the previous owner keeps read and write access, defaults to false
+    keep_access: Option<bool>,
+}
+
+#[utoipa::path(
+    patch,
+    path = "/inventory/transferOwnership",
+    summary = "Transfer the ownership of an inventory",
+    description = r#"Makes another member of the inventory's campaign the owner, who becomes reader and writer and loses their role.
+The previous owner loses read and write access unless `keep_access` is set.
+Requires authentication and ownership or DM privileges. Returns an error if access is denied or the new owner is not a member of the campaign."#,
+    params(InventoryOwnershipParams),
+    responses(
+        (status = 204, description = "Ownership transferred successfully")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Inventories"
+)]
+#[patch("/inventory/transferOwnership?<params..>")]
+pub async fn transfer_inventory_ownership(
+    params: InventoryOwnershipParams,
+    user: super::AuthenticatedUser,
+    inv_rep: &State<InventoryRepository>,
+    cmp_rep: &State<CampaignRepository>,
+    chg_rep: &State<ChangeLogRepository>,
+) -> Result<Status> {
+    if !user_has_permission_on_inventory(
+        inv_rep.inner(),
+        &params.inventory_uuid,
+        &user.user_id,
+        InventoryPermission::Own,
+    )
+    .await?
+        && !user_is_dm_of_inventory(
+            inv_rep.inner(),
+            cmp_rep.inner(),
+            &params.inventory_uuid,
+            &user.user_id,
+        )
+        .await?
+    {
+        return Err(create_error(ACCESS_DENIAL_MESSAGE));
+    }
+    let old_shares = get_shares_value(inv_rep.inner(), &params.inventory_uuid).await?;
+    inv_rep
+        .transfer_inventory(
+            &params.inventory_uuid,
+            &params.new_owner_uuid,
+            params.keep_access.unwrap_or(false),
+        )
+        .await?;
+    let new_shares = get_shares_value(inv_rep.inner(), &params.inventory_uuid).await?;
+    crate::report_change_on_inventory!(
+        chg_rep,
+        &user.user_id,
+        &params.inventory_uuid,
+        InventoryChangeKind::SharesChanged,
+        None,
+        Some(&old_shares),
+        Some(&new_shares)
+    );
     Ok(Status::NoContent)
 }
 
@@ -523,7 +1266,7 @@ pub async fn remove_share_from_inventory(
     path = "/inventory/delete",
     summary = "Delete an inventory",
     description = r#"Deletes an entire inventory from the system.
-Requires authentication and creator privileges. Returns an error if the user is not the creator."#,
+Requires authentication and ownership. Returns an error if the user is not the owner."#,
     params(InventoryUUIDParams),
     responses(
         (status = 204, description = "Inventory deleted successfully")
@@ -536,23 +1279,311 @@ pub async fn delete_inventory(
     params: InventoryUUIDParams,
     user: super::AuthenticatedUser,
     inv_rep: &State<InventoryRepository>,
+    chg_rep: &State<ChangeLogRepository>,
 ) -> Result<Status> {
-    if !user_is_creator_of_inventory(
+    if !user_has_permission_on_inventory(
         inv_rep.inner(),
-        params.inventory_uuid.clone(),
-        user.user_id.clone(),
+        &params.inventory_uuid,
+        &user.user_id,
+        InventoryPermission::Own,
     )
     .await?
     {
         return Err(create_error(ACCESS_DENIAL_MESSAGE));
     }
+    let mut old_inventory = to_value(inv_rep.get_raw_inventory(&params.inventory_uuid).await?)?;
+    // Lets a sync tell the readers, who have to drop the inventory, apart from everybody else
+    old_inventory["reader"] = json!(inv_rep.get_readers(&params.inventory_uuid).await?);
     inv_rep.delete_inventory(&params.inventory_uuid).await?;
-    crate::report_change_on_inventory!(&params.inventory_uuid);
+    crate::report_change_on_inventory!(
+        chg_rep,
+        &user.user_id,
+        &params.inventory_uuid,
+        InventoryChangeKind::InventoryDeleted,
+        None,
+        Some(&old_inventory.to_string()),
+        None
+    );
+    Ok(Status::NoContent)
+}
+
+/// Restores one of the snapshots of an operation and reports the resulting change.
+///
+/// Money differences are booked into the ledger with the given reason.
+async fn restore_snapshot(
+    inv_rep: &InventoryRepository,
+    chg_rep: &ChangeLogRepository,
+    mon_rep: &MoneyRepository,
+    user_id: &str,
+    operation: &InventoryOperation,
+    snapshot: Option<&str>,
+    reason: &str,
+) -> Result<()> {
+    let inventory_uuid = &operation.inventory_uuid;
+    match &operation.item_uuid {
+        Some(item_uuid) => {
+            let old_item = if inv_rep.item_exists(inventory_uuid, item_uuid).await? {
+                Some(
+                    inv_rep
+                        .get_inventory_item(inventory_uuid, item_uuid)
+                        .await?,
+                )
+            } else {
+                None
+            };
+            let kind = match snapshot {
+                Some(snapshot) => {
+                    let item: InventoryItem = from_str(snapshot)?;
+                    let is_dm_operation = is_dm_only_operation(operation.change_kind);
+                    inv_rep
+                        .restore_inventory_item(&item, !is_dm_operation)
+                        .await?;
+                    if old_item.is_none() {
+                        InventoryChangeKind::ItemAdded
+                    } else if is_dm_operation {
+                        operation.change_kind
+                    } else {
+                        InventoryChangeKind::ItemEdited
+                    }
+                }
+                None => {
+                    inv_rep
+                        .remove_inventory_item(inventory_uuid, item_uuid)
+                        .await?;
+                    InventoryChangeKind::ItemRemoved
+                }
+            };
+            let new_item = match snapshot {
+                Some(_) => Some(
+                    inv_rep
+                        .get_inventory_item(inventory_uuid, item_uuid)
+                        .await?,
+                ),
+                None => None,
+            };
+            crate::report_change_on_inventory!(
+                chg_rep,
+                user_id,
+                inventory_uuid,
+                kind,
+                new_item.as_ref().or(old_item.as_ref()),
+                old_item.as_ref().map(to_string).transpose()?.as_deref(),
+                new_item.as_ref().map(to_string).transpose()?.as_deref()
+            );
+        }
+        None => {
+            let snapshot = snapshot.ok_or_else(|| create_error("missing inventory snapshot"))?;
+            let inventory: RawInventory = from_str(snapshot)?;
+            let old_inventory = inv_rep.get_raw_inventory(inventory_uuid).await?;
+            inv_rep
+                .update_inventory(inventory_uuid, Some(&inventory.name))
+                .await?;
+            inv_rep
+                .set_encumbrance_config(
+                    inventory_uuid,
+                    inventory.strength,
+                    inventory.carrying_capacity,
+                    inventory.count_money_weight,
+                )
+                .await?;
+            if inventory.money != old_inventory.money {
+                mon_rep
+                    .post_transaction(
+                        inventory_uuid,
+                        user_id,
+                        inventory.money - old_inventory.money,
+                        Some(reason),
+                    )
+                    .await?;
+            }
+            let new_inventory = inv_rep.get_raw_inventory(inventory_uuid).await?;
+            crate::report_change_on_inventory!(
+                chg_rep,
+                user_id,
+                inventory_uuid,
+                InventoryChangeKind::InventoryEdited,
+                None,
+                Some(&to_string(&old_inventory)?),
+                Some(&to_string(&new_inventory)?)
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Checks if the user may undo or redo the given operation, changes of DM notes can only be reverted by DMs of the campaign.
+async fn user_may_revert_operation(
+    inv_rep: &InventoryRepository,
+    cmp_rep: &CampaignRepository,
+    user_id: &str,
+    operation: &InventoryOperation,
+) -> Result<bool> {
+    for permission in permissions_for_operation(operation)? {
+        if !user_has_permission_on_inventory(
+            inv_rep,
+            &operation.inventory_uuid,
+            user_id,
+            permission,
+        )
+        .await?
+        {
+            return Ok(false);
+        }
+    }
+    Ok(!is_dm_only_operation(operation.change_kind)
+        || user_is_dm_of_inventory(inv_rep, cmp_rep, &operation.inventory_uuid, user_id).await?)
+}
+
+/// Returns the permissions needed to revert an operation, edits of an inventory need the permissions for the changed fields.
+fn permissions_for_operation(operation: &InventoryOperation) -> Result<Vec<InventoryPermission>> {
+    if operation.change_kind != InventoryChangeKind::InventoryEdited {
+        return Ok(vec![InventoryPermission::EditItems]);
+    }
+    let (Some(old), Some(new)) = (&operation.old_value, &operation.new_value) else {
+        return Ok(vec![
+            InventoryPermission::EditMoney,
+            InventoryPermission::EditSettings,
+        ]);
+    };
+    let old: RawInventory = from_str(old)?;
+    let new: RawInventory = from_str(new)?;
+    let mut permissions = Vec::new();
+    if old.money != new.money {
+        permissions.push(InventoryPermission::EditMoney);
+    }
+    if old.name != new.name
+        || old.strength != new.strength
+        || old.carrying_capacity != new.carrying_capacity
+        || old.count_money_weight != new.count_money_weight
+    {
+        permissions.push(InventoryPermission::EditSettings);
+    }
+    Ok(permissions)
+}
+
+/// Checks if an operation changed fields only DMs may change, such as DM notes or disguises.
+fn is_dm_only_operation(kind: InventoryChangeKind) -> bool {
+    matches!(
+        kind,
+        InventoryChangeKind::DmNoteEdited | InventoryChangeKind::ItemRevealed
+    )
+}
+
+#[utoipa::path(
+    patch,
+    path = "/inventory/undo",
+    summary = "Undo the last change on an inventory",
+    description = r#"Reverts the latest not yet undone item or money edit of the inventory, restoring deleted items including their notes and sorting.
+Requires authentication and the permissions to edit what the change touched, reverting a DM note or a reveal additionally requires DM privileges. Returns an error if there is nothing to undo."#,
+    params(InventoryUUIDParams),
+    responses(
+        (status = 204, description = "Change undone successfully")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Inventories"
+)]
+#[patch("/inventory/undo?<params..>")]
+pub async fn undo_inventory_change(
+    params: InventoryUUIDParams,
+    user: super::AuthenticatedUser,
+    inv_rep: &State<InventoryRepository>,
+    cmp_rep: &State<CampaignRepository>,
+    chg_rep: &State<ChangeLogRepository>,
+    und_rep: &State<UndoRepository>,
+    mon_rep: &State<MoneyRepository>,
+) -> Result<Status> {
+    if !user_has_permission_on_inventory(
+        inv_rep.inner(),
+        &params.inventory_uuid,
+        &user.user_id,
+        InventoryPermission::View,
+    )
+    .await?
+    {
+        return Err(create_error(ACCESS_DENIAL_MESSAGE));
+    }
+    let operation = und_rep
+        .get_undo_candidate(&params.inventory_uuid)
+        .await?
+        .ok_or_else(|| create_error("nothing to undo"))?;
+    if !user_may_revert_operation(inv_rep.inner(), cmp_rep.inner(), &user.user_id, &operation)
+        .await?
+    {
+        return Err(create_error(ACCESS_DENIAL_MESSAGE));
+    }
+    restore_snapshot(
+        inv_rep.inner(),
+        chg_rep.inner(),
+        mon_rep.inner(),
+        &user.user_id,
+        &operation,
+        operation.old_value.as_deref(),
+        "Undo",
+    )
+    .await?;
+    und_rep.set_undone(operation.id, true).await?;
+    Ok(Status::NoContent)
+}
+
+#[utoipa::path(
+    patch,
+    path = "/inventory/redo",
+    summary = "Redo the last undone change on an inventory",
+    description = r#"Reapplies the change that was undone last.
+Requires authentication and the permissions to edit what the change touched, reapplying a DM note or a reveal additionally requires DM privileges. Returns an error if there is nothing to redo."#,
+    params(InventoryUUIDParams),
+    responses(
+        (status = 204, description = "Change redone successfully")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Inventories"
+)]
+#[patch("/inventory/redo?<params..>")]
+pub async fn redo_inventory_change(
+    params: InventoryUUIDParams,
+    user: super::AuthenticatedUser,
+    inv_rep: &State<InventoryRepository>,
+    cmp_rep: &State<CampaignRepository>,
+    chg_rep: &State<ChangeLogRepository>,
+    und_rep: &State<UndoRepository>,
+    mon_rep: &State<MoneyRepository>,
+) -> Result<Status> {
+    if !user_has_permission_on_inventory(
+        inv_rep.inner(),
+        &params.inventory_uuid,
+        &user.user_id,
+        InventoryPermission::View,
+    )
+    .await?
+    {
+        return Err(create_error(ACCESS_DENIAL_MESSAGE));
+    }
+    let operation = und_rep
+        .get_redo_candidate(&params.inventory_uuid)
+        .await?
+        .ok_or_else(|| create_error("nothing to redo"))?;
+    if !user_may_revert_operation(inv_rep.inner(), cmp_rep.inner(), &user.user_id, &operation)
+        .await?
+    {
+        return Err(create_error(ACCESS_DENIAL_MESSAGE));
+    }
+    restore_snapshot(
+        inv_rep.inner(),
+        chg_rep.inner(),
+        mon_rep.inner(),
+        &user.user_id,
+        &operation,
+        operation.new_value.as_deref(),
+        "Redo",
+    )
+    .await?;
+    und_rep.set_undone(operation.id, false).await?;
     Ok(Status::NoContent)
 }
 
 #[derive(OpenApi)]
 #[openapi(
+    modifiers(&BearerAuthAddon),
     paths(
         get_all_inventories,
         get_specific_inventory,
@@ -561,26 +1592,49 @@ pub async fn delete_inventory(
         add_new_item_to_inventory,
         edit_item,
         add_note_to_item,
+        add_secret_item_to_inventory,
+        reveal_item,
+        move_item,
         delete_item_from_inventory,
         edit_inventory,
         add_share_to_inventory,
         remove_share_from_inventory,
-        delete_inventory
+        get_inventory_roles,
+        set_inventory_role,
+        remove_inventory_role,
+        transfer_inventory_ownership,
+        delete_inventory,
+        undo_inventory_change,
+        redo_inventory_change
     ),
     components(
         schemas(
             InventoryUUIDParams,
+            InventoryListParams,
             InventoryCreateParams,
             InventoryAddItemByPresetParams,
             InventoryAddItemByNameParams,
             ItemEditParams,
             NoteAddParams,
+            SecretItemAddParams,
+            ItemRevealParams,
+            ItemMoveParams,
+            ItemDisguise,
             ItemDeleteParams,
             InventoryEditParams,
             InventoryShareParams,
+            InventoryRoleParams,
+            InventoryRoleRemoveParams,
+            InventoryOwnershipParams,
+            InventoryRole,
+            InventoryPermission,
+            InventoryRoleAssignment,
             GetAllInventoriesReturn,
+            FrontendInventoryView,
             FullFrontendInventory,
-            ItemPreset
+            PlayerFullFrontendInventory,
+            ItemPreset,
+            InventoryItem
         )
     ),
     tags(
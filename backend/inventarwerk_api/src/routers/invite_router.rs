@@ -0,0 +1,242 @@
+use repos::model::{Invite, InviteRedemption};
+use repos::repos::campaign_repository::CampaignRepository;
+use repos::repos::invite_repository::InviteRepository;
+use repos::repos::user_repository::UserRepository;
+use rocket::http::Status;
+use rocket::{form::FromForm, serde::json::Json, State};
+use rocket_errors::anyhow::Result;
+
+use utoipa::IntoParams;
+use utoipa::OpenApi;
+use utoipa::ToSchema;
+
+use super::router_utility::{user_is_dm, user_is_dm_of_campaign, ACCESS_DENIAL_MESSAGE};
+use super::{create_error, BearerAuthAddon};
+
+#[derive(FromForm, ToSchema, IntoParams)]
+pub struct InviteCreateParams {
+    /// Campaign the invited user joins as player
+    campaign_uuid: Option<String>,
+    /// Number of users that can redeem the invite, 1 for a single-use invite, unlimited if not set
+    max_uses: Option<i32>,
+    /// Hours until the invite expires, never if not set
+    expires_in_hours: Option<i32>,
+}
+
+#[utoipa::path(
+    put,
+    path = "/invite",
+    summary = "Create an invite",
+    description = r#"Creates an invitation code, the invited user joins the campaign of the invite.
+Only invites of server DMs let a new user register while the server is locked, see `/account/login`.
+Requires authentication and DM privileges, DMs of a campaign can only invite existing users into their campaign."#,
+    params(InviteCreateParams),
+    responses(
+        (status = 200, description = "The created invite", body = Invite)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Invites"
+)]
+#[put("/invite?<params..>")]
+pub async fn create_invite(
+    params: InviteCreateParams,
+    user: super::AuthenticatedUser,
+    usr_rep: &State<UserRepository>,
+    cmp_rep: &State<CampaignRepository>,
+    ivt_rep: &State<InviteRepository>,
+) -> Result<Json<Invite>> {
+    if !user_may_manage_invite(
+        usr_rep,
+        cmp_rep,
+        params.campaign_uuid.as_deref(),
+        &user.user_id,
+    )
+    .await?
+    {
+        return Err(create_error(ACCESS_DENIAL_MESSAGE));
+    }
+    let server_dm = user_is_dm(usr_rep.inner(), user.user_id.clone()).await?;
+    Ok(Json(
+        ivt_rep
+            .create_invite(
+                &user.user_id,
+                params.campaign_uuid.as_deref(),
+                params.max_uses,
+                params.expires_in_hours,
+                server_dm,
+            )
+            .await?,
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/invite/all",
+    summary = "Retrieve all manageable invites",
+    description = r#"Returns all invites for server DMs, for DMs of a campaign the invites into their campaigns. The newest first.
+Requires authentication."#,
+    responses(
+        (status = 200, description = "The invites", body = Vec<Invite>)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Invites"
+)]
+#[get("/invite/all")]
+pub async fn get_all_invites(
+    user: super::AuthenticatedUser,
+    usr_rep: &State<UserRepository>,
+    cmp_rep: &State<CampaignRepository>,
+    ivt_rep: &State<InviteRepository>,
+) -> Result<Json<Vec<Invite>>> {
+    if user_is_dm(usr_rep.inner(), user.user_id.clone()).await? {
+        return Ok(Json(ivt_rep.get_all_invites().await?));
+    }
+    let campaign_ids = cmp_rep.get_dm_campaign_ids(&user.user_id).await?;
+    Ok(Json(ivt_rep.get_campaign_invites(&campaign_ids).await?))
+}
+
+#[derive(FromForm, ToSchema, IntoParams)]
+pub struct InviteUUIDParams {
+    invite_uuid: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/invite/redemptions",
+    summary = "Retrieve who redeemed an invite",
+    description = r#"Returns the users that redeemed the invite, the earliest first.
+Requires authentication and the privileges to manage the invite."#,
+    params(InviteUUIDParams),
+    responses(
+        (status = 200, description = "The redemptions of the invite", body = Vec<InviteRedemption>)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Invites"
+)]
+#[get("/invite/redemptions?<params..>")]
+pub async fn get_invite_redemptions(
+    params: InviteUUIDParams,
+    user: super::AuthenticatedUser,
+    usr_rep: &State<UserRepository>,
+    cmp_rep: &State<CampaignRepository>,
+    ivt_rep: &State<InviteRepository>,
+) -> Result<Json<Vec<InviteRedemption>>> {
+    let invite = ivt_rep.get_invite(&params.invite_uuid).await?;
+    if !user_may_manage_invite(
+        usr_rep,
+        cmp_rep,
+        invite.campaign_uuid.as_deref(),
+        &user.user_id,
+    )
+    .await?
+    {
+        return Err(create_error(ACCESS_DENIAL_MESSAGE));
+    }
+    Ok(Json(ivt_rep.get_redemptions(&invite.uuid).await?))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/invite/revoke",
+    summary = "Revoke an invite",
+    description = r#"Revokes the invite, it can not be redeemed anymore. The redemptions are kept.
+Requires authentication and the privileges to manage the invite."#,
+    params(InviteUUIDParams),
+    responses(
+        (status = 204, description = "Invite successfully revoked")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Invites"
+)]
+#[patch("/invite/revoke?<params..>")]
+pub async fn revoke_invite(
+    params: InviteUUIDParams,
+    user: super::AuthenticatedUser,
+    usr_rep: &State<UserRepository>,
+    cmp_rep: &State<CampaignRepository>,
+    ivt_rep: &State<InviteRepository>,
+) -> Result<Status> {
+    let invite = ivt_rep.get_invite(&params.invite_uuid).await?;
+    if !user_may_manage_invite(
+        usr_rep,
+        cmp_rep,
+        invite.campaign_uuid.as_deref(),
+        &user.user_id,
+    )
+    .await?
+    {
+        return Err(create_error(ACCESS_DENIAL_MESSAGE));
+    }
+    ivt_rep.revoke_invite(&invite.uuid).await?;
+    Ok(Status::NoContent)
+}
+
+#[derive(FromForm, ToSchema, IntoParams)]
+pub struct InviteCodeParams {
+    code: String,
+}
+
+#[utoipa::path(
+    patch,
+    path = "/invite/redeem",
+    summary = "Redeem an invite",
+    description = r#"Redeems an invite as an existing user, who joins the campaign of the invite.
+Requires authentication. Returns an error if the invite is revoked, expired or used up."#,
+    params(InviteCodeParams),
+    responses(
+        (status = 200, description = "The redeemed invite", body = Invite)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Invites"
+)]
+#[patch("/invite/redeem?<params..>")]
+pub async fn redeem_invite(
+    params: InviteCodeParams,
+    user: super::AuthenticatedUser,
+    ivt_rep: &State<InviteRepository>,
+) -> Result<Json<Invite>> {
+    Ok(Json(
+        ivt_rep.redeem_invite(&params.code, &user.user_id).await?,
+    ))
+}
+
+/// Server DMs may manage all invites, DMs of a campaign the invites into their campaign.
+async fn user_may_manage_invite(
+    usr_rep: &UserRepository,
+    cmp_rep: &CampaignRepository,
+    campaign_uuid: Option<&str>,
+    user_id: &str,
+) -> Result<bool> {
+    if user_is_dm(usr_rep, user_id.to_string()).await? {
+        return Ok(true);
+    }
+    match campaign_uuid {
+        Some(campaign_uuid) => user_is_dm_of_campaign(cmp_rep, campaign_uuid, user_id).await,
+        None => Ok(false),
+    }
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    modifiers(&BearerAuthAddon),
+    paths(
+        create_invite,
+        get_all_invites,
+        get_invite_redemptions,
+        revoke_invite,
+        redeem_invite
+    ),
+    components(
+        schemas(
+            InviteCreateParams,
+            InviteUUIDParams,
+            InviteCodeParams,
+            Invite,
+            InviteRedemption
+        )
+    ),
+    tags(
+        (name = "Invites", description = "Endpoints for inviting new users")
+    )
+)]
+pub struct InviteApiDoc;
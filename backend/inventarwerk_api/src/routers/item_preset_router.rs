@@ -4,7 +4,8 @@ use std::time::Duration;
 use repos::{
     model::ItemPreset,
     repos::{
-        inventory_repository::InventoryRepository, item_preset_repository::ItemPresetRepository,
+        campaign_repository::CampaignRepository, inventory_repository::InventoryRepository,
+        item_preset_repository::ItemPresetRepository,
     },
 };
 use rocket::{form::FromForm, http::Status, serde::json::Json, State};
@@ -17,7 +18,11 @@ use utoipa::ToSchema;
 
 use super::{
     create_error,
-    router_utility::{user_has_read_access_to_item_preset, ACCESS_DENIAL_MESSAGE},
+    router_utility::{
+        user_has_read_access_to_item_preset, user_is_dm_of_inventory, user_is_member_of_campaign,
+        ACCESS_DENIAL_MESSAGE,
+    },
+    BearerAuthAddon,
 };
 
 #[derive(Serialize, Deserialize, ToSchema, IntoParams)]
@@ -36,7 +41,7 @@ pub struct ItemPresetUUIDParams {
     summary = "Retrieve a specific item preset",
     description = r#"Retrieves a specific item preset by UUID.
 
-Requires authentication and read access. Returns an error if the user lacks access or the preset does not exist."#,
+Requires authentication and read access, public presets require membership of their campaign. Returns an error if the user lacks access or the preset does not exist."#,
     params(ItemPresetUUIDParams),
     responses(
         (status = 200, description = "Returns the requested item preset", body = ItemPreset)
@@ -50,9 +55,13 @@ pub async fn get_item_preset(
     user: super::AuthenticatedUser,
     ipr_rep: &State<ItemPresetRepository>,
     inv_rep: &State<InventoryRepository>,
+    cmp_rep: &State<CampaignRepository>,
 ) -> Result<Json<ItemPreset>> {
     let preset = ipr_rep.get_by_uuid(&params.item_preset_uuid).await?;
-    if !preset.creator.starts_with("public")
+    let public_and_member = preset.creator.starts_with("public")
+        && user_is_member_of_campaign(cmp_rep.inner(), &preset.campaign_uuid, &user.user_id)
+            .await?;
+    if !public_and_member
         && !user_has_read_access_to_item_preset(
             inv_rep.inner(),
             &user.user_id,
@@ -73,6 +82,10 @@ pub struct ItemModifyParams {
     weight: Option<f32>,
     description: Option<String>,
     item_type: Option<String>,
+    /// Items of the preset can hold other items
+    container: Option<bool>,
+    /// The contents do not add to the weight of the container
+    weightless_contents: Option<bool>,
 }
 
 #[utoipa::path(
@@ -81,7 +94,7 @@ pub struct ItemModifyParams {
     summary = "Modify an existing item preset",
     description = r#"Modifies an item preset. Only the creator can modify their preset.
 
-Requires authentication and creator privileges. Returns an error if the user is not the creator."#,
+Requires authentication and creator privileges. Returns an error if the user is not the creator or the preset should no longer be a container while one of its items holds other items."#,
     params(ItemModifyParams),
     responses(
         (status = 204, description = "Item preset successfully modified")
@@ -99,6 +112,11 @@ pub async fn modify_item_preset(
     if preset.creator != user.user_id {
         return Err(create_error(ACCESS_DENIAL_MESSAGE));
     }
+    if params.container == Some(false) && ipr_rep.has_contents(&preset.uuid).await? {
+        return Err(create_error(
+            "the items of the preset still hold other items, take them out first",
+        ));
+    }
     ipr_rep
         .update_item_preset(
             &params.item_preset_uuid,
@@ -107,6 +125,8 @@ pub async fn modify_item_preset(
             params.weight,
             params.description.as_deref(),
             params.item_type.as_deref(),
+            params.container,
+            params.weightless_contents,
         )
         .await?;
     Ok(Status::NoContent)
@@ -145,7 +165,8 @@ pub async fn delete_item_preset(
     get,
     path = "/itemPreset/all",
     summary = "Retrieve all accessible item presets",
-    description = r#"Retrieves all public item presets and those in the user's inventories.
+    description = r#"Retrieves all public item presets of the user's campaigns and those in the user's inventories.
+The presets of unidentified items are left out, unless the user is a DM of the inventory's campaign.
 
 Requires authentication. Returns an error if the retrieval fails."#,
     responses(
@@ -159,17 +180,24 @@ pub async fn get_all_item_presets(
     user: super::AuthenticatedUser,
     inv_rep: &State<InventoryRepository>,
     ipr_rep: &State<ItemPresetRepository>,
+    cmp_rep: &State<CampaignRepository>,
 ) -> Result<Json<GetItemPresetReturn>> {
-    let mut item_presets = ipr_rep.get_public_presets().await?;
+    let campaign_ids: Vec<String> = cmp_rep
+        .get_campaigns_of_user(&user.user_id)
+        .await?
+        .into_iter()
+        .map(|campaign| campaign.uuid)
+        .collect();
+    let mut item_presets = ipr_rep.get_public_presets(&campaign_ids).await?;
     let invs = inv_rep
         .get_owned_and_readable_inventory_ids(&user.user_id)
         .await?;
     for i in invs {
-        item_presets.extend(ipr_rep.get_presets_in_inventory(&i).await?);
+        let is_dm =
+            user_is_dm_of_inventory(inv_rep.inner(), cmp_rep.inner(), &i, &user.user_id).await?;
+        item_presets.extend(ipr_rep.get_presets_in_inventory(&i, is_dm).await?);
     }
-    Ok(Json(GetItemPresetReturn {
-        item_presets: item_presets,
-    }))
+    Ok(Json(GetItemPresetReturn { item_presets }))
 }
 
 #[allow(non_snake_case)]
@@ -182,6 +210,10 @@ pub struct ExternPresetData {
     description: String,
     creator: String,
     itemType: String,
+    #[serde(default)]
+    container: bool,
+    #[serde(default)]
+    weightlessContents: bool,
 }
 
 #[derive(Serialize, Deserialize, ToSchema, IntoParams)]
@@ -189,13 +221,20 @@ pub struct ExternPresetDataList {
     presets: Vec<ExternPresetData>,
 }
 
+#[derive(FromForm, ToSchema, IntoParams)]
+pub struct ExternPresetParams {
+    /// Campaign the presets are imported into
+    campaign_uuid: String,
+}
+
 #[utoipa::path(
     put,
     path = "/itemPreset/addExtern",
     summary = "Import external item presets",
-    description = r#"Imports external item presets from the provided JSON list.
+    description = r#"Imports external item presets from the provided JSON list into a campaign.
 
-Requires authentication. Retries up to 5 times on creation errors, then skips the preset."#,
+Requires authentication and membership of the campaign. Retries up to 5 times on creation errors, then skips the preset."#,
+    params(ExternPresetParams),
     request_body = ExternPresetDataList,
     responses(
         (status = 204, description = "External item presets successfully imported")
@@ -203,12 +242,17 @@ Requires authentication. Retries up to 5 times on creation errors, then skips th
     security(("bearer_auth" = [])),
     tag = "Item Presets"
 )]
-#[put("/itemPreset/addExtern", data = "<json_data>")]
+#[put("/itemPreset/addExtern?<params..>", data = "<json_data>")]
 pub async fn add_extern(
+    params: ExternPresetParams,
     json_data: Json<ExternPresetDataList>,
-    _user: super::AuthenticatedUser,
+    user: super::AuthenticatedUser,
     ipr_rep: &State<ItemPresetRepository>,
+    cmp_rep: &State<CampaignRepository>,
 ) -> Result<Status> {
+    if !user_is_member_of_campaign(cmp_rep.inner(), &params.campaign_uuid, &user.user_id).await? {
+        return Err(create_error(ACCESS_DENIAL_MESSAGE));
+    }
     for x in &json_data.presets {
         let mut i = 0;
         loop {
@@ -221,6 +265,9 @@ pub async fn add_extern(
                 creator: x.creator.clone(),
                 item_type: x.itemType.clone(),
                 creation: None,
+                container: x.container,
+                weightless_contents: x.weightlessContents,
+                campaign_uuid: params.campaign_uuid.clone(),
             };
             let res = ipr_rep.create(&preset).await;
             match res {
@@ -243,6 +290,7 @@ pub async fn add_extern(
 
 #[derive(OpenApi)]
 #[openapi(
+    modifiers(&BearerAuthAddon),
     paths(
         get_item_preset,
         modify_item_preset,
@@ -257,7 +305,8 @@ pub async fn add_extern(
             ItemModifyParams,
             ItemPreset,
             ExternPresetData,
-            ExternPresetDataList
+            ExternPresetDataList,
+            ExternPresetParams
         )
     ),
     tags(
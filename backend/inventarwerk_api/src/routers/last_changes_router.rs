@@ -1,11 +1,25 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
+use repos::model::{
+    FrontendItem, FrontendItemView, InventoryChangeKind, PlayerFrontendItem, RawInventory,
+};
+use repos::repos::campaign_repository::CampaignRepository;
+use repos::repos::change_log_repository::ChangeLogRepository;
 use repos::repos::inventory_repository::InventoryRepository;
-use rocket::{serde::json::Json, State};
+use rocket::form::FromForm;
+use rocket::response::stream::{Event, EventStream};
+use rocket::serde::json::{from_str, Value};
+use rocket::tokio::select;
+use rocket::tokio::sync::broadcast::error::RecvError;
+use rocket::{serde::json::Json, Shutdown, State};
+use serde::{Deserialize, Serialize};
 
+use utoipa::IntoParams;
 use utoipa::OpenApi;
+use utoipa::ToSchema;
 
-use crate::get_last_inventory_change;
+use super::BearerAuthAddon;
+use crate::last_changes_map_macro::{InventoryChangeEvent, GLOBAL_CHANGE_CHANNEL};
 use rocket_errors::anyhow::Result;
 
 #[utoipa::path(
@@ -23,21 +37,304 @@ use rocket_errors::anyhow::Result;
 pub async fn last_changes(
     user: super::AuthenticatedUser,
     inv_rep: &State<InventoryRepository>,
-) -> Result<Json<HashMap<String, u128>>> {
-    let mut inv_hash: HashMap<String, u128> = HashMap::new();
+    chg_rep: &State<ChangeLogRepository>,
+) -> Result<Json<HashMap<String, i64>>> {
     let invs = inv_rep
         .get_owned_and_readable_inventory_ids(&user.user_id)
         .await?;
-    for i in invs {
-        inv_hash.insert(i.clone(), get_last_inventory_change!(&i));
-    }
+    let last_changes = chg_rep.get_last_changes(&invs).await?;
+    let inv_hash = invs
+        .into_iter()
+        .map(|i| {
+            let last_change = last_changes.get(&i).copied().unwrap_or(0);
+            (i, last_change)
+        })
+        .collect();
     Ok(Json(inv_hash))
 }
 
+#[utoipa::path(
+    get,
+    path = "/lastChanges/stream",
+    summary = "Subscribe to inventory changes",
+    description = r#"Opens a Server-Sent Events stream that pushes an event for every change on an inventory the authenticated user can read.
+Changes on inventories the user just lost access to (e.g. by deletion or a removed share) are still delivered once, so clients can drop them."#,
+    responses(
+        (status = 200, description = "Stream of inventory changes", body = InventoryChangeEvent, content_type = "text/event-stream")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Last Changes"
+)]
+#[get("/lastChanges/stream")]
+pub async fn last_changes_stream<'r>(
+    user: super::AuthenticatedUser,
+    inv_rep: &'r State<InventoryRepository>,
+    mut end: Shutdown,
+) -> Result<EventStream![Event + 'r]> {
+    let mut rx = GLOBAL_CHANGE_CHANNEL.subscribe();
+    let mut readable = inv_rep
+        .get_owned_and_readable_inventory_ids(&user.user_id)
+        .await?;
+
+    Ok(EventStream! {
+        loop {
+            let change = select! {
+                change = rx.recv() => match change {
+                    Ok(change) => change,
+                    Err(RecvError::Closed) => break,
+                    Err(RecvError::Lagged(_)) => continue,
+                },
+                _ = &mut end => break,
+            };
+            let was_readable = readable.contains(&change.inventory_uuid);
+            // Only these kinds can grant or revoke access, shares also cover roles, owners and campaign members
+            if matches!(
+                change.kind,
+                InventoryChangeKind::SharesChanged | InventoryChangeKind::InventoryCreated
+            ) {
+                match inv_rep.get_owned_and_readable_inventory_ids(&user.user_id).await {
+                    Ok(ids) => readable = ids,
+                    Err(_) => continue,
+                }
+            }
+            if was_readable || readable.contains(&change.inventory_uuid) {
+                yield Event::json(&change);
+            }
+        }
+    })
+}
+
+#[derive(FromForm, ToSchema, IntoParams)]
+pub struct SyncParams {
+    /// Cursor returned by the previous sync, omit for a full sync
+    cursor: Option<i64>,
+    /// Milliseconds since UNIX epoch, only used if no cursor is given
+    timestamp: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncItem {
+    inventory_uuid: String,
+    /// DM notes are only included for DMs of the inventory's campaign
+    item: FrontendItemView,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncItemTombstone {
+    inventory_uuid: String,
+    item_uuid: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncShares {
+    inventory_uuid: String,
+    reader: Vec<String>,
+    writer: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncResponse {
+    /// Cursor to pass to the next sync
+    cursor: i64,
+    /// Created or edited inventories, including their money
+    inventories: Vec<RawInventory>,
+    /// Inventories that were deleted or that the user lost access to
+    deleted_inventories: Vec<String>,
+    /// Added or edited items
+    items: Vec<SyncItem>,
+    /// Removed items
+    deleted_items: Vec<SyncItemTombstone>,
+    /// Current readers and writers of inventories whose shares changed
+    shares: Vec<SyncShares>,
+}
+
+/// Everything that has to be sent for a single inventory during a sync.
+#[derive(Default)]
+struct InventoryDelta {
+    full: bool,
+    inventory: bool,
+    shares: bool,
+    items: BTreeSet<String>,
+}
+
+/// Checks if a change log value of a share change lists the user as reader.
+fn shares_value_contains_reader(value: &Option<String>, user_id: &str) -> bool {
+    value
+        .as_deref()
+        .and_then(|v| from_str::<Value>(v).ok())
+        .and_then(|v| {
+            v["reader"]
+                .as_array()
+                .map(|readers| readers.iter().any(|r| r.as_str() == Some(user_id)))
+        })
+        .unwrap_or(false)
+}
+
+/// Checks if the user could read an inventory before it was deleted, judging by the change log value of the deletion.
+fn deleted_value_readable_by(value: &Option<String>, user_id: &str) -> bool {
+    let owned = value
+        .as_deref()
+        .and_then(|v| from_str::<Value>(v).ok())
+        .is_some_and(|v| v["ownerUuid"].as_str() == Some(user_id));
+    owned || shares_value_contains_reader(value, user_id)
+}
+
+#[utoipa::path(
+    get,
+    path = "/lastChanges/sync",
+    summary = "Retrieve everything that changed since a cursor",
+    description = r#"Returns only the inventories, items and shares that changed after the given cursor (or timestamp), including tombstones for deleted inventories the user could read and for deleted items.
+Omitting both cursor and timestamp returns the complete state of all inventories the user can read. The returned cursor is to be passed to the next call.
+Changes made a few seconds before the cursor may be returned again, so that changes committed late are not missed."#,
+    params(SyncParams),
+    responses(
+        (status = 200, description = "Changes since the given cursor", body = SyncResponse)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Last Changes"
+)]
+#[get("/lastChanges/sync?<params..>")]
+pub async fn sync(
+    params: SyncParams,
+    user: super::AuthenticatedUser,
+    inv_rep: &State<InventoryRepository>,
+    chg_rep: &State<ChangeLogRepository>,
+    cmp_rep: &State<CampaignRepository>,
+) -> Result<Json<SyncResponse>> {
+    let dm_campaigns = cmp_rep.get_dm_campaign_ids(&user.user_id).await?;
+    let cursor = chg_rep.get_latest_change_id().await?;
+    let readable = inv_rep
+        .get_owned_and_readable_inventory_ids(&user.user_id)
+        .await?;
+    let full_sync = params.cursor.is_none() && params.timestamp.is_none();
+
+    let mut deltas: BTreeMap<String, InventoryDelta> = BTreeMap::new();
+    let mut deleted_inventories: BTreeSet<String> = BTreeSet::new();
+    if full_sync {
+        for inv in &readable {
+            deltas.entry(inv.clone()).or_default().full = true;
+        }
+    } else {
+        let changes = chg_rep
+            .get_changes_since(
+                params.cursor.unwrap_or(0),
+                params.timestamp.unwrap_or(0),
+                cursor,
+                &readable,
+            )
+            .await?;
+        for change in changes {
+            let inv = change.inventory_uuid;
+            if change.change_kind == InventoryChangeKind::InventoryDeleted {
+                deltas.remove(&inv);
+                // Inventories the user never knew of are not reported
+                if deleted_value_readable_by(&change.old_value, &user.user_id) {
+                    deleted_inventories.insert(inv);
+                }
+                continue;
+            }
+            if !readable.contains(&inv) {
+                // Only share changes on unreadable inventories get here, report lost access
+                if shares_value_contains_reader(&change.old_value, &user.user_id) {
+                    deleted_inventories.insert(inv);
+                }
+                continue;
+            }
+            deleted_inventories.remove(&inv);
+            let delta = deltas.entry(inv).or_default();
+            match change.change_kind {
+                InventoryChangeKind::InventoryCreated => delta.full = true,
+                InventoryChangeKind::InventoryEdited => delta.inventory = true,
+                InventoryChangeKind::SharesChanged => {
+                    delta.shares = true;
+                    // Newly shared inventories have to be sent completely
+                    if !shares_value_contains_reader(&change.old_value, &user.user_id) {
+                        delta.full = true;
+                    }
+                }
+                _ => {
+                    if let Some(item) = change.item_uuid {
+                        delta.items.insert(item);
+                    }
+                }
+            }
+        }
+    }
+
+    let mut response = SyncResponse {
+        cursor,
+        inventories: Vec::new(),
+        deleted_inventories: deleted_inventories.into_iter().collect(),
+        items: Vec::new(),
+        deleted_items: Vec::new(),
+        shares: Vec::new(),
+    };
+    for (inv, delta) in deltas {
+        let inventory = inv_rep.get_raw_inventory(&inv).await?;
+        let is_dm = dm_campaigns.contains(&inventory.campaign_uuid);
+        if delta.full || delta.inventory {
+            response.inventories.push(inventory);
+        }
+        if delta.full || delta.shares {
+            response.shares.push(SyncShares {
+                inventory_uuid: inv.clone(),
+                reader: inv_rep.get_readers(&inv).await?,
+                writer: inv_rep.get_writers(&inv).await?,
+            });
+        }
+        let items = if delta.full {
+            inv_rep.get_frontend_items_in_inventory(&inv).await?
+        } else if !delta.items.is_empty() {
+            let uuids: Vec<String> = delta.items.iter().cloned().collect();
+            let items = inv_rep.get_frontend_items_by_uuids(&inv, &uuids).await?;
+            for uuid in uuids {
+                if !items.iter().any(|i| i.uuid == uuid) {
+                    response.deleted_items.push(SyncItemTombstone {
+                        inventory_uuid: inv.clone(),
+                        item_uuid: uuid,
+                    });
+                }
+            }
+            items
+        } else {
+            Vec::new()
+        };
+        response
+            .items
+            .extend(items.into_iter().map(|item| SyncItem {
+                inventory_uuid: inv.clone(),
+                item: FrontendItemView::for_viewer(item, is_dm),
+            }));
+    }
+    Ok(Json(response))
+}
+
 #[derive(OpenApi)]
 #[openapi(
+    modifiers(&BearerAuthAddon),
     paths(
-        last_changes
+        last_changes,
+        last_changes_stream,
+        sync
+    ),
+    components(
+        schemas(
+            InventoryChangeEvent,
+            InventoryChangeKind,
+            SyncParams,
+            SyncItem,
+            SyncItemTombstone,
+            SyncShares,
+            SyncResponse,
+            FrontendItem,
+            PlayerFrontendItem,
+            FrontendItemView,
+            RawInventory
+        )
     ),
     tags(
         (name = "Last Changes", description = "Endpoint to get last changes")
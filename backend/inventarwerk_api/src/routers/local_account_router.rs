@@ -0,0 +1,280 @@
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use lazy_static::lazy_static;
+use openssl::rand::rand_bytes;
+use repos::repos::invite_repository::InviteRepository;
+use repos::repos::local_account_repository::LocalAccountRepository;
+use repos::repos::session_repository::SessionRepository;
+use repos::repos::setting_repository::SettingRepository;
+use repos::repos::user_repository::UserRepository;
+use rocket::http::{CookieJar, Status};
+use rocket::serde::json::Json;
+use rocket::serde::{Deserialize, Serialize};
+use rocket::{form::FromForm, State};
+use rocket_errors::anyhow::Result;
+
+use utoipa::IntoParams;
+use utoipa::OpenApi;
+use utoipa::ToSchema;
+
+use super::router_utility::{registration_allowed, user_is_dm, ACCESS_DENIAL_MESSAGE};
+use super::session_router::{start_session, SessionConfig};
+use super::{create_error, BearerAuthAddon};
+
+/// Passwords shorter than this are refused.
+const MIN_PASSWORD_LENGTH: usize = 8;
+
+/// Error returned for an unknown username or a wrong password alike.
+const INVALID_CREDENTIALS_MESSAGE: &str = "invalid username or password";
+
+lazy_static! {
+    /// Checked for unknown usernames, so they take as long to refuse as a wrong password.
+    static ref DUMMY_PASSWORD_HASH: String =
+        hash_password("not the password of any account").expect("the dummy password can be hashed");
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalRegisterRequest {
+    username: String,
+    /// At least 8 characters
+    password: String,
+    /// Invitation code, lets a new user register while the server is locked
+    invite: Option<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/account/local/register",
+    summary = "Register a local account",
+    description = r#"Creates a new user logging in with username and password instead of Discord and logs them in. The first user becomes DM.
+While the server is locked new users are only accepted with a valid invitation code of a server DM, which is redeemed.
+Returns an error if the username is taken, the password is too short or the invite is not valid."#,
+    request_body = LocalRegisterRequest,
+    responses(
+        (status = 204, description = "Registered and logged in successfully")
+    ),
+    tag = "Accounts"
+)]
+#[post("/account/local/register", data = "<json_data>")]
+#[allow(clippy::too_many_arguments)]
+pub async fn register_local_account(
+    json_data: Json<LocalRegisterRequest>,
+    cookies: &CookieJar<'_>,
+    lac_rep: &State<LocalAccountRepository>,
+    set_rep: &State<SettingRepository>,
+    ivt_rep: &State<InviteRepository>,
+    ses_rep: &State<SessionRepository>,
+    ses_cfg: &State<SessionConfig>,
+    user_agent: super::UserAgent,
+) -> Result<Status> {
+    let username = json_data.username.trim();
+    if username.is_empty() {
+        return Err(create_error("the username must not be empty"));
+    }
+    if !registration_allowed(set_rep, ivt_rep, json_data.invite.as_deref()).await? {
+        return Err(create_error("No new Users allowed"));
+    }
+    let password_hash = hash_password(&json_data.password)?;
+    let user = lac_rep
+        .register(username, &password_hash, json_data.invite.as_deref())
+        .await?;
+    start_session(cookies, ses_rep, ses_cfg, &user.uuid, &user_agent).await?;
+    Ok(Status::NoContent)
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalLoginRequest {
+    username: String,
+    password: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalLoginResponse {
+    user_uuid: String,
+    /// The password was reset by a DM and should be changed, see `/account/local/password`.
+    /// Only a hint for the client, the user is not restricted until then
+    must_change_password: bool,
+}
+
+#[utoipa::path(
+    post,
+    path = "/account/local/login",
+    summary = "Log in with username and password",
+    description = r#"Logs a user with a local account in, the username is not case sensitive. Returns an error if the username or the password is wrong.
+`mustChangePassword` asks the client to have the user change a password reset by a DM, the server does not restrict the user until then."#,
+    request_body = LocalLoginRequest,
+    responses(
+        (status = 200, description = "Logged in successfully", body = LocalLoginResponse)
+    ),
+    tag = "Accounts"
+)]
+#[post("/account/local/login", data = "<json_data>")]
+pub async fn login_local_account(
+    json_data: Json<LocalLoginRequest>,
+    cookies: &CookieJar<'_>,
+    lac_rep: &State<LocalAccountRepository>,
+    ses_rep: &State<SessionRepository>,
+    ses_cfg: &State<SessionConfig>,
+    user_agent: super::UserAgent,
+) -> Result<Json<LocalLoginResponse>> {
+    let Some(account) = lac_rep
+        .get_account_by_username(json_data.username.trim())
+        .await?
+    else {
+        // Verify anyway, so the response time does not reveal which usernames exist
+        verify_password(&json_data.password, &DUMMY_PASSWORD_HASH)?;
+        return Err(create_error(INVALID_CREDENTIALS_MESSAGE));
+    };
+    if !verify_password(&json_data.password, &account.password_hash)? {
+        return Err(create_error(INVALID_CREDENTIALS_MESSAGE));
+    }
+    start_session(cookies, ses_rep, ses_cfg, &account.user_uuid, &user_agent).await?;
+    Ok(Json(LocalLoginResponse {
+        user_uuid: account.user_uuid,
+        must_change_password: account.must_change_password,
+    }))
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PasswordChangeRequest {
+    old_password: String,
+    /// At least 8 characters
+    new_password: String,
+}
+
+#[utoipa::path(
+    patch,
+    path = "/account/local/password",
+    summary = "Change the own password",
+    description = r#"Replaces the password of the own local account, also after a DM reset it, and ends the other sessions of the user.
+Requires a login session, API tokens are refused. Returns an error if the user has no local account, the old password is wrong or the new one is too short."#,
+    request_body = PasswordChangeRequest,
+    responses(
+        (status = 204, description = "Password changed successfully")
+    ),
+    tag = "Accounts"
+)]
+#[patch("/account/local/password", data = "<json_data>")]
+pub async fn change_password(
+    json_data: Json<PasswordChangeRequest>,
+    user: super::SessionUser,
+    lac_rep: &State<LocalAccountRepository>,
+    ses_rep: &State<SessionRepository>,
+) -> Result<Status> {
+    let Some(account) = lac_rep.get_account(&user.user_id).await? else {
+        return Err(create_error("the user has no local account"));
+    };
+    if !verify_password(&json_data.old_password, &account.password_hash)? {
+        return Err(create_error("the old password is wrong"));
+    }
+    let password_hash = hash_password(&json_data.new_password)?;
+    lac_rep
+        .set_password(&user.user_id, &password_hash, false)
+        .await?;
+    ses_rep
+        .revoke_all_sessions(&user.user_id, Some(&user.session_uuid))
+        .await?;
+    Ok(Status::NoContent)
+}
+
+#[derive(FromForm, ToSchema, IntoParams)]
+pub struct PasswordResetParams {
+    account_uuid: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PasswordResetResponse {
+    /// Handed to the user, who should replace it on the next login
+    temporary_password: String,
+}
+
+#[utoipa::path(
+    patch,
+    path = "/account/local/resetPassword",
+    params(PasswordResetParams),
+    summary = "Reset the password of a user",
+    description = r#"Replaces the password of a local account with a random temporary password, which is only returned once, and ends all sessions of the user. The user is asked to change it on the next login.
+Requires a login session, API tokens are refused and DM privileges. Returns an error if access is denied or the user has no local account."#,
+    responses(
+        (status = 200, description = "The temporary password", body = PasswordResetResponse)
+    ),
+    tag = "Accounts"
+)]
+#[patch("/account/local/resetPassword?<params..>")]
+pub async fn reset_password(
+    params: PasswordResetParams,
+    user: super::SessionUser,
+    usr_rep: &State<UserRepository>,
+    lac_rep: &State<LocalAccountRepository>,
+    ses_rep: &State<SessionRepository>,
+) -> Result<Json<PasswordResetResponse>> {
+    if !user_is_dm(usr_rep.inner(), user.user_id).await? {
+        return Err(create_error(ACCESS_DENIAL_MESSAGE));
+    }
+    let mut bytes = [0u8; 9];
+    rand_bytes(&mut bytes)?;
+    let temporary_password: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    let password_hash = hash_password(&temporary_password)?;
+    lac_rep
+        .set_password(&params.account_uuid, &password_hash, true)
+        .await?;
+    ses_rep
+        .revoke_all_sessions(&params.account_uuid, None)
+        .await?;
+    Ok(Json(PasswordResetResponse { temporary_password }))
+}
+
+/// Hashes a password with Argon2 and a random salt, refusing passwords that are too short.
+fn hash_password(password: &str) -> Result<String> {
+    if password.chars().count() < MIN_PASSWORD_LENGTH {
+        return Err(create_error(&format!(
+            "the password must be at least {} characters long",
+            MIN_PASSWORD_LENGTH
+        )));
+    }
+    let mut salt = [0u8; 16];
+    rand_bytes(&mut salt)?;
+    let salt = SaltString::encode_b64(&salt).map_err(|e| create_error(&e.to_string()))?;
+    let hash = Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|e| create_error(&e.to_string()))?;
+    Ok(hash.to_string())
+}
+
+/// Checks a password against a stored Argon2 hash.
+fn verify_password(password: &str, password_hash: &str) -> Result<bool> {
+    let hash = PasswordHash::new(password_hash).map_err(|e| create_error(&e.to_string()))?;
+    Ok(Argon2::default()
+        .verify_password(password.as_bytes(), &hash)
+        .is_ok())
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    modifiers(&BearerAuthAddon),
+    paths(
+        register_local_account,
+        login_local_account,
+        change_password,
+        reset_password
+    ),
+    components(
+        schemas(
+            LocalRegisterRequest,
+            LocalLoginRequest,
+            LocalLoginResponse,
+            PasswordChangeRequest,
+            PasswordResetParams,
+            PasswordResetResponse
+        )
+    ),
+    tags(
+        (name = "Accounts", description = "Endpoints for managing user accounts")
+    )
+)]
+pub struct LocalAccountApiDoc;
@@ -1,21 +1,44 @@
-use rocket::http::Status;
+use repos::repos::api_token_repository::ApiTokenRepository;
+use repos::repos::session_repository::SessionRepository;
+use rocket::http::{Method, Status};
 use rocket::request::Outcome;
 use rocket::Request;
 use rocket::{request::FromRequest, Route};
+use utoipa::openapi::security::{Http, HttpAuthScheme, SecurityScheme};
+use utoipa::Modify;
 
 use anyhow::anyhow;
 use rocket_errors::anyhow::AnyhowError;
 
 pub mod account_router;
+pub mod api_token_router;
+pub mod campaign_router;
+pub mod currency_router;
+pub mod encumbrance_router;
 pub mod inventory_router;
+pub mod invite_router;
 pub mod item_preset_router;
 pub mod last_changes_router;
+pub mod local_account_router;
+pub mod money_router;
 mod router_utility;
+pub mod session_router;
+pub mod transfer_router;
 
 use account_router::*;
+use api_token_router::*;
+use campaign_router::*;
+use currency_router::*;
+use encumbrance_router::*;
 use inventory_router::*;
+use invite_router::*;
 use item_preset_router::*;
 use last_changes_router::*;
+use local_account_router::*;
+use money_router::*;
+use router_utility::hash_token;
+use session_router::*;
+use transfer_router::*;
 
 /// Returns all inventory-related routes.
 pub fn get_inventory_routes() -> Vec<Route> {
@@ -28,10 +51,19 @@ pub fn get_inventory_routes() -> Vec<Route> {
         edit_inventory,
         add_share_to_inventory,
         remove_share_from_inventory,
+        get_inventory_roles,
+        set_inventory_role,
+        remove_inventory_role,
+        transfer_inventory_ownership,
         delete_inventory,
         edit_item,
         delete_item_from_inventory,
-        add_note_to_item
+        add_note_to_item,
+        add_secret_item_to_inventory,
+        reveal_item,
+        move_item,
+        undo_inventory_change,
+        redo_inventory_change
     ]
 }
 
@@ -42,17 +74,117 @@ pub fn get_account_routes() -> Vec<Route> {
         is_account_dm,
         callback,
         login,
+        link_identity,
+        get_providers,
+        get_identities,
+        unlink_identity,
         account_info,
         user_logged_in,
         logout,
         is_locked,
-        toggle_lock
+        toggle_lock,
+        set_lock,
+        unset_lock,
+        grant_dm,
+        revoke_dm,
+        get_role_changes,
+        delete_account
     ]
 }
 
 /// Returns all last-changes-related routes.
 pub fn get_last_changes_routes() -> Vec<Route> {
-    routes![last_changes]
+    routes![last_changes, last_changes_stream, sync]
+}
+
+/// Returns all money-related routes.
+pub fn get_money_routes() -> Vec<Route> {
+    routes![
+        post_transaction,
+        get_coins,
+        post_coins,
+        transfer_money,
+        split_loot,
+        get_ledger,
+        get_party_ledger,
+        get_wealth_report
+    ]
+}
+
+/// Returns all item transfer-related routes.
+pub fn get_transfer_routes() -> Vec<Route> {
+    routes![
+        transfer_item,
+        get_gifts,
+        accept_gift,
+        decline_gift,
+        split_item,
+        merge_items
+    ]
+}
+
+/// Returns all encumbrance-related routes.
+pub fn get_encumbrance_routes() -> Vec<Route> {
+    routes![get_encumbrance, configure_encumbrance]
+}
+
+/// Returns all currency-related routes.
+pub fn get_currency_routes() -> Vec<Route> {
+    routes![
+        get_all_currencies,
+        create_currency,
+        modify_currency,
+        delete_currency
+    ]
+}
+
+/// Returns all campaign-related routes.
+pub fn get_campaign_routes() -> Vec<Route> {
+    routes![
+        get_campaigns,
+        get_open_campaigns,
+        create_campaign,
+        modify_campaign,
+        delete_campaign,
+        get_campaign_members,
+        join_campaign,
+        add_campaign_member,
+        remove_campaign_member,
+        grant_campaign_dm,
+        revoke_campaign_dm,
+        get_campaign_role_changes
+    ]
+}
+
+/// Returns all invite-related routes.
+pub fn get_invite_routes() -> Vec<Route> {
+    routes![
+        create_invite,
+        get_all_invites,
+        get_invite_redemptions,
+        revoke_invite,
+        redeem_invite
+    ]
+}
+
+/// Returns all API token-related routes.
+pub fn get_api_token_routes() -> Vec<Route> {
+    routes![create_api_token, get_api_tokens, revoke_api_token]
+}
+
+/// Returns all session-related routes.
+pub fn get_session_routes() -> Vec<Route> {
+    routes![get_sessions, revoke_session, revoke_all_sessions]
+}
+
+/// Returns all routes of local accounts.
+pub fn get_local_account_routes() -> Vec<Route> {
+    routes![
+        register_local_account,
+        login_local_account,
+        change_password,
+        reset_password
+    ]
 }
 
 /// Returns all item preset-related routes.
@@ -66,27 +198,127 @@ pub fn get_item_preset_routes() -> Vec<Route> {
     ]
 }
 
-/// Extractor for authenticated users based on a private `user_id` cookie.
+/// Extractor for authenticated users based on an `Authorization: Bearer` API token or the private session cookie.
+///
+/// Read-only tokens are refused for any request but `GET`. With sliding renewal every request extends the session and its cookie.
 pub struct AuthenticatedUser {
     /// The user id of the authenticated user
     pub user_id: String,
+    /// The session of the request, not set for API tokens
+    pub session_uuid: Option<String>,
 }
 
 #[rocket::async_trait]
 impl<'r> FromRequest<'r> for AuthenticatedUser {
     type Error = ();
     async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        if let Some(authorization) = request.headers().get_one("Authorization") {
+            let Some(token) = authorization.strip_prefix("Bearer ") else {
+                return Outcome::Error((Status::Unauthorized, ()));
+            };
+            let Some(tok_rep) = request.rocket().state::<ApiTokenRepository>() else {
+                return Outcome::Error((Status::InternalServerError, ()));
+            };
+            return match tok_rep.authenticate(&hash_token(token.trim())).await {
+                Ok(Some(api_token)) if api_token.read_only && request.method() != Method::Get => {
+                    Outcome::Error((Status::Forbidden, ()))
+                }
+                Ok(Some(api_token)) => Outcome::Success(AuthenticatedUser {
+                    user_id: api_token.user_uuid,
+                    session_uuid: None,
+                }),
+                Ok(None) => Outcome::Error((Status::Unauthorized, ())),
+                Err(_) => Outcome::Error((Status::InternalServerError, ())),
+            };
+        }
+
         let cookies = request.cookies();
+        let Some(cookie) = cookies.get_private(SESSION_COOKIE) else {
+            return Outcome::Error((Status::Unauthorized, ()));
+        };
+        let (Some(ses_rep), Some(config)) = (
+            request.rocket().state::<SessionRepository>(),
+            request.rocket().state::<SessionConfig>(),
+        ) else {
+            return Outcome::Error((Status::InternalServerError, ()));
+        };
+        match ses_rep
+            .authenticate(&hash_token(cookie.value()), config.renew_hours())
+            .await
+        {
+            Ok(Some(session)) => {
+                if config.sliding {
+                    set_session_cookie(cookies, cookie.value().to_string(), config);
+                }
+                Outcome::Success(AuthenticatedUser {
+                    user_id: session.user_uuid,
+                    session_uuid: Some(session.uuid),
+                })
+            }
+            Ok(None) => {
+                cookies.remove_private(SESSION_COOKIE);
+                Outcome::Error((Status::Unauthorized, ()))
+            }
+            Err(_) => Outcome::Error((Status::InternalServerError, ())),
+        }
+    }
+}
+
+/// Extractor for users logged in with the session cookie, for endpoints managing credentials or the account.
+///
+/// API tokens are refused with `403`, so a leaked token can not create new tokens, end sessions or delete the account.
+pub struct SessionUser {
+    /// The user id of the authenticated user
+    pub user_id: String,
+    /// The session of the request
+    pub session_uuid: String,
+}
 
-        if let Some(cookie) = cookies.get_private("user_id") {
-            let user_id = cookie.value().to_string();
-            Outcome::Success(AuthenticatedUser { user_id })
-        } else {
-            Outcome::Error((Status::Unauthorized, ()))
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for SessionUser {
+    type Error = ();
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        match request.guard::<AuthenticatedUser>().await {
+            Outcome::Success(AuthenticatedUser {
+                user_id,
+                session_uuid: Some(session_uuid),
+            }) => Outcome::Success(SessionUser {
+                user_id,
+                session_uuid,
+            }),
+            Outcome::Success(_) => Outcome::Error((Status::Forbidden, ())),
+            Outcome::Error(error) => Outcome::Error(error),
+            Outcome::Forward(status) => Outcome::Forward(status),
         }
     }
 }
 
+/// The `User-Agent` header of a request, stored with the sessions started by it.
+pub struct UserAgent(pub Option<String>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for UserAgent {
+    type Error = ();
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(UserAgent(
+            request.headers().get_one("User-Agent").map(str::to_string),
+        ))
+    }
+}
+
+/// Adds the `bearer_auth` security scheme referenced by the endpoints to an API doc.
+pub struct BearerAuthAddon;
+
+impl Modify for BearerAuthAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer_auth",
+            SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)),
+        );
+    }
+}
+
 /// Helper to create an error for API responses.
 fn create_error(msg: &str) -> AnyhowError {
     anyhow!(msg.to_string()).into()
@@ -0,0 +1,613 @@
+use repos::model::{
+    CoinAmount, InventoryChangeKind, InventoryPermission, InventoryValue, ItemGift, ItemTypeValue,
+    MoneyTransaction, RawInventory, WealthReport,
+};
+use repos::repos::campaign_repository::CampaignRepository;
+use repos::repos::change_log_repository::ChangeLogRepository;
+use repos::repos::inventory_repository::InventoryRepository;
+use repos::repos::loot_repository::LootRepository;
+use repos::repos::money_repository::MoneyRepository;
+use repos::repos::undo_repository::UndoRepository;
+use rocket::serde::json::to_string;
+use rocket::{form::FromForm, serde::json::Json, State};
+use rocket_errors::anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use utoipa::IntoParams;
+use utoipa::OpenApi;
+use utoipa::ToSchema;
+
+use super::inventory_router::report_operation;
+use super::router_utility::{
+    user_has_permission_on_inventory, user_has_read_access_to_inventory, user_is_dm_of_campaign,
+    user_is_member_of_campaign, ACCESS_DENIAL_MESSAGE, FOREIGN_CAMPAIGN_MESSAGE,
+};
+use super::transfer_router::{report_item_change, ItemTransferMode};
+use super::{create_error, BearerAuthAddon};
+
+/// Page size used if the client does not request one.
+const DEFAULT_PAGE_SIZE: i64 = 50;
+/// Largest page size a client may request.
+const MAX_PAGE_SIZE: i64 = 200;
+
+#[derive(FromForm, ToSchema, IntoParams)]
+pub struct TransactionPostParams {
+    inventory_uuid: String,
+    /// Signed amount of money in the smallest unit, negative values withdraw money
+    delta: i32,
+    reason: Option<String>,
+}
+
+#[utoipa::path(
+    put,
+    path = "/money/transaction",
+    summary = "Post a money transaction",
+    description = r#"Books a signed amount of money with an optional reason into the ledger of an inventory and updates its money accordingly.
+The amount is converted into coins of the currencies that convert automatically, withdrawals take the smallest coins first and return change.
+Requires authentication and write access. Returns an error if access is denied or the money would become negative."#,
+    params(TransactionPostParams),
+    responses(
+        (status = 200, description = "The booked transaction", body = MoneyTransaction)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Money"
+)]
+#[put("/money/transaction?<params..>")]
+pub async fn post_transaction(
+    params: TransactionPostParams,
+    user: super::AuthenticatedUser,
+    inv_rep: &State<InventoryRepository>,
+    chg_rep: &State<ChangeLogRepository>,
+    und_rep: &State<UndoRepository>,
+    mon_rep: &State<MoneyRepository>,
+) -> Result<Json<MoneyTransaction>> {
+    if !user_has_permission_on_inventory(
+        inv_rep.inner(),
+        &params.inventory_uuid,
+        &user.user_id,
+        InventoryPermission::EditMoney,
+    )
+    .await?
+    {
+        return Err(create_error(ACCESS_DENIAL_MESSAGE));
+    }
+    let old_inventory = inv_rep.get_raw_inventory(&params.inventory_uuid).await?;
+    let transaction = mon_rep
+        .post_transaction(
+            &params.inventory_uuid,
+            &user.user_id,
+            params.delta,
+            params.reason.as_deref(),
+        )
+        .await?;
+    let new_inventory = inv_rep.get_raw_inventory(&params.inventory_uuid).await?;
+    report_operation(
+        chg_rep,
+        und_rep,
+        &user.user_id,
+        &params.inventory_uuid,
+        InventoryChangeKind::InventoryEdited,
+        None,
+        Some(to_string(&old_inventory)?),
+        Some(to_string(&new_inventory)?),
+    )
+    .await?;
+    Ok(Json(transaction))
+}
+
+#[derive(FromForm, ToSchema, IntoParams)]
+pub struct CoinParams {
+    inventory_uuid: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/money/coins",
+    summary = "Retrieve the coins of an inventory",
+    description = r#"Returns the number of coins per currency held by an inventory, the most valuable currency first.
+Requires authentication and read access. Returns an error if access is denied."#,
+    params(CoinParams),
+    responses(
+        (status = 200, description = "The coins of the inventory", body = Vec<CoinAmount>)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Money"
+)]
+#[get("/money/coins?<params..>")]
+pub async fn get_coins(
+    params: CoinParams,
+    user: super::AuthenticatedUser,
+    inv_rep: &State<InventoryRepository>,
+) -> Result<Json<Vec<CoinAmount>>> {
+    if !user_has_read_access_to_inventory(
+        inv_rep.inner(),
+        params.inventory_uuid.clone(),
+        user.user_id,
+    )
+    .await?
+    {
+        return Err(create_error(ACCESS_DENIAL_MESSAGE));
+    }
+    Ok(Json(inv_rep.get_coins(&params.inventory_uuid).await?))
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CoinTransactionRequest {
+    inventory_uuid: String,
+    /// Signed number of coins per currency, negative amounts withdraw coins
+    coins: Vec<CoinAmount>,
+    reason: Option<String>,
+}
+
+#[utoipa::path(
+    put,
+    path = "/money/coins",
+    summary = "Post a transaction of coins",
+    description = r#"Adds and removes coins of specific currencies without converting between them, e.g. to exchange coins or to pay in a currency that does not convert automatically.
+The transaction booked into the ledger carries the change in value. Undo only restores the value of an inventory and not its coins, so coin transactions are not recorded for undo.
+Requires authentication and write access. Returns an error if access is denied or the coins of a currency would become negative."#,
+    request_body = CoinTransactionRequest,
+    responses(
+        (status = 200, description = "The booked transaction", body = MoneyTransaction)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Money"
+)]
+#[put("/money/coins", data = "<json_data>")]
+pub async fn post_coins(
+    json_data: Json<CoinTransactionRequest>,
+    user: super::AuthenticatedUser,
+    inv_rep: &State<InventoryRepository>,
+    chg_rep: &State<ChangeLogRepository>,
+    mon_rep: &State<MoneyRepository>,
+) -> Result<Json<MoneyTransaction>> {
+    if !user_has_permission_on_inventory(
+        inv_rep.inner(),
+        &json_data.inventory_uuid,
+        &user.user_id,
+        InventoryPermission::EditMoney,
+    )
+    .await?
+    {
+        return Err(create_error(ACCESS_DENIAL_MESSAGE));
+    }
+    let old_inventory = inv_rep.get_raw_inventory(&json_data.inventory_uuid).await?;
+    let transaction = mon_rep
+        .post_coins(
+            &json_data.inventory_uuid,
+            &user.user_id,
+            &json_data.coins,
+            json_data.reason.as_deref(),
+        )
+        .await?;
+    let new_inventory = inv_rep.get_raw_inventory(&json_data.inventory_uuid).await?;
+    report_money_change(chg_rep, &user.user_id, &old_inventory, &new_inventory).await?;
+    Ok(Json(transaction))
+}
+
+/// Reports a money change on an inventory that is only logged and not recorded for undo.
+///
+/// Transfers span several inventories and coin transactions change coins undo can not restore.
+async fn report_money_change(
+    chg_rep: &ChangeLogRepository,
+    user_id: &str,
+    old_inventory: &RawInventory,
+    new_inventory: &RawInventory,
+) -> Result<()> {
+    crate::report_change_on_inventory!(
+        chg_rep,
+        user_id,
+        &new_inventory.uuid,
+        InventoryChangeKind::InventoryEdited,
+        None,
+        Some(&to_string(old_inventory)?),
+        Some(&to_string(new_inventory)?)
+    );
+    Ok(())
+}
+
+#[derive(FromForm, ToSchema, IntoParams)]
+pub struct MoneyTransferParams {
+    source_inventory_uuid: String,
+    target_inventory_uuid: String,
+    /// Positive amount of money to move
+    amount: i32,
+    reason: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MoneyTransferResponse {
+    withdrawal: MoneyTransaction,
+    deposit: MoneyTransaction,
+}
+
+#[utoipa::path(
+    put,
+    path = "/money/transfer",
+    summary = "Transfer money to another inventory",
+    description = r#"Moves money from the source to the target inventory in a single transaction.
+Both ledgers get a transaction referring to the other inventory.
+Requires authentication and write access to the source. Returns an error if access is denied, the inventories belong to different campaigns or the source lacks the money."#,
+    params(MoneyTransferParams),
+    responses(
+        (status = 200, description = "The booked transactions", body = MoneyTransferResponse)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Money"
+)]
+#[put("/money/transfer?<params..>")]
+pub async fn transfer_money(
+    params: MoneyTransferParams,
+    user: super::AuthenticatedUser,
+    inv_rep: &State<InventoryRepository>,
+    chg_rep: &State<ChangeLogRepository>,
+    mon_rep: &State<MoneyRepository>,
+) -> Result<Json<MoneyTransferResponse>> {
+    if !user_has_permission_on_inventory(
+        inv_rep.inner(),
+        &params.source_inventory_uuid,
+        &user.user_id,
+        InventoryPermission::EditMoney,
+    )
+    .await?
+    {
+        return Err(create_error(ACCESS_DENIAL_MESSAGE));
+    }
+    let old_source = inv_rep
+        .get_raw_inventory(&params.source_inventory_uuid)
+        .await?;
+    let old_target = inv_rep
+        .get_raw_inventory(&params.target_inventory_uuid)
+        .await?;
+    if old_source.campaign_uuid != old_target.campaign_uuid {
+        return Err(create_error(FOREIGN_CAMPAIGN_MESSAGE));
+    }
+    let (withdrawal, deposit) = mon_rep
+        .transfer_money(
+            &params.source_inventory_uuid,
+            &params.target_inventory_uuid,
+            &user.user_id,
+            params.amount,
+            params.reason.as_deref(),
+        )
+        .await?;
+    for old_inventory in [old_source, old_target] {
+        let new_inventory = inv_rep.get_raw_inventory(&old_inventory.uuid).await?;
+        report_money_change(chg_rep, &user.user_id, &old_inventory, &new_inventory).await?;
+    }
+    Ok(Json(MoneyTransferResponse {
+        withdrawal,
+        deposit,
+    }))
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LootSplitRequest {
+    source_inventory_uuid: String,
+    target_inventory_uuids: Vec<String>,
+    /// Money to divide, defaults to 0
+    #[serde(default)]
+    amount: i32,
+    /// Items of the source whose whole stack is divided
+    #[serde(default)]
+    item_uuids: Vec<String>,
+    reason: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LootSplitResponse {
+    transactions: Vec<MoneyTransaction>,
+    /// Items held as gifts for targets the user can not put items into
+    gifts: Vec<ItemGift>,
+    /// Money that could not be divided evenly and stayed in the source
+    remainder: i32,
+}
+
+#[utoipa::path(
+    put,
+    path = "/money/split",
+    summary = "Split loot across inventories",
+    description = r#"Divides an amount of money and the stacks of the given items of the source evenly across the target inventories in a single transaction.
+Everything that can not be divided evenly stays in the source. Every recipient gets a ledger transaction referring to the source.
+Requires authentication and the permission to edit money of the source, dividing items also the permission to edit its items.
+Items go into the targets like `/inventory/item/transfer`: directly if the user may edit the items of a target, otherwise in gift mode as a gift the recipient has to accept.
+Returns an error if access is denied, an inventory belongs to another campaign or the source lacks the money."#,
+    request_body = LootSplitRequest,
+    responses(
+        (status = 200, description = "The booked transactions and the remainder", body = LootSplitResponse)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Money"
+)]
+#[put("/money/split", data = "<json_data>")]
+pub async fn split_loot(
+    json_data: Json<LootSplitRequest>,
+    user: super::AuthenticatedUser,
+    inv_rep: &State<InventoryRepository>,
+    chg_rep: &State<ChangeLogRepository>,
+    lot_rep: &State<LootRepository>,
+    mode: &State<ItemTransferMode>,
+) -> Result<Json<LootSplitResponse>> {
+    let mut permissions = vec![InventoryPermission::EditMoney];
+    if !json_data.item_uuids.is_empty() {
+        permissions.push(InventoryPermission::EditItems);
+    }
+    for permission in permissions {
+        if !user_has_permission_on_inventory(
+            inv_rep.inner(),
+            &json_data.source_inventory_uuid,
+            &user.user_id,
+            permission,
+        )
+        .await?
+        {
+            return Err(create_error(ACCESS_DENIAL_MESSAGE));
+        }
+    }
+    let mut old_inventories = vec![
+        inv_rep
+            .get_raw_inventory(&json_data.source_inventory_uuid)
+            .await?,
+    ];
+    for target in &json_data.target_inventory_uuids {
+        old_inventories.push(inv_rep.get_raw_inventory(target).await?);
+    }
+    if old_inventories
+        .iter()
+        .any(|inventory| inventory.campaign_uuid != old_inventories[0].campaign_uuid)
+    {
+        return Err(create_error(FOREIGN_CAMPAIGN_MESSAGE));
+    }
+    let mut gift_targets = Vec::new();
+    if !json_data.item_uuids.is_empty() {
+        for target in &json_data.target_inventory_uuids {
+            if !user_has_permission_on_inventory(
+                inv_rep.inner(),
+                target,
+                &user.user_id,
+                InventoryPermission::EditItems,
+            )
+            .await?
+            {
+                if *mode.inner() != ItemTransferMode::Gift {
+                    return Err(create_error(ACCESS_DENIAL_MESSAGE));
+                }
+                gift_targets.push(target.clone());
+            }
+        }
+    }
+    let split = lot_rep
+        .split_loot(
+            &json_data.source_inventory_uuid,
+            &json_data.target_inventory_uuids,
+            &gift_targets,
+            &user.user_id,
+            json_data.amount,
+            &json_data.item_uuids,
+            json_data.reason.as_deref(),
+        )
+        .await?;
+    if !split.transactions.is_empty() {
+        for old_inventory in old_inventories {
+            let new_inventory = inv_rep.get_raw_inventory(&old_inventory.uuid).await?;
+            report_money_change(chg_rep, &user.user_id, &old_inventory, &new_inventory).await?;
+        }
+    }
+    for change in &split.item_changes {
+        report_item_change(chg_rep, &user.user_id, change).await?;
+    }
+    Ok(Json(LootSplitResponse {
+        transactions: split.transactions,
+        gifts: split.gifts.into_iter().map(ItemGift::redacted).collect(),
+        remainder: split.remainder,
+    }))
+}
+
+#[derive(FromForm, ToSchema, IntoParams)]
+pub struct LedgerParams {
+    inventory_uuid: String,
+    /// Zero based page, defaults to 0
+    page: Option<i64>,
+    /// Defaults to 50, at most 200
+    page_size: Option<i64>,
+}
+
+#[derive(FromForm, ToSchema, IntoParams)]
+pub struct PartyLedgerParams {
+    campaign_uuid: String,
+    /// Zero based page, defaults to 0
+    page: Option<i64>,
+    /// Defaults to 50, at most 200
+    page_size: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LedgerResponse {
+    transactions: Vec<MoneyTransaction>,
+    /// Total number of transactions over all pages
+    total: i64,
+    page: i64,
+    page_size: i64,
+}
+
+/// Loads a page of the ledger of the given inventories, newest transactions first.
+async fn get_ledger_page(
+    mon_rep: &MoneyRepository,
+    inventory_uuids: &[String],
+    page: Option<i64>,
+    page_size: Option<i64>,
+) -> Result<LedgerResponse> {
+    let page = page.unwrap_or(0).max(0);
+    let page_size = page_size
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    let (transactions, total) = mon_rep
+        .get_ledger(inventory_uuids, page_size, page * page_size)
+        .await?;
+    Ok(LedgerResponse {
+        transactions,
+        total,
+        page,
+        page_size,
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/money/ledger",
+    summary = "Retrieve the money ledger of an inventory",
+    description = r#"Returns a page of the transactions of an inventory, newest first.
+Requires authentication and read access. Returns an error if access is denied."#,
+    params(LedgerParams),
+    responses(
+        (status = 200, description = "A page of the ledger", body = LedgerResponse)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Money"
+)]
+#[get("/money/ledger?<params..>")]
+pub async fn get_ledger(
+    params: LedgerParams,
+    user: super::AuthenticatedUser,
+    inv_rep: &State<InventoryRepository>,
+    mon_rep: &State<MoneyRepository>,
+) -> Result<Json<LedgerResponse>> {
+    if !user_has_read_access_to_inventory(
+        inv_rep.inner(),
+        params.inventory_uuid.clone(),
+        user.user_id,
+    )
+    .await?
+    {
+        return Err(create_error(ACCESS_DENIAL_MESSAGE));
+    }
+    Ok(Json(
+        get_ledger_page(
+            mon_rep.inner(),
+            &[params.inventory_uuid],
+            params.page,
+            params.page_size,
+        )
+        .await?,
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/money/partyLedger",
+    summary = "Retrieve the money ledger of all inventories of a campaign",
+    description = r#"Returns a page of the transactions of all inventories of the campaign, newest first.
+Requires authentication and DM privileges in the campaign. Returns an error if the user is not a DM of the campaign."#,
+    params(PartyLedgerParams),
+    responses(
+        (status = 200, description = "A page of the ledger", body = LedgerResponse)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Money"
+)]
+#[get("/money/partyLedger?<params..>")]
+pub async fn get_party_ledger(
+    params: PartyLedgerParams,
+    user: super::AuthenticatedUser,
+    inv_rep: &State<InventoryRepository>,
+    cmp_rep: &State<CampaignRepository>,
+    mon_rep: &State<MoneyRepository>,
+) -> Result<Json<LedgerResponse>> {
+    if !user_is_dm_of_campaign(cmp_rep.inner(), &params.campaign_uuid, &user.user_id).await? {
+        return Err(create_error(ACCESS_DENIAL_MESSAGE));
+    }
+    let inventories = inv_rep
+        .get_campaign_inventory_ids(&params.campaign_uuid)
+        .await?;
+    Ok(Json(
+        get_ledger_page(mon_rep.inner(), &inventories, params.page, params.page_size).await?,
+    ))
+}
+
+#[derive(FromForm, ToSchema, IntoParams)]
+pub struct WealthParams {
+    campaign_uuid: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/money/wealth",
+    summary = "Retrieve the wealth of all readable inventories of a campaign",
+    description = r#"Returns the cash and the item value (price x amount) of every inventory of the campaign the user can read, broken down by item type, and the totals over all of them.
+Unidentified items are counted with their disguised item type and without value, unless the user is a DM of the campaign.
+Requires authentication and membership in the campaign."#,
+    params(WealthParams),
+    responses(
+        (status = 200, description = "Wealth per inventory and in total", body = WealthReport)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Money"
+)]
+#[get("/money/wealth?<params..>")]
+pub async fn get_wealth_report(
+    params: WealthParams,
+    user: super::AuthenticatedUser,
+    inv_rep: &State<InventoryRepository>,
+    cmp_rep: &State<CampaignRepository>,
+) -> Result<Json<WealthReport>> {
+    if !user_is_member_of_campaign(cmp_rep.inner(), &params.campaign_uuid, &user.user_id).await? {
+        return Err(create_error(ACCESS_DENIAL_MESSAGE));
+    }
+    let is_dm =
+        user_is_dm_of_campaign(cmp_rep.inner(), &params.campaign_uuid, &user.user_id).await?;
+    let campaign_inventories = inv_rep
+        .get_campaign_inventory_ids(&params.campaign_uuid)
+        .await?;
+    let inventories: Vec<String> = inv_rep
+        .get_owned_and_readable_inventory_ids(&user.user_id)
+        .await?
+        .into_iter()
+        .filter(|inventory| campaign_inventories.contains(inventory))
+        .collect();
+    Ok(Json(inv_rep.get_wealth_report(&inventories, is_dm).await?))
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    modifiers(&BearerAuthAddon),
+    paths(
+        post_transaction,
+        get_coins,
+        post_coins,
+        transfer_money,
+        split_loot,
+        get_ledger,
+        get_party_ledger,
+        get_wealth_report
+    ),
+    components(
+        schemas(
+            TransactionPostParams,
+            CoinParams,
+            CoinTransactionRequest,
+            CoinAmount,
+            MoneyTransferParams,
+            MoneyTransferResponse,
+            LootSplitRequest,
+            LootSplitResponse,
+            LedgerParams,
+            PartyLedgerParams,
+            WealthParams,
+            LedgerResponse,
+            MoneyTransaction,
+            ItemGift,
+            WealthReport,
+            InventoryValue,
+            ItemTypeValue
+        )
+    ),
+    tags(
+        (name = "Money", description = "Endpoints for managing the money of inventories")
+    )
+)]
+pub struct MoneyApiDoc;
@@ -1,7 +1,15 @@
-use repos::repos::{inventory_repository::InventoryRepository, user_repository::UserRepository};
+use anyhow::anyhow;
+use openssl::sha::sha256;
+use repos::model::{FrontendItemView, InventoryPermission};
+use repos::repos::{
+    campaign_repository::CampaignRepository, inventory_repository::InventoryRepository,
+    invite_repository::InviteRepository, item_preset_repository::ItemPresetRepository,
+    setting_repository::SettingRepository, user_repository::UserRepository,
+};
 use rocket_errors::anyhow::Result;
 
 pub static ACCESS_DENIAL_MESSAGE: &str = "no access";
+pub static FOREIGN_CAMPAIGN_MESSAGE: &str = "the inventories belong to different campaigns";
 
 /// Checks if a user has read access to a specific inventory.
 ///
@@ -11,7 +19,7 @@ pub static ACCESS_DENIAL_MESSAGE: &str = "no access";
 /// * `user_id` - The UUID of the user.
 ///
 /// # Returns
-/// `true` if the user is a reader of the inventory, otherwise `false`.
+/// `true` if the user is a reader of the inventory and a member of its campaign, otherwise `false`.
 pub async fn user_has_read_access_to_inventory(
     inv_rep: &InventoryRepository,
     inventory_uuid: String,
@@ -21,57 +29,218 @@ pub async fn user_has_read_access_to_inventory(
         .get_full_inventory(&inventory_uuid)
         .await?
         .reader
-        .contains(&user_id))
+        .contains(&user_id)
+        && inv_rep
+            .is_campaign_member(&inventory_uuid, &user_id)
+            .await?)
 }
 
-/// Checks if a user has write access to a specific inventory.
+/// Checks if a user has a permission on a specific inventory.
+///
+/// The owner holds every permission and a role grants exactly the permissions of the role. Without a role
+/// writers hold all permissions but sharing and owning, readers may only view.
 ///
 /// # Arguments
 /// * `inv_rep` - Reference to the inventory repository.
 /// * `inventory_uuid` - The UUID of the inventory.
 /// * `user_id` - The UUID of the user.
+/// * `permission` - The permission to check.
 ///
 /// # Returns
-/// `true` if the user is a writer of the inventory, otherwise `false`.
-pub async fn user_has_write_access_to_inventory(
+/// `true` if the user holds the permission and is a member of the inventory's campaign, otherwise `false`.
+pub async fn user_has_permission_on_inventory(
     inv_rep: &InventoryRepository,
-    inventory_uuid: String,
-    user_id: String,
+    inventory_uuid: &str,
+    user_id: &str,
+    permission: InventoryPermission,
 ) -> Result<bool> {
-    Ok(inv_rep
-        .get_full_inventory(&inventory_uuid)
-        .await?
-        .writer
-        .contains(&user_id))
+    if !inv_rep.is_campaign_member(inventory_uuid, user_id).await? {
+        return Ok(false);
+    }
+    if inv_rep.get_raw_inventory(inventory_uuid).await?.owner_uuid == user_id {
+        return Ok(true);
+    }
+    if let Some(role) = inv_rep.get_role(inventory_uuid, user_id).await? {
+        return Ok(role.permits(permission));
+    }
+    let user_id = user_id.to_string();
+    Ok(match permission {
+        InventoryPermission::View => inv_rep
+            .get_readers(inventory_uuid)
+            .await?
+            .contains(&user_id),
+        InventoryPermission::Share | InventoryPermission::Own => false,
+        _ => inv_rep
+            .get_writers(inventory_uuid)
+            .await?
+            .contains(&user_id),
+    })
 }
 
-/// Checks if a user is a Dungeon Master (DM).
+/// Checks if a user is a server Dungeon Master (DM), who administers the server itself.
+///
+/// DMs of a campaign are checked with `user_is_dm_of_campaign`.
 ///
 /// # Arguments
 /// * `usr_rep` - Reference to the user repository.
 /// * `user_id` - The UUID of the user.
 ///
 /// # Returns
-/// `true` if the user is a DM, otherwise `false`.
+/// `true` if the user is a server DM, otherwise `false`.
 pub async fn user_is_dm(usr_rep: &UserRepository, user_id: String) -> Result<bool> {
     Ok(usr_rep.get_user(&user_id).await?.dm == 1)
 }
 
-/// Checks if a user is the creator (owner) of a specific inventory.
+/// Checks if a user is a member of a campaign.
+///
+/// # Arguments
+/// * `cmp_rep` - Reference to the campaign repository.
+/// * `campaign_uuid` - The UUID of the campaign.
+/// * `user_id` - The UUID of the user.
+///
+/// # Returns
+/// `true` if the user is a player or DM of the campaign, otherwise `false`.
+pub async fn user_is_member_of_campaign(
+    cmp_rep: &CampaignRepository,
+    campaign_uuid: &str,
+    user_id: &str,
+) -> Result<bool> {
+    Ok(cmp_rep.is_member(campaign_uuid, user_id).await?)
+}
+
+/// Checks if a user is a Dungeon Master (DM) of a campaign.
+///
+/// # Arguments
+/// * `cmp_rep` - Reference to the campaign repository.
+/// * `campaign_uuid` - The UUID of the campaign.
+/// * `user_id` - The UUID of the user.
+///
+/// # Returns
+/// `true` if the user is a DM of the campaign, otherwise `false`.
+pub async fn user_is_dm_of_campaign(
+    cmp_rep: &CampaignRepository,
+    campaign_uuid: &str,
+    user_id: &str,
+) -> Result<bool> {
+    Ok(cmp_rep.is_dm(campaign_uuid, user_id).await?)
+}
+
+/// Checks if a user is a Dungeon Master (DM) of the campaign an inventory belongs to.
 ///
 /// # Arguments
 /// * `inv_rep` - Reference to the inventory repository.
+/// * `cmp_rep` - Reference to the campaign repository.
 /// * `inventory_uuid` - The UUID of the inventory.
 /// * `user_id` - The UUID of the user.
 ///
 /// # Returns
-/// `true` if the user is the creator of the inventory, otherwise `false`.
-pub async fn user_is_creator_of_inventory(
+/// `true` if the user is a DM of the inventory's campaign, otherwise `false`.
+pub async fn user_is_dm_of_inventory(
     inv_rep: &InventoryRepository,
-    inventory_uuid: String,
-    user_id: String,
+    cmp_rep: &CampaignRepository,
+    inventory_uuid: &str,
+    user_id: &str,
+) -> Result<bool> {
+    let inventory = inv_rep.get_raw_inventory(inventory_uuid).await?;
+    user_is_dm_of_campaign(cmp_rep, &inventory.campaign_uuid, user_id).await
+}
+
+/// Checks if a new user may register, while the server is locked only with an invite of a server DM.
+///
+/// # Arguments
+/// * `set_rep` - Reference to the setting repository.
+/// * `ivt_rep` - Reference to the invite repository.
+/// * `invite_code` - The invitation code the user registers with, if any.
+///
+/// # Returns
+/// `true` if the server is unlocked or the invite allows registration, otherwise `false`.
+pub async fn registration_allowed(
+    set_rep: &SettingRepository,
+    ivt_rep: &InviteRepository,
+    invite_code: Option<&str>,
 ) -> Result<bool> {
-    Ok(inv_rep.get_raw_inventory(&inventory_uuid).await?.owner_uuid == user_id)
+    if !set_rep.is_locked().await? {
+        return Ok(true);
+    }
+    match invite_code {
+        Some(code) => Ok(ivt_rep.allows_registration(code).await?),
+        None => Ok(false),
+    }
+}
+
+/// Loads an item the way the user may see it, only DMs of the inventory's campaign get the DM note and the real preset of a disguised item.
+///
+/// # Arguments
+/// * `inv_rep` - Reference to the inventory repository.
+/// * `cmp_rep` - Reference to the campaign repository.
+/// * `inventory_uuid` - The UUID of the inventory holding the item.
+/// * `item_uuid` - The UUID of the item.
+/// * `user_id` - The UUID of the user.
+///
+/// # Returns
+/// The item as seen by the user, an error if the inventory does not hold it.
+pub async fn item_view_for_user(
+    inv_rep: &InventoryRepository,
+    cmp_rep: &CampaignRepository,
+    inventory_uuid: &str,
+    item_uuid: &str,
+    user_id: &str,
+) -> Result<FrontendItemView> {
+    let is_dm = user_is_dm_of_inventory(inv_rep, cmp_rep, inventory_uuid, user_id).await?;
+    let item = inv_rep
+        .get_frontend_items_by_uuids(inventory_uuid, &[item_uuid.to_string()])
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("the item does not exist"))?;
+    Ok(FrontendItemView::for_viewer(item, is_dm))
+}
+
+/// Checks if two inventories belong to the same campaign, items and money never leave a campaign.
+///
+/// # Arguments
+/// * `inv_rep` - Reference to the inventory repository.
+/// * `inventory_uuid` - The UUID of the first inventory.
+/// * `other_inventory_uuid` - The UUID of the second inventory.
+///
+/// # Returns
+/// `true` if both inventories belong to the same campaign, otherwise `false`.
+pub async fn inventories_share_campaign(
+    inv_rep: &InventoryRepository,
+    inventory_uuid: &str,
+    other_inventory_uuid: &str,
+) -> Result<bool> {
+    Ok(inv_rep
+        .get_raw_inventory(inventory_uuid)
+        .await?
+        .campaign_uuid
+        == inv_rep
+            .get_raw_inventory(other_inventory_uuid)
+            .await?
+            .campaign_uuid)
+}
+
+/// Checks if an item preset belongs to the campaign of an inventory, so its items may be added to it.
+///
+/// # Arguments
+/// * `inv_rep` - Reference to the inventory repository.
+/// * `ipr_rep` - Reference to the item preset repository.
+/// * `inventory_uuid` - The UUID of the inventory.
+/// * `item_preset_uuid` - The UUID of the item preset.
+///
+/// # Returns
+/// `true` if the preset belongs to the inventory's campaign, otherwise `false`.
+pub async fn preset_belongs_to_campaign_of_inventory(
+    inv_rep: &InventoryRepository,
+    ipr_rep: &ItemPresetRepository,
+    inventory_uuid: &str,
+    item_preset_uuid: &str,
+) -> Result<bool> {
+    Ok(inv_rep
+        .get_raw_inventory(inventory_uuid)
+        .await?
+        .campaign_uuid
+        == ipr_rep.get_by_uuid(item_preset_uuid).await?.campaign_uuid)
 }
 
 /// Checks if a user has read access to a specific item preset (by being a reader or owner of any inventory containing the preset).
@@ -118,5 +287,13 @@ pub async fn has_access_to_item(
             break;
         }
     }
-    return Ok(has_access);
+    Ok(has_access)
+}
+
+/// Returns the hex encoded SHA-256 hash API tokens and session tokens are stored and looked up by.
+pub fn hash_token(token: &str) -> String {
+    sha256(token.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
 }
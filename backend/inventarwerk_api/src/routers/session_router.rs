@@ -0,0 +1,211 @@
+use repos::model::Session;
+use repos::repos::session_repository::SessionRepository;
+use rocket::http::{Cookie, CookieJar, SameSite, Status};
+use rocket::serde::{Deserialize, Serialize};
+use rocket::time::Duration;
+use rocket::{form::FromForm, serde::json::Json, State};
+use rocket_errors::anyhow::Result;
+use std::env;
+
+use utoipa::IntoParams;
+use utoipa::OpenApi;
+use utoipa::ToSchema;
+
+use super::router_utility::hash_token;
+use super::BearerAuthAddon;
+use crate::identity_provider::random_token;
+
+/// Name of the private cookie holding the session token.
+pub(super) const SESSION_COOKIE: &str = "session";
+
+/// Lifetime of sessions, configured through `SESSION_LIFETIME_HOURS` and `SESSION_SLIDING`.
+#[derive(Clone, Copy, Debug)]
+pub struct SessionConfig {
+    /// Hours until a session expires
+    pub lifetime_hours: i32,
+    /// Every request extends the session to expire `lifetime_hours` from then
+    pub sliding: bool,
+}
+
+impl SessionConfig {
+    /// Reads the lifetime from `SESSION_LIFETIME_HOURS`, defaults to 720 hours (30 days),
+    /// and the sliding renewal from `SESSION_SLIDING` (`true` or `false`), defaults to `true`.
+    pub fn from_env() -> Self {
+        let lifetime_hours = match env::var("SESSION_LIFETIME_HOURS") {
+            Err(_) => 720,
+            Ok(hours) => match hours.parse() {
+                Ok(hours) if hours > 0 => hours,
+                _ => panic!(
+                    "SESSION_LIFETIME_HOURS must be a positive number, got {}",
+                    hours
+                ),
+            },
+        };
+        let sliding = match env::var("SESSION_SLIDING").as_deref() {
+            Err(_) | Ok("true") => true,
+            Ok("false") => false,
+            Ok(sliding) => panic!("SESSION_SLIDING must be true or false, got {}", sliding),
+        };
+        Self {
+            lifetime_hours,
+            sliding,
+        }
+    }
+
+    /// Returns the hours a used session is renewed for, `None` without sliding renewal.
+    pub fn renew_hours(&self) -> Option<i32> {
+        self.sliding.then_some(self.lifetime_hours)
+    }
+}
+
+/// Sets the session cookie, which expires together with the session.
+pub(super) fn set_session_cookie(cookies: &CookieJar<'_>, token: String, config: &SessionConfig) {
+    let cookie = Cookie::build((SESSION_COOKIE, token))
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .max_age(Duration::hours(config.lifetime_hours.into()));
+    cookies.add_private(cookie);
+}
+
+/// Logs the user in by starting a session and setting its cookie, which is read by `AuthenticatedUser`.
+pub(super) async fn start_session(
+    cookies: &CookieJar<'_>,
+    ses_rep: &SessionRepository,
+    config: &SessionConfig,
+    user_id: &str,
+    user_agent: &super::UserAgent,
+) -> Result<()> {
+    let token = random_token()?;
+    ses_rep
+        .create_session(
+            user_id,
+            &hash_token(&token),
+            user_agent.0.as_deref(),
+            config.lifetime_hours,
+        )
+        .await?;
+    set_session_cookie(cookies, token, config);
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionListResponse {
+    /// Session of the request
+    current_session_uuid: String,
+    sessions: Vec<Session>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/session/all",
+    summary = "Retrieve the own sessions",
+    description = r#"Returns the own sessions that have not expired, the most recently used first. Requires a login session, API tokens are refused."#,
+    responses(
+        (status = 200, description = "The sessions of the user", body = SessionListResponse)
+    ),
+    tag = "Sessions"
+)]
+#[get("/session/all")]
+pub async fn get_sessions(
+    user: super::SessionUser,
+    ses_rep: &State<SessionRepository>,
+) -> Result<Json<SessionListResponse>> {
+    Ok(Json(SessionListResponse {
+        sessions: ses_rep.get_sessions(&user.user_id).await?,
+        current_session_uuid: user.session_uuid,
+    }))
+}
+
+#[derive(FromForm, ToSchema, IntoParams)]
+pub struct SessionUUIDParams {
+    session_uuid: String,
+}
+
+#[utoipa::path(
+    delete,
+    path = "/session/revoke",
+    summary = "Revoke a session",
+    description = r#"Ends an own session, which is logged out. Revoking the current session also removes its cookie. Requires a login session, API tokens are refused."#,
+    params(SessionUUIDParams),
+    responses(
+        (status = 204, description = "Session revoked successfully"),
+        (status = 404, description = "The user has no such session")
+    ),
+    tag = "Sessions"
+)]
+#[delete("/session/revoke?<params..>")]
+pub async fn revoke_session(
+    params: SessionUUIDParams,
+    user: super::SessionUser,
+    cookies: &CookieJar<'_>,
+    ses_rep: &State<SessionRepository>,
+) -> Result<Status> {
+    if ses_rep
+        .revoke_session(&params.session_uuid, &user.user_id)
+        .await?
+        == 0
+    {
+        return Ok(Status::NotFound);
+    }
+    if user.session_uuid == params.session_uuid {
+        cookies.remove_private(SESSION_COOKIE);
+    }
+    Ok(Status::NoContent)
+}
+
+#[derive(FromForm, ToSchema, IntoParams)]
+pub struct SessionRevokeAllParams {
+    /// Keep the session of the request, defaults to false
+    keep_current: Option<bool>,
+}
+
+#[utoipa::path(
+    delete,
+    path = "/session/revokeAll",
+    summary = "Log out everywhere",
+    description = r#"Ends all own sessions, or all but the current one with `keep_current`. API tokens are not affected, see `/apiToken/revoke`.
+Requires a login session, API tokens are refused."#,
+    params(SessionRevokeAllParams),
+    responses(
+        (status = 204, description = "Sessions revoked successfully")
+    ),
+    tag = "Sessions"
+)]
+#[delete("/session/revokeAll?<params..>")]
+pub async fn revoke_all_sessions(
+    params: SessionRevokeAllParams,
+    user: super::SessionUser,
+    cookies: &CookieJar<'_>,
+    ses_rep: &State<SessionRepository>,
+) -> Result<Status> {
+    let keep_current = params.keep_current.unwrap_or(false);
+    let except = Some(user.session_uuid.as_str()).filter(|_| keep_current);
+    ses_rep.revoke_all_sessions(&user.user_id, except).await?;
+    if !keep_current {
+        cookies.remove_private(SESSION_COOKIE);
+    }
+    Ok(Status::NoContent)
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    modifiers(&BearerAuthAddon),
+    paths(
+        get_sessions,
+        revoke_session,
+        revoke_all_sessions
+    ),
+    components(
+        schemas(
+            SessionListResponse,
+            SessionUUIDParams,
+            SessionRevokeAllParams,
+            Session
+        )
+    ),
+    tags(
+        (name = "Sessions", description = "Endpoints for managing login sessions")
+    )
+)]
+pub struct SessionApiDoc;
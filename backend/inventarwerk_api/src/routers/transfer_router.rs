@@ -0,0 +1,440 @@
+use repos::model::{
+    FrontendItemView, InventoryChangeKind, InventoryPermission, ItemChange, ItemGift,
+};
+use repos::repos::campaign_repository::CampaignRepository;
+use repos::repos::change_log_repository::ChangeLogRepository;
+use repos::repos::inventory_repository::InventoryRepository;
+use repos::repos::item_transfer_repository::ItemTransferRepository;
+use rocket::http::Status;
+use rocket::serde::json::to_string;
+use rocket::{form::FromForm, serde::json::Json, State};
+use rocket_errors::anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::env;
+
+use utoipa::IntoParams;
+use utoipa::OpenApi;
+use utoipa::ToSchema;
+
+use super::router_utility::{
+    inventories_share_campaign, item_view_for_user, user_has_permission_on_inventory,
+    ACCESS_DENIAL_MESSAGE, FOREIGN_CAMPAIGN_MESSAGE,
+};
+use super::{create_error, BearerAuthAddon};
+
+/// Rule for moving items into an inventory, configured through `ITEM_TRANSFER_MODE`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ItemTransferMode {
+    /// The sender needs write access to the target inventory.
+    Write,
+    /// Without write access to the target the items are held as a gift until the recipient accepts them.
+    Gift,
+}
+
+impl ItemTransferMode {
+    /// Reads the mode from `ITEM_TRANSFER_MODE` (`write` or `gift`), defaults to `write`.
+    pub fn from_env() -> Self {
+        match env::var("ITEM_TRANSFER_MODE").as_deref() {
+            Err(_) | Ok("write") => ItemTransferMode::Write,
+            Ok("gift") => ItemTransferMode::Gift,
+            Ok(mode) => panic!("ITEM_TRANSFER_MODE must be write or gift, got {}", mode),
+        }
+    }
+}
+
+/// Reports the change of an item caused by a transfer.
+///
+/// Transfers span two inventories or stacks, so they are only logged and not recorded for undo.
+pub(super) async fn report_item_change(
+    chg_rep: &ChangeLogRepository,
+    user_id: &str,
+    change: &ItemChange,
+) -> Result<()> {
+    let item = change
+        .after
+        .as_ref()
+        .or(change.before.as_ref())
+        .expect("changed item exists");
+    let kind = match (&change.before, &change.after) {
+        (None, _) => InventoryChangeKind::ItemAdded,
+        (_, None) => InventoryChangeKind::ItemRemoved,
+        _ => InventoryChangeKind::ItemEdited,
+    };
+    let old_value = change.before.as_ref().map(to_string).transpose()?;
+    let new_value = change.after.as_ref().map(to_string).transpose()?;
+    crate::report_change_on_inventory!(
+        chg_rep,
+        user_id,
+        &item.inventory_uuid,
+        kind,
+        Some(item),
+        old_value.as_deref(),
+        new_value.as_deref()
+    );
+    Ok(())
+}
+
+#[derive(FromForm, ToSchema, IntoParams)]
+pub struct ItemTransferParams {
+    source_inventory_uuid: String,
+    target_inventory_uuid: String,
+    item_uuid: String,
+    amount: i32,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemTransferResponse {
+    /// The pending gift, `null` if the items were moved directly
+    gift: Option<ItemGift>,
+}
+
+#[utoipa::path(
+    patch,
+    path = "/inventory/item/transfer",
+    summary = "Transfer an item to another inventory",
+    description = r#"Moves the given amount of an item from the source to the target inventory in a single transaction.
+The stack is split if only a part is moved and merged into a top level stack of the same preset with the same notes in the target, otherwise a new stack is created.
+Requires authentication and write access to the source. Write access to the target is required as well,
+unless the server runs in gift mode, where the items are held as a gift until the recipient accepts them.
+Both inventories have to belong to the same campaign."#,
+    params(ItemTransferParams),
+    responses(
+        (status = 200, description = "Item transferred or gift created", body = ItemTransferResponse)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Transfers"
+)]
+#[patch("/inventory/item/transfer?<params..>")]
+pub async fn transfer_item(
+    params: ItemTransferParams,
+    user: super::AuthenticatedUser,
+    inv_rep: &State<InventoryRepository>,
+    chg_rep: &State<ChangeLogRepository>,
+    itr_rep: &State<ItemTransferRepository>,
+    mode: &State<ItemTransferMode>,
+) -> Result<Json<ItemTransferResponse>> {
+    if !user_has_permission_on_inventory(
+        inv_rep.inner(),
+        &params.source_inventory_uuid,
+        &user.user_id,
+        InventoryPermission::EditItems,
+    )
+    .await?
+    {
+        return Err(create_error(ACCESS_DENIAL_MESSAGE));
+    }
+    if !inventories_share_campaign(
+        inv_rep.inner(),
+        &params.source_inventory_uuid,
+        &params.target_inventory_uuid,
+    )
+    .await?
+    {
+        return Err(create_error(FOREIGN_CAMPAIGN_MESSAGE));
+    }
+    let target_writable = user_has_permission_on_inventory(
+        inv_rep.inner(),
+        &params.target_inventory_uuid,
+        &user.user_id,
+        InventoryPermission::EditItems,
+    )
+    .await?;
+    if target_writable {
+        let (source_change, target_change) = itr_rep
+            .transfer_item(
+                &params.source_inventory_uuid,
+                &params.target_inventory_uuid,
+                &params.item_uuid,
+                params.amount,
+            )
+            .await?;
+        report_item_change(chg_rep, &user.user_id, &source_change).await?;
+        report_item_change(chg_rep, &user.user_id, &target_change).await?;
+        return Ok(Json(ItemTransferResponse { gift: None }));
+    }
+    if *mode.inner() != ItemTransferMode::Gift {
+        return Err(create_error(ACCESS_DENIAL_MESSAGE));
+    }
+    let (gift, source_change) = itr_rep
+        .create_gift(
+            &params.source_inventory_uuid,
+            &params.target_inventory_uuid,
+            &params.item_uuid,
+            params.amount,
+            &user.user_id,
+        )
+        .await?;
+    report_item_change(chg_rep, &user.user_id, &source_change).await?;
+    Ok(Json(ItemTransferResponse {
+        gift: Some(gift.redacted()),
+    }))
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GetGiftsReturn {
+    gifts: Vec<ItemGift>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/inventory/gift/all",
+    summary = "Retrieve all pending gifts",
+    description = r#"Returns all pending gifts sent from or to an inventory the user has write access to.
+Requires authentication."#,
+    responses(
+        (status = 200, description = "All pending gifts", body = GetGiftsReturn)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Transfers"
+)]
+#[get("/inventory/gift/all")]
+pub async fn get_gifts(
+    user: super::AuthenticatedUser,
+    inv_rep: &State<InventoryRepository>,
+    itr_rep: &State<ItemTransferRepository>,
+) -> Result<Json<GetGiftsReturn>> {
+    let inventories = inv_rep.get_inventories_by_writer(&user.user_id).await?;
+    Ok(Json(GetGiftsReturn {
+        gifts: itr_rep
+            .get_gifts_of_inventories(&inventories)
+            .await?
+            .into_iter()
+            .map(ItemGift::redacted)
+            .collect(),
+    }))
+}
+
+#[derive(FromForm, ToSchema, IntoParams)]
+pub struct GiftParams {
+    gift_uuid: String,
+}
+
+#[utoipa::path(
+    patch,
+    path = "/inventory/gift/accept",
+    summary = "Accept a gift",
+    description = r#"Moves the items of a pending gift into the target inventory, merging them into a matching stack.
+Requires authentication and write access to the target inventory. Returns an error if access is denied."#,
+    params(GiftParams),
+    responses(
+        (status = 204, description = "Gift accepted successfully")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Transfers"
+)]
+#[patch("/inventory/gift/accept?<params..>")]
+pub async fn accept_gift(
+    params: GiftParams,
+    user: super::AuthenticatedUser,
+    inv_rep: &State<InventoryRepository>,
+    chg_rep: &State<ChangeLogRepository>,
+    itr_rep: &State<ItemTransferRepository>,
+) -> Result<Status> {
+    let gift = itr_rep.get_gift(&params.gift_uuid).await?;
+    if !user_has_permission_on_inventory(
+        inv_rep.inner(),
+        &gift.target_inventory_uuid,
+        &user.user_id,
+        InventoryPermission::EditItems,
+    )
+    .await?
+    {
+        return Err(create_error(ACCESS_DENIAL_MESSAGE));
+    }
+    let (_, change) = itr_rep.accept_gift(&params.gift_uuid).await?;
+    report_item_change(chg_rep, &user.user_id, &change).await?;
+    Ok(Status::NoContent)
+}
+
+#[utoipa::path(
+    patch,
+    path = "/inventory/gift/decline",
+    summary = "Decline or withdraw a gift",
+    description = r#"Returns the items of a pending gift to the source inventory.
+Requires authentication and write access to the target (decline) or the source inventory (withdraw). Returns an error if access is denied."#,
+    params(GiftParams),
+    responses(
+        (status = 204, description = "Gift declined successfully")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Transfers"
+)]
+#[patch("/inventory/gift/decline?<params..>")]
+pub async fn decline_gift(
+    params: GiftParams,
+    user: super::AuthenticatedUser,
+    inv_rep: &State<InventoryRepository>,
+    chg_rep: &State<ChangeLogRepository>,
+    itr_rep: &State<ItemTransferRepository>,
+) -> Result<Status> {
+    let gift = itr_rep.get_gift(&params.gift_uuid).await?;
+    if !user_has_permission_on_inventory(
+        inv_rep.inner(),
+        &gift.target_inventory_uuid,
+        &user.user_id,
+        InventoryPermission::EditItems,
+    )
+    .await?
+        && !user_has_permission_on_inventory(
+            inv_rep.inner(),
+            &gift.source_inventory_uuid,
+            &user.user_id,
+            InventoryPermission::EditItems,
+        )
+        .await?
+    {
+        return Err(create_error(ACCESS_DENIAL_MESSAGE));
+    }
+    let (_, change) = itr_rep.decline_gift(&params.gift_uuid).await?;
+    report_item_change(chg_rep, &user.user_id, &change).await?;
+    Ok(Status::NoContent)
+}
+
+#[derive(FromForm, ToSchema, IntoParams)]
+pub struct ItemSplitParams {
+    inventory_uuid: String,
+    item_uuid: String,
+    /// Amount moved into the new stack, at least one item has to stay
+    amount: i32,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemSplitResponse {
+    /// The new stack, with the DM note and the real preset only for DMs
+    item: FrontendItemView,
+}
+
+#[utoipa::path(
+    patch,
+    path = "/inventory/item/split",
+    summary = "Split a stack",
+    description = r#"Moves the given amount of an item into a new stack of the same inventory, which takes over the notes and the container of the item.
+Requires authentication and write access. Returns an error if access is denied."#,
+    params(ItemSplitParams),
+    responses(
+        (status = 200, description = "Stack split successfully", body = ItemSplitResponse)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Transfers"
+)]
+#[patch("/inventory/item/split?<params..>")]
+pub async fn split_item(
+    params: ItemSplitParams,
+    user: super::AuthenticatedUser,
+    inv_rep: &State<InventoryRepository>,
+    chg_rep: &State<ChangeLogRepository>,
+    itr_rep: &State<ItemTransferRepository>,
+    cmp_rep: &State<CampaignRepository>,
+) -> Result<Json<ItemSplitResponse>> {
+    if !user_has_permission_on_inventory(
+        inv_rep.inner(),
+        &params.inventory_uuid,
+        &user.user_id,
+        InventoryPermission::EditItems,
+    )
+    .await?
+    {
+        return Err(create_error(ACCESS_DENIAL_MESSAGE));
+    }
+    let (source_change, new_change) = itr_rep
+        .split_item(&params.inventory_uuid, &params.item_uuid, params.amount)
+        .await?;
+    report_item_change(chg_rep, &user.user_id, &source_change).await?;
+    report_item_change(chg_rep, &user.user_id, &new_change).await?;
+    let new_item = new_change.after.expect("new stack exists");
+    Ok(Json(ItemSplitResponse {
+        item: item_view_for_user(
+            inv_rep.inner(),
+            cmp_rep.inner(),
+            &params.inventory_uuid,
+            &new_item.uuid,
+            &user.user_id,
+        )
+        .await?,
+    }))
+}
+
+#[derive(FromForm, ToSchema, IntoParams)]
+pub struct ItemMergeParams {
+    inventory_uuid: String,
+    /// The stack that is removed
+    source_item_uuid: String,
+    /// The stack that receives the items and keeps its notes
+    target_item_uuid: String,
+}
+
+#[utoipa::path(
+    patch,
+    path = "/inventory/item/merge",
+    summary = "Merge two stacks",
+    description = r#"Moves all items of the source stack into the target stack of the same preset and inventory and removes the source.
+The contents of the source are moved into the container holding it.
+Requires authentication and write access. Returns an error if access is denied or the stacks differ in preset or disguise."#,
+    params(ItemMergeParams),
+    responses(
+        (status = 204, description = "Stacks merged successfully")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Transfers"
+)]
+#[patch("/inventory/item/merge?<params..>")]
+pub async fn merge_items(
+    params: ItemMergeParams,
+    user: super::AuthenticatedUser,
+    inv_rep: &State<InventoryRepository>,
+    chg_rep: &State<ChangeLogRepository>,
+    itr_rep: &State<ItemTransferRepository>,
+) -> Result<Status> {
+    if !user_has_permission_on_inventory(
+        inv_rep.inner(),
+        &params.inventory_uuid,
+        &user.user_id,
+        InventoryPermission::EditItems,
+    )
+    .await?
+    {
+        return Err(create_error(ACCESS_DENIAL_MESSAGE));
+    }
+    let (source_change, target_change) = itr_rep
+        .merge_items(
+            &params.inventory_uuid,
+            &params.source_item_uuid,
+            &params.target_item_uuid,
+        )
+        .await?;
+    report_item_change(chg_rep, &user.user_id, &source_change).await?;
+    report_item_change(chg_rep, &user.user_id, &target_change).await?;
+    Ok(Status::NoContent)
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    modifiers(&BearerAuthAddon),
+    paths(
+        transfer_item,
+        get_gifts,
+        accept_gift,
+        decline_gift,
+        split_item,
+        merge_items
+    ),
+    components(
+        schemas(
+            ItemTransferParams,
+            ItemTransferResponse,
+            GetGiftsReturn,
+            GiftParams,
+            ItemGift,
+            ItemSplitParams,
+            ItemSplitResponse,
+            ItemMergeParams
+        )
+    ),
+    tags(
+        (name = "Transfers", description = "Endpoints for moving items between inventories and stacks")
+    )
+)]
+pub struct TransferApiDoc;
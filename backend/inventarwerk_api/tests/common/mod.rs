@@ -0,0 +1,34 @@
+//! Helpers shared by the integration tests.
+
+use reqwest::Url;
+use sqlx::{Connection, Executor, PgConnection};
+use std::env;
+
+/// A database only used by one test.
+pub struct TestDatabase {
+    admin_url: String,
+    name: String,
+}
+
+impl TestDatabase {
+    /// Creates an empty database next to the one `DATABASE_URL` points to and returns its URL.
+    pub async fn create() -> (Self, String) {
+        let admin_url = env::var("DATABASE_URL")
+            .expect("DATABASE_URL must point to a PostgreSQL server for the integration tests");
+        let name = format!("inventarwerk_test_{:016x}", rand::random::<u64>());
+        let mut conn = PgConnection::connect(&admin_url).await.unwrap();
+        conn.execute(format!("CREATE DATABASE {}", name).as_str())
+            .await
+            .unwrap();
+        let mut url = Url::parse(&admin_url).unwrap();
+        url.set_path(&name);
+        (Self { admin_url, name }, url.to_string())
+    }
+
+    pub async fn drop(self) {
+        let mut conn = PgConnection::connect(&self.admin_url).await.unwrap();
+        conn.execute(format!("DROP DATABASE {} WITH (FORCE)", self.name).as_str())
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,499 @@
+//! Checks who may read and change an inventory and that undo keeps the changes it does not record.
+//!
+//! Users register local accounts and send their requests with an API token, so several of them can act on the same server.
+
+mod common;
+
+use common::TestDatabase;
+use inventarwerk_api::build_rocket;
+use inventarwerk_api::identity_provider::IdentityProviders;
+use rocket::http::{Header, Method, Status};
+use rocket::local::asynchronous::{Client, LocalResponse};
+use rocket::serde::json::{json, Value};
+
+const PASSWORD: &str = "correct horse battery";
+
+/// A registered user and the API token their requests are sent with.
+struct User {
+    uuid: String,
+    token: String,
+}
+
+/// Builds the API with local accounts only.
+async fn start_app() -> (Client, TestDatabase) {
+    let (database, url) = TestDatabase::create().await;
+    let dbconn = repos::create_pg_pool(url).await.unwrap();
+    let identity_providers = IdentityProviders::new(vec![]).unwrap();
+    let client = Client::tracked(build_rocket(dbconn, identity_providers).await)
+        .await
+        .unwrap();
+    (client, database)
+}
+
+/// Registers a local account and creates an API token for it, the session ends again.
+async fn register(app: &Client, username: &str, read_only: bool) -> User {
+    let response = app
+        .post("/account/local/register")
+        .json(&json!({ "username": username, "password": PASSWORD }))
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::NoContent);
+    let info: Value = app
+        .get("/account/info")
+        .dispatch()
+        .await
+        .into_json()
+        .await
+        .unwrap();
+    let response = app
+        .put(format!("/apiToken?name=tests&read_only={}", read_only))
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    let token: Value = response.into_json().await.unwrap();
+    let logout = app.get("/account/logout").dispatch().await;
+    assert_eq!(logout.status(), Status::NoContent);
+    User {
+        uuid: info["userUUID"].as_str().unwrap().to_string(),
+        token: token["token"].as_str().unwrap().to_string(),
+    }
+}
+
+async fn send<'c>(app: &'c Client, method: Method, path: &str, user: &User) -> LocalResponse<'c> {
+    app.req(method, path.to_string())
+        .header(Header::new(
+            "Authorization",
+            format!("Bearer {}", user.token),
+        ))
+        .dispatch()
+        .await
+}
+
+/// Sends a request that has to succeed and returns the status.
+async fn send_ok(app: &Client, method: Method, path: &str, user: &User) -> Status {
+    let status = send(app, method, path, user).await.status();
+    assert!(
+        status.class().is_success(),
+        "{} {} failed with {}",
+        method,
+        path,
+        status
+    );
+    status
+}
+
+async fn send_json<'c>(
+    app: &'c Client,
+    method: Method,
+    path: &str,
+    body: &Value,
+    user: &User,
+) -> LocalResponse<'c> {
+    app.req(method, path.to_string())
+        .header(Header::new(
+            "Authorization",
+            format!("Bearer {}", user.token),
+        ))
+        .json(body)
+        .dispatch()
+        .await
+}
+
+async fn get_json(app: &Client, path: &str, user: &User) -> Value {
+    let response = send(app, Method::Get, path, user).await;
+    assert_eq!(response.status(), Status::Ok, "GET {}", path);
+    response.into_json().await.unwrap()
+}
+
+async fn put_json(app: &Client, path: &str, user: &User) -> Value {
+    let response = send(app, Method::Put, path, user).await;
+    assert_eq!(response.status(), Status::Ok, "PUT {}", path);
+    response.into_json().await.unwrap()
+}
+
+/// Creates a campaign of `dm` with the players as members.
+async fn create_campaign(app: &Client, dm: &User, players: &[&User]) -> String {
+    let campaign = put_json(app, "/campaign?name=Phandelver", dm).await;
+    let campaign_uuid = campaign["uuid"].as_str().unwrap().to_string();
+    for player in players {
+        send_ok(
+            app,
+            Method::Patch,
+            &format!(
+                "/campaign/addMember?campaign_uuid={}&user_uuid={}",
+                campaign_uuid, player.uuid
+            ),
+            dm,
+        )
+        .await;
+    }
+    campaign_uuid
+}
+
+async fn create_inventory(app: &Client, campaign_uuid: &str, owner: &User) -> String {
+    let inventory = put_json(
+        app,
+        &format!("/inventory?name=Backpack&campaign_uuid={}", campaign_uuid),
+        owner,
+    )
+    .await;
+    inventory["uuid"].as_str().unwrap().to_string()
+}
+
+async fn get_inventory(app: &Client, inventory_uuid: &str, user: &User) -> Value {
+    get_json(
+        app,
+        &format!("/inventory?inventory_uuid={}", inventory_uuid),
+        user,
+    )
+    .await
+}
+
+/// Adds a new item and returns its uuid.
+async fn add_item(app: &Client, inventory_uuid: &str, amount: i32, user: &User) -> String {
+    send_ok(
+        app,
+        Method::Put,
+        &format!(
+            "/inventory/item/addNew?inventory_uuid={}&name=Rope&amount={}",
+            inventory_uuid, amount
+        ),
+        user,
+    )
+    .await;
+    let inventory = get_inventory(app, inventory_uuid, user).await;
+    inventory["items"][0]["uuid"].as_str().unwrap().to_string()
+}
+
+/// Returns the amount of the item, 0 if the inventory does not hold it.
+async fn item_amount(app: &Client, inventory_uuid: &str, item_uuid: &str, user: &User) -> i64 {
+    let inventory = get_inventory(app, inventory_uuid, user).await;
+    inventory["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|item| item["uuid"] == json!(item_uuid))
+        .map_or(0, |item| item["amount"].as_i64().unwrap())
+}
+
+async fn money(app: &Client, inventory_uuid: &str, user: &User) -> i64 {
+    get_inventory(app, inventory_uuid, user).await["money"]
+        .as_i64()
+        .unwrap()
+}
+
+fn edit_item_path(inventory_uuid: &str, item_uuid: &str, amount: i32) -> String {
+    format!(
+        "/inventory/item/edit?inventory_uuid={}&item_uuid={}&amount={}",
+        inventory_uuid, item_uuid, amount
+    )
+}
+
+fn transaction_path(inventory_uuid: &str, delta: i32) -> String {
+    format!(
+        "/money/transaction?inventory_uuid={}&delta={}",
+        inventory_uuid, delta
+    )
+}
+
+fn set_role_path(inventory_uuid: &str, user: &User, role: &str) -> String {
+    format!(
+        "/inventory/setRole?inventory_uuid={}&user_uuid={}&role={}",
+        inventory_uuid, user.uuid, role
+    )
+}
+
+#[rocket::async_test]
+async fn roles_decide_the_permissions_on_an_inventory() {
+    let (app, database) = start_app().await;
+    let dm = register(&app, "dungeon_master", false).await;
+    let owner = register(&app, "owner", false).await;
+    let player = register(&app, "player", false).await;
+    let stranger = register(&app, "stranger", false).await;
+    let campaign_uuid = create_campaign(&app, &dm, &[&owner, &player]).await;
+    let inventory_uuid = create_inventory(&app, &campaign_uuid, &owner).await;
+    let item_uuid = add_item(&app, &inventory_uuid, 5, &owner).await;
+    let inventory_path = format!("/inventory?inventory_uuid={}", inventory_uuid);
+
+    // the DM of the campaign reads every inventory of it, other members only shared ones
+    get_inventory(&app, &inventory_uuid, &dm).await;
+    let response = send(&app, Method::Get, &inventory_path, &player).await;
+    assert_ne!(response.status(), Status::Ok);
+
+    send_ok(
+        &app,
+        Method::Patch,
+        &set_role_path(&inventory_uuid, &player, "viewer"),
+        &owner,
+    )
+    .await;
+    get_inventory(&app, &inventory_uuid, &player).await;
+    let edit = edit_item_path(&inventory_uuid, &item_uuid, 4);
+    let response = send(&app, Method::Patch, &edit, &player).await;
+    assert_ne!(response.status(), Status::NoContent);
+    let response = send(
+        &app,
+        Method::Put,
+        &transaction_path(&inventory_uuid, 10),
+        &player,
+    )
+    .await;
+    assert_ne!(response.status(), Status::Ok);
+
+    send_ok(
+        &app,
+        Method::Patch,
+        &set_role_path(&inventory_uuid, &player, "itemEditor"),
+        &owner,
+    )
+    .await;
+    send_ok(&app, Method::Patch, &edit, &player).await;
+    assert_eq!(
+        item_amount(&app, &inventory_uuid, &item_uuid, &owner).await,
+        4
+    );
+    let response = send(
+        &app,
+        Method::Put,
+        &transaction_path(&inventory_uuid, 10),
+        &player,
+    )
+    .await;
+    assert_ne!(response.status(), Status::Ok);
+
+    send_ok(
+        &app,
+        Method::Patch,
+        &set_role_path(&inventory_uuid, &player, "moneyEditor"),
+        &owner,
+    )
+    .await;
+    send_ok(
+        &app,
+        Method::Put,
+        &transaction_path(&inventory_uuid, 10),
+        &player,
+    )
+    .await;
+    assert_eq!(money(&app, &inventory_uuid, &owner).await, 10);
+    // only the share permission lets a user hand out roles, including to themselves
+    let response = send(
+        &app,
+        Method::Patch,
+        &set_role_path(&inventory_uuid, &player, "manager"),
+        &player,
+    )
+    .await;
+    assert_ne!(response.status(), Status::NoContent);
+
+    send_ok(
+        &app,
+        Method::Patch,
+        &set_role_path(&inventory_uuid, &player, "manager"),
+        &owner,
+    )
+    .await;
+    let response = send(
+        &app,
+        Method::Delete,
+        &format!("/inventory/delete?inventory_uuid={}", inventory_uuid),
+        &player,
+    )
+    .await;
+    assert_ne!(response.status(), Status::NoContent);
+
+    // users outside the campaign get neither a role nor access
+    let response = send(
+        &app,
+        Method::Patch,
+        &set_role_path(&inventory_uuid, &stranger, "viewer"),
+        &owner,
+    )
+    .await;
+    assert_ne!(response.status(), Status::NoContent);
+    let response = send(&app, Method::Get, &inventory_path, &stranger).await;
+    assert_ne!(response.status(), Status::Ok);
+
+    database.drop().await;
+}
+
+#[rocket::async_test]
+async fn api_tokens_are_limited_to_their_scope() {
+    let (app, database) = start_app().await;
+    let dm = register(&app, "dungeon_master", false).await;
+    let reader = register(&app, "reader", true).await;
+    let campaign_uuid = create_campaign(&app, &dm, &[&reader]).await;
+
+    get_json(&app, "/inventory/all", &reader).await;
+    let path = format!("/inventory?name=Backpack&campaign_uuid={}", campaign_uuid);
+    let response = send(&app, Method::Put, &path, &reader).await;
+    assert_eq!(response.status(), Status::Forbidden);
+
+    // credentials are only managed with a session
+    let response = send(&app, Method::Put, "/apiToken?name=another", &dm).await;
+    assert_eq!(response.status(), Status::Forbidden);
+    let response = send(&app, Method::Get, "/session/all", &dm).await;
+    assert_eq!(response.status(), Status::Forbidden);
+
+    let response = app
+        .get("/inventory/all")
+        .header(Header::new("Authorization", "Bearer iw_unknown"))
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    database.drop().await;
+}
+
+#[rocket::async_test]
+async fn undo_and_redo_an_item_edit() {
+    let (app, database) = start_app().await;
+    let dm = register(&app, "dungeon_master", false).await;
+    let campaign_uuid = create_campaign(&app, &dm, &[]).await;
+    let inventory_uuid = create_inventory(&app, &campaign_uuid, &dm).await;
+    let item_uuid = add_item(&app, &inventory_uuid, 5, &dm).await;
+    let undo = format!("/inventory/undo?inventory_uuid={}", inventory_uuid);
+    let redo = format!("/inventory/redo?inventory_uuid={}", inventory_uuid);
+
+    send_ok(
+        &app,
+        Method::Patch,
+        &edit_item_path(&inventory_uuid, &item_uuid, 3),
+        &dm,
+    )
+    .await;
+    send_ok(&app, Method::Patch, &undo, &dm).await;
+    assert_eq!(item_amount(&app, &inventory_uuid, &item_uuid, &dm).await, 5);
+    send_ok(&app, Method::Patch, &redo, &dm).await;
+    assert_eq!(item_amount(&app, &inventory_uuid, &item_uuid, &dm).await, 3);
+
+    send_ok(
+        &app,
+        Method::Put,
+        &transaction_path(&inventory_uuid, 100),
+        &dm,
+    )
+    .await;
+    send_ok(&app, Method::Patch, &undo, &dm).await;
+    assert_eq!(money(&app, &inventory_uuid, &dm).await, 0);
+
+    database.drop().await;
+}
+
+#[rocket::async_test]
+async fn undo_does_not_revert_transfers() {
+    let (app, database) = start_app().await;
+    let dm = register(&app, "dungeon_master", false).await;
+    let campaign_uuid = create_campaign(&app, &dm, &[]).await;
+    let source_uuid = create_inventory(&app, &campaign_uuid, &dm).await;
+    let target_uuid = create_inventory(&app, &campaign_uuid, &dm).await;
+    let item_uuid = add_item(&app, &source_uuid, 5, &dm).await;
+    let undo = format!("/inventory/undo?inventory_uuid={}", source_uuid);
+
+    send_ok(
+        &app,
+        Method::Patch,
+        &edit_item_path(&source_uuid, &item_uuid, 3),
+        &dm,
+    )
+    .await;
+    let transfer = format!(
+        "/inventory/item/transfer?source_inventory_uuid={}&target_inventory_uuid={}&item_uuid={}&amount=2",
+        source_uuid, target_uuid, item_uuid
+    );
+    send_ok(&app, Method::Patch, &transfer, &dm).await;
+    // undoing the edit would bring back the transferred items
+    let response = send(&app, Method::Patch, &undo, &dm).await;
+    assert_ne!(response.status(), Status::NoContent);
+    assert_eq!(item_amount(&app, &source_uuid, &item_uuid, &dm).await, 1);
+    let target = get_inventory(&app, &target_uuid, &dm).await;
+    assert_eq!(target["items"][0]["amount"], json!(2));
+
+    send_ok(&app, Method::Put, &transaction_path(&source_uuid, 100), &dm).await;
+    let transfer = format!(
+        "/money/transfer?source_inventory_uuid={}&target_inventory_uuid={}&amount=40",
+        source_uuid, target_uuid
+    );
+    send_ok(&app, Method::Put, &transfer, &dm).await;
+    let response = send(&app, Method::Patch, &undo, &dm).await;
+    assert_ne!(response.status(), Status::NoContent);
+    assert_eq!(money(&app, &source_uuid, &dm).await, 60);
+    assert_eq!(money(&app, &target_uuid, &dm).await, 40);
+
+    // recorded edits after the transfer can be undone again
+    send_ok(&app, Method::Put, &transaction_path(&source_uuid, -10), &dm).await;
+    send_ok(&app, Method::Patch, &undo, &dm).await;
+    assert_eq!(money(&app, &source_uuid, &dm).await, 60);
+
+    database.drop().await;
+}
+
+#[rocket::async_test]
+async fn loot_split_needs_the_item_permission_on_the_targets() {
+    let (app, database) = start_app().await;
+    let dm = register(&app, "dungeon_master", false).await;
+    let player = register(&app, "player", false).await;
+    let campaign_uuid = create_campaign(&app, &dm, &[&player]).await;
+    let source_uuid = create_inventory(&app, &campaign_uuid, &player).await;
+    let target_uuid = create_inventory(&app, &campaign_uuid, &dm).await;
+    let item_uuid = add_item(&app, &source_uuid, 4, &player).await;
+    send_ok(
+        &app,
+        Method::Put,
+        &transaction_path(&source_uuid, 100),
+        &player,
+    )
+    .await;
+    let split = |item_uuids: Vec<&str>| {
+        json!({
+            "sourceInventoryUuid": source_uuid,
+            "targetInventoryUuids": [target_uuid],
+            "amount": 10,
+            "itemUuids": item_uuids,
+        })
+    };
+
+    // money can be handed to anyone, items only to inventories the user may put items into
+    let response = send_json(
+        &app,
+        Method::Put,
+        "/money/split",
+        &split(vec![&item_uuid]),
+        &player,
+    )
+    .await;
+    assert_ne!(response.status(), Status::Ok);
+    assert_eq!(
+        item_amount(&app, &source_uuid, &item_uuid, &player).await,
+        4
+    );
+    let response = send_json(&app, Method::Put, "/money/split", &split(vec![]), &player).await;
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(money(&app, &target_uuid, &dm).await, 10);
+
+    send_ok(
+        &app,
+        Method::Patch,
+        &set_role_path(&target_uuid, &player, "itemEditor"),
+        &dm,
+    )
+    .await;
+    let response = send_json(
+        &app,
+        Method::Put,
+        "/money/split",
+        &split(vec![&item_uuid]),
+        &player,
+    )
+    .await;
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(
+        item_amount(&app, &source_uuid, &item_uuid, &player).await,
+        0
+    );
+    let target = get_inventory(&app, &target_uuid, &dm).await;
+    assert_eq!(target["items"][0]["amount"], json!(4));
+    assert_eq!(money(&app, &source_uuid, &player).await, 80);
+
+    database.drop().await;
+}
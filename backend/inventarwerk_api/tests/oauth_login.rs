@@ -0,0 +1,442 @@
+//! Runs the OAuth login through Discord and an OpenID Connect server against a local stand-in for both.
+//!
+//! Every test works on its own database, created next to the one `DATABASE_URL` points to.
+
+mod common;
+
+use common::TestDatabase;
+use inventarwerk_api::build_rocket;
+use inventarwerk_api::identity_provider::{
+    DiscordConfig, DiscordProvider, IdentityProviders, OidcConfig, OidcProvider,
+};
+use openssl::base64::encode_block;
+use openssl::sha::sha256;
+use reqwest::redirect::Policy;
+use reqwest::Url;
+use rocket::fairing::AdHoc;
+use rocket::form::Form;
+use rocket::http::Status;
+use rocket::local::asynchronous::{Client, LocalResponse};
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::response::Redirect;
+use rocket::serde::json::{json, Json, Value};
+use rocket::tokio::sync::oneshot;
+use rocket::{get, post, routes, FromForm, State};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+const CLIENT_ID: &str = "inventarwerk";
+const CLIENT_SECRET: &str = "discord-secret";
+const REDIRECT_URI: &str = "http://localhost:8000/account/oauth/callback";
+const OIDC_PROVIDER: &str = "mock-oidc";
+
+/// Users the stand-in knows, as subject and name.
+const DISCORD_USER: (&str, &str) = ("80351110224678912", "nelly");
+const OIDC_USER: (&str, &str) = ("3f1c9a5e-oidc-user", "alex");
+
+/// An authorization code handed out by the stand-in.
+struct PendingCode {
+    subject: String,
+    redirect_uri: String,
+    code_challenge: Option<String>,
+}
+
+/// State of the stand-in provider, shared with the tests.
+#[derive(Default)]
+struct MockProvider {
+    base_url: OnceLock<String>,
+    codes: Mutex<HashMap<String, PendingCode>>,
+    /// Access tokens and the subject they belong to
+    tokens: Mutex<HashMap<String, String>>,
+    revoked: Mutex<Vec<String>>,
+}
+
+fn user_name(subject: &str) -> Option<&'static str> {
+    [DISCORD_USER, OIDC_USER]
+        .into_iter()
+        .find(|(s, _)| *s == subject)
+        .map(|(_, name)| name)
+}
+
+fn s256(verifier: &str) -> String {
+    encode_block(&sha256(verifier.as_bytes()))
+        .replace('+', "-")
+        .replace('/', "_")
+        .trim_end_matches('=')
+        .to_string()
+}
+
+#[derive(FromForm)]
+struct AuthorizeParams {
+    client_id: String,
+    redirect_uri: String,
+    state: String,
+    code_challenge: Option<String>,
+    /// Stands in for the user entering their credentials
+    login_as: String,
+}
+
+#[get("/oauth2/authorize?<params..>")]
+fn authorize(params: AuthorizeParams, mock: &State<Arc<MockProvider>>) -> Result<Redirect, Status> {
+    if params.client_id != CLIENT_ID || user_name(&params.login_as).is_none() {
+        return Err(Status::BadRequest);
+    }
+    let mut codes = mock.codes.lock().unwrap();
+    let code = format!("code-{}", codes.len());
+    codes.insert(
+        code.clone(),
+        PendingCode {
+            subject: params.login_as,
+            redirect_uri: params.redirect_uri.clone(),
+            code_challenge: params.code_challenge,
+        },
+    );
+    Ok(Redirect::to(format!(
+        "{}?code={}&state={}",
+        params.redirect_uri, code, params.state
+    )))
+}
+
+#[derive(FromForm)]
+struct TokenRequest {
+    client_id: String,
+    client_secret: Option<String>,
+    grant_type: String,
+    code: String,
+    redirect_uri: String,
+    code_verifier: Option<String>,
+}
+
+/// Exchanges a code, clients using PKCE need no secret.
+#[post("/api/oauth2/token", data = "<form>")]
+fn token(form: Form<TokenRequest>, mock: &State<Arc<MockProvider>>) -> Result<Json<Value>, Status> {
+    let pending = mock
+        .codes
+        .lock()
+        .unwrap()
+        .remove(&form.code)
+        .ok_or(Status::BadRequest)?;
+    if form.client_id != CLIENT_ID
+        || form.grant_type != "authorization_code"
+        || form.redirect_uri != pending.redirect_uri
+    {
+        return Err(Status::BadRequest);
+    }
+    let authenticated = match &pending.code_challenge {
+        Some(challenge) => form.code_verifier.as_deref().map(s256).as_ref() == Some(challenge),
+        None => form.client_secret.as_deref() == Some(CLIENT_SECRET),
+    };
+    if !authenticated {
+        return Err(Status::Unauthorized);
+    }
+    let access_token = format!("access-{}", form.code);
+    mock.tokens
+        .lock()
+        .unwrap()
+        .insert(access_token.clone(), pending.subject);
+    Ok(Json(json!({
+        "access_token": access_token,
+        "token_type": "Bearer",
+        "refresh_token": format!("refresh-{}", form.code),
+    })))
+}
+
+#[derive(FromForm)]
+struct RevokeRequest {
+    token: String,
+}
+
+#[post("/api/oauth2/token/revoke", data = "<form>")]
+fn revoke(form: Form<RevokeRequest>, mock: &State<Arc<MockProvider>>) -> Status {
+    mock.revoked.lock().unwrap().push(form.token.clone());
+    Status::Ok
+}
+
+/// The user an access token was issued for.
+struct TokenSubject(String);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for TokenSubject {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, ()> {
+        let mock = request.rocket().state::<Arc<MockProvider>>().unwrap();
+        let subject = request
+            .headers()
+            .get_one("Authorization")
+            .and_then(|header| header.strip_prefix("Bearer "))
+            .and_then(|token| mock.tokens.lock().unwrap().get(token).cloned());
+        match subject {
+            Some(subject) => Outcome::Success(TokenSubject(subject)),
+            None => Outcome::Error((Status::Unauthorized, ())),
+        }
+    }
+}
+
+#[get("/api/users/@me")]
+fn discord_user(subject: TokenSubject) -> Json<Value> {
+    Json(json!({
+        "id": subject.0,
+        "username": user_name(&subject.0),
+        "avatar": "a_1269e74af4df7417b13759eae50c83dc",
+    }))
+}
+
+#[get("/oidc/.well-known/openid-configuration")]
+fn oidc_discovery(mock: &State<Arc<MockProvider>>) -> Json<Value> {
+    let base_url = mock.base_url.get().unwrap();
+    Json(json!({
+        "issuer": format!("{}/oidc", base_url),
+        "authorization_endpoint": format!("{}/oauth2/authorize", base_url),
+        "token_endpoint": format!("{}/api/oauth2/token", base_url),
+        "userinfo_endpoint": format!("{}/oidc/userinfo", base_url),
+    }))
+}
+
+#[get("/oidc/userinfo")]
+fn oidc_userinfo(subject: TokenSubject) -> Json<Value> {
+    Json(json!({
+        "sub": subject.0,
+        "preferred_username": user_name(&subject.0),
+        "picture": "https://id.example.com/alex.png",
+    }))
+}
+
+/// Starts the stand-in on a free port and waits until it accepts requests.
+async fn start_mock_provider() -> Arc<MockProvider> {
+    let mock = Arc::new(MockProvider::default());
+    let (ready, port) = oneshot::channel();
+    let figment = rocket::Config::figment()
+        .merge(("address", "127.0.0.1"))
+        .merge(("port", 0))
+        .merge(("log_level", "off"))
+        .merge(("shutdown.ctrlc", false));
+    let server = rocket::custom(figment)
+        .manage(mock.clone())
+        .mount(
+            "/",
+            routes![
+                authorize,
+                token,
+                revoke,
+                discord_user,
+                oidc_discovery,
+                oidc_userinfo
+            ],
+        )
+        .attach(AdHoc::on_liftoff("Report port", |rocket| {
+            Box::pin(async move {
+                let _ = ready.send(rocket.config().port);
+            })
+        }));
+    rocket::tokio::spawn(server.launch());
+    let port = port.await.expect("the mock provider did not start");
+    mock.base_url
+        .set(format!("http://127.0.0.1:{}", port))
+        .unwrap();
+    mock
+}
+
+/// Builds the API with both providers pointing at the stand-in.
+async fn start_app(mock: &MockProvider) -> (Client, TestDatabase) {
+    let (database, url) = TestDatabase::create().await;
+    let dbconn = repos::create_pg_pool(url).await.unwrap();
+    let base_url = mock.base_url.get().unwrap();
+    let http_client = reqwest::Client::new();
+    let identity_providers = IdentityProviders::new(vec![
+        Box::new(DiscordProvider::new(
+            DiscordConfig {
+                client_id: CLIENT_ID.to_string(),
+                client_secret: CLIENT_SECRET.to_string(),
+                redirect_uri: REDIRECT_URI.to_string(),
+                base_url: base_url.clone(),
+            },
+            http_client.clone(),
+        )),
+        Box::new(OidcProvider::new(
+            OidcConfig {
+                id: OIDC_PROVIDER.to_string(),
+                name: "Mock OIDC".to_string(),
+                issuer_url: format!("{}/oidc", base_url),
+                client_id: CLIENT_ID.to_string(),
+                client_secret: None,
+                redirect_uri: REDIRECT_URI.to_string(),
+                scopes: "openid profile".to_string(),
+                name_claim: "preferred_username".to_string(),
+                avatar_claim: "picture".to_string(),
+            },
+            http_client,
+        )),
+    ])
+    .unwrap();
+    let client = Client::tracked(build_rocket(dbconn, identity_providers).await)
+        .await
+        .unwrap();
+    (client, database)
+}
+
+/// Starts a login and returns the login page of the provider it redirects to.
+async fn authorization_url(app: &Client, path: &str) -> Url {
+    let response = app.get(path.to_string()).dispatch().await;
+    assert_eq!(response.status(), Status::SeeOther);
+    Url::parse(response.headers().get_one("Location").unwrap()).unwrap()
+}
+
+/// Logs the user in at the provider like a browser would and returns where the provider redirects to.
+async fn authorize_at_provider(mut url: Url, login_as: &str) -> Url {
+    url.query_pairs_mut().append_pair("login_as", login_as);
+    let browser = reqwest::Client::builder()
+        .redirect(Policy::none())
+        .build()
+        .unwrap();
+    let response = browser.get(url).send().await.unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::SEE_OTHER);
+    Url::parse(response.headers()["location"].to_str().unwrap()).unwrap()
+}
+
+/// Runs the whole login started at `path` and returns the response of the callback.
+async fn log_in<'c>(app: &'c Client, path: &str, login_as: &str) -> LocalResponse<'c> {
+    let url = authorization_url(app, path).await;
+    let callback = authorize_at_provider(url, login_as).await;
+    assert_eq!(callback.path(), "/account/oauth/callback");
+    app.get(format!("{}?{}", callback.path(), callback.query().unwrap()))
+        .dispatch()
+        .await
+}
+
+async fn get_json(app: &Client, path: &str) -> Value {
+    let response = app.get(path.to_string()).dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+    response.into_json().await.unwrap()
+}
+
+async fn is_logged_in(app: &Client) -> bool {
+    get_json(app, "/account/isLoggedIn").await["loggedIn"] == json!(true)
+}
+
+#[rocket::async_test]
+async fn discord_login_creates_user_and_session() {
+    let mock = start_mock_provider().await;
+    let (app, database) = start_app(&mock).await;
+
+    let url = authorization_url(&app, "/account/login").await;
+    assert!(url.as_str().starts_with(mock.base_url.get().unwrap()));
+    let callback = log_in(&app, "/account/login", DISCORD_USER.0).await;
+    assert_eq!(callback.status(), Status::SeeOther);
+    assert_eq!(callback.headers().get_one("Location"), Some("/"));
+
+    assert!(is_logged_in(&app).await);
+    let info = get_json(&app, "/account/info").await;
+    assert_eq!(info["userUUID"], json!(DISCORD_USER.0));
+    let accounts = get_json(&app, "/account/get").await;
+    assert_eq!(accounts["accounts"][0]["name"], json!(DISCORD_USER.1));
+    assert_eq!(accounts["accounts"][0]["dm"], json!(1));
+    assert_eq!(mock.revoked.lock().unwrap().len(), 1);
+
+    database.drop().await;
+}
+
+#[rocket::async_test]
+async fn oidc_login_uses_pkce() {
+    let mock = start_mock_provider().await;
+    let (app, database) = start_app(&mock).await;
+
+    let path = format!("/account/login?provider={}", OIDC_PROVIDER);
+    let url = authorization_url(&app, &path).await;
+    let params: HashMap<_, _> = url.query_pairs().into_owned().collect();
+    assert_eq!(params["code_challenge_method"], "S256");
+
+    let callback = log_in(&app, &path, OIDC_USER.0).await;
+    assert_eq!(callback.status(), Status::SeeOther);
+    assert!(is_logged_in(&app).await);
+    let identities = get_json(&app, "/account/identities").await;
+    assert_eq!(identities[0]["provider"], json!(OIDC_PROVIDER));
+    assert_eq!(identities[0]["subject"], json!(OIDC_USER.0));
+    let accounts = get_json(&app, "/account/get").await;
+    assert_eq!(accounts["accounts"][0]["name"], json!(OIDC_USER.1));
+
+    database.drop().await;
+}
+
+#[rocket::async_test]
+async fn callback_rejects_wrong_state() {
+    let mock = start_mock_provider().await;
+    let (app, database) = start_app(&mock).await;
+
+    let url = authorization_url(&app, "/account/login").await;
+    let callback = authorize_at_provider(url, DISCORD_USER.0).await;
+    let params: HashMap<_, _> = callback.query_pairs().into_owned().collect();
+    let response = app
+        .get(format!(
+            "/account/oauth/callback?code={}&state=forged",
+            params["code"]
+        ))
+        .dispatch()
+        .await;
+    assert_ne!(response.status(), Status::SeeOther);
+    assert!(!is_logged_in(&app).await);
+
+    database.drop().await;
+}
+
+#[rocket::async_test]
+async fn failed_token_exchange_does_not_log_in() {
+    let mock = start_mock_provider().await;
+    let (app, database) = start_app(&mock).await;
+
+    let url = authorization_url(&app, "/account/login").await;
+    let params: HashMap<_, _> = url.query_pairs().into_owned().collect();
+    let response = app
+        .get(format!(
+            "/account/oauth/callback?code=unknown&state={}",
+            params["state"]
+        ))
+        .dispatch()
+        .await;
+    assert_ne!(response.status(), Status::SeeOther);
+    assert!(!is_logged_in(&app).await);
+
+    database.drop().await;
+}
+
+#[rocket::async_test]
+async fn linked_identity_logs_in_the_same_user() {
+    let mock = start_mock_provider().await;
+    let (app, database) = start_app(&mock).await;
+
+    log_in(&app, "/account/login", DISCORD_USER.0).await;
+    let path = format!("/account/link?provider={}", OIDC_PROVIDER);
+    let linked = log_in(&app, &path, OIDC_USER.0).await;
+    assert_eq!(linked.status(), Status::SeeOther);
+    let identities = get_json(&app, "/account/identities").await;
+    assert_eq!(identities.as_array().unwrap().len(), 2);
+
+    let logout = app.get("/account/logout").dispatch().await;
+    assert_eq!(logout.status(), Status::NoContent);
+    assert!(!is_logged_in(&app).await);
+
+    let path = format!("/account/login?provider={}", OIDC_PROVIDER);
+    log_in(&app, &path, OIDC_USER.0).await;
+    let info = get_json(&app, "/account/info").await;
+    assert_eq!(info["userUUID"], json!(DISCORD_USER.0));
+
+    database.drop().await;
+}
+
+#[rocket::async_test]
+async fn stale_invite_does_not_block_existing_user() {
+    let mock = start_mock_provider().await;
+    let (app, database) = start_app(&mock).await;
+
+    log_in(&app, "/account/login", DISCORD_USER.0).await;
+    let logout = app.get("/account/logout").dispatch().await;
+    assert_eq!(logout.status(), Status::NoContent);
+
+    let callback = log_in(&app, "/account/login?invite=expired", DISCORD_USER.0).await;
+    assert_eq!(callback.status(), Status::SeeOther);
+    assert!(is_logged_in(&app).await);
+    let info = get_json(&app, "/account/info").await;
+    assert_eq!(info["userUUID"], json!(DISCORD_USER.0));
+
+    database.drop().await;
+}
@@ -0,0 +1,15 @@
+-- Durable log of every change on an inventory.
+-- Intentionally without foreign keys, so the history (and deletions) survive
+-- the removal of the inventory, preset or user it refers to.
+CREATE TABLE inventory_change_log (
+    id BIGSERIAL PRIMARY KEY,
+    inventory_uuid TEXT NOT NULL,
+    user_uuid TEXT NOT NULL,
+    change_kind TEXT NOT NULL,
+    item_preset_uuid TEXT,
+    old_value TEXT,
+    new_value TEXT,
+    changed_at BIGINT NOT NULL DEFAULT (EXTRACT(EPOCH FROM CURRENT_TIMESTAMP) * 1000)::BIGINT
+);
+
+CREATE INDEX inventory_change_log_inventory_idx ON inventory_change_log(inventory_uuid, id);
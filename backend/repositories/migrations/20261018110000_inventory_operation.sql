@@ -0,0 +1,17 @@
+-- Reversible operations on inventories, used for undo and redo.
+-- old_value and new_value hold JSON snapshots of the item (or the inventory
+-- itself if item_preset_uuid is NULL), NULL meaning the item did not exist.
+CREATE TABLE inventory_operation (
+    id BIGSERIAL PRIMARY KEY,
+    inventory_uuid TEXT NOT NULL,
+    user_uuid TEXT NOT NULL,
+    change_kind TEXT NOT NULL,
+    item_preset_uuid TEXT,
+    old_value TEXT,
+    new_value TEXT,
+    undone BOOLEAN NOT NULL DEFAULT FALSE,
+    creation TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(inventory_uuid) REFERENCES inventory(uuid) ON DELETE CASCADE
+);
+
+CREATE INDEX inventory_operation_inventory_idx ON inventory_operation(inventory_uuid, id);
@@ -0,0 +1,18 @@
+-- Ledger of all money changes of an inventory, inventory.money is kept as the sum of all deltas.
+CREATE TABLE money_transaction (
+    uuid TEXT NOT NULL PRIMARY KEY,
+    inventory_uuid TEXT NOT NULL,
+    user_uuid TEXT NOT NULL,
+    delta INTEGER NOT NULL,
+    reason TEXT,
+    creation TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(inventory_uuid) REFERENCES inventory(uuid) ON DELETE CASCADE
+);
+
+CREATE INDEX money_transaction_inventory_idx ON money_transaction(inventory_uuid, creation);
+
+-- Existing balances become the opening transaction of each inventory
+INSERT INTO money_transaction (uuid, inventory_uuid, user_uuid, delta, reason)
+SELECT gen_random_uuid()::TEXT, uuid, owner_uuid, money, 'Opening balance'
+FROM inventory
+WHERE money <> 0;
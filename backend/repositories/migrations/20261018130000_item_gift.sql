@@ -0,0 +1,20 @@
+-- Items in transit between two inventories, waiting for the recipient to accept or decline them.
+-- The items are already removed from the source inventory and keep their notes while in escrow.
+CREATE TABLE item_gift (
+    uuid TEXT NOT NULL PRIMARY KEY,
+    source_inventory_uuid TEXT NOT NULL,
+    target_inventory_uuid TEXT NOT NULL,
+    item_preset_uuid TEXT NOT NULL,
+    sender_uuid TEXT NOT NULL,
+    amount INTEGER NOT NULL,
+    dm_note TEXT NOT NULL,
+    sorting INTEGER NOT NULL,
+    inventory_item_note TEXT NOT NULL,
+    creation TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(source_inventory_uuid) REFERENCES inventory(uuid) ON DELETE CASCADE,
+    FOREIGN KEY(target_inventory_uuid) REFERENCES inventory(uuid) ON DELETE CASCADE,
+    FOREIGN KEY(item_preset_uuid) REFERENCES item_preset(uuid) ON DELETE CASCADE
+);
+
+CREATE INDEX item_gift_target_idx ON item_gift(target_inventory_uuid);
+CREATE INDEX item_gift_source_idx ON item_gift(source_inventory_uuid);
@@ -0,0 +1,3 @@
+-- Transfers book a transaction in both inventories, each referring to the other one
+ALTER TABLE money_transaction ADD COLUMN counterpart_inventory_uuid TEXT;
+ALTER TABLE money_transaction ADD FOREIGN KEY(counterpart_inventory_uuid) REFERENCES inventory(uuid) ON DELETE SET NULL;
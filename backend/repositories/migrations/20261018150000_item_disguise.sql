@@ -0,0 +1,9 @@
+-- Disguise shown to players instead of the real preset of an unidentified item.
+-- Either all disguise columns are set or none, NULL meaning the item is identified.
+ALTER TABLE inventory_item ADD COLUMN disguise_name TEXT;
+ALTER TABLE inventory_item ADD COLUMN disguise_description TEXT;
+ALTER TABLE inventory_item ADD COLUMN disguise_item_type TEXT;
+
+ALTER TABLE item_gift ADD COLUMN disguise_name TEXT;
+ALTER TABLE item_gift ADD COLUMN disguise_description TEXT;
+ALTER TABLE item_gift ADD COLUMN disguise_item_type TEXT;
@@ -0,0 +1,11 @@
+-- Items of container presets can hold other items of the same inventory.
+-- weightless_contents marks containers whose contents do not add to their weight (bag of holding).
+ALTER TABLE item_preset ADD COLUMN container BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE item_preset ADD COLUMN weightless_contents BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- NULL means the item lies directly in the inventory
+ALTER TABLE inventory_item ADD COLUMN container_preset_uuid TEXT;
+ALTER TABLE inventory_item ADD CHECK (container_preset_uuid IS NULL OR container_preset_uuid <> item_preset_uuid);
+-- Contents of containers removed through a deleted preset fall out to the top level
+ALTER TABLE inventory_item ADD FOREIGN KEY (inventory_uuid, container_preset_uuid)
+    REFERENCES inventory_item(inventory_uuid, item_preset_uuid) ON DELETE SET NULL (container_preset_uuid);
@@ -0,0 +1,62 @@
+-- Inventory items get their own UUID, so an inventory can hold several stacks of the same preset.
+ALTER TABLE inventory_item ADD COLUMN uuid TEXT;
+UPDATE inventory_item SET uuid = gen_random_uuid()::TEXT;
+ALTER TABLE inventory_item ALTER COLUMN uuid SET NOT NULL;
+
+-- Containers are referenced by their item instead of their preset
+ALTER TABLE inventory_item ADD COLUMN container_item_uuid TEXT;
+UPDATE inventory_item ii SET container_item_uuid = c.uuid
+    FROM inventory_item c
+    WHERE c.inventory_uuid = ii.inventory_uuid AND c.item_preset_uuid = ii.container_preset_uuid;
+ALTER TABLE inventory_item DROP CONSTRAINT inventory_item_inventory_uuid_container_preset_uuid_fkey;
+ALTER TABLE inventory_item DROP CONSTRAINT inventory_item_check;
+ALTER TABLE inventory_item DROP COLUMN container_preset_uuid;
+
+ALTER TABLE inventory_item DROP CONSTRAINT inventory_item_pkey;
+ALTER TABLE inventory_item ADD PRIMARY KEY (uuid);
+ALTER TABLE inventory_item ADD UNIQUE (inventory_uuid, uuid);
+CREATE INDEX inventory_item_preset_idx ON inventory_item(inventory_uuid, item_preset_uuid);
+
+ALTER TABLE inventory_item ADD CHECK (container_item_uuid IS NULL OR container_item_uuid <> uuid);
+-- Contents of removed containers fall out to the top level
+ALTER TABLE inventory_item ADD FOREIGN KEY (inventory_uuid, container_item_uuid)
+    REFERENCES inventory_item(inventory_uuid, uuid) ON DELETE SET NULL (container_item_uuid);
+
+-- Changes refer to the item besides its preset, changes of items that no longer exist lose the reference
+ALTER TABLE inventory_change_log ADD COLUMN item_uuid TEXT;
+UPDATE inventory_change_log cl SET item_uuid = ii.uuid
+    FROM inventory_item ii
+    WHERE ii.inventory_uuid = cl.inventory_uuid AND ii.item_preset_uuid = cl.item_preset_uuid;
+
+-- Recorded operations refer to the item as well, items that no longer exist get a new UUID to be restored with
+ALTER TABLE inventory_operation ADD COLUMN item_uuid TEXT;
+UPDATE inventory_operation op SET item_uuid = ii.uuid
+    FROM inventory_item ii
+    WHERE ii.inventory_uuid = op.inventory_uuid AND ii.item_preset_uuid = op.item_preset_uuid;
+UPDATE inventory_operation op SET item_uuid = removed.uuid
+    FROM (
+        SELECT inventory_uuid, item_preset_uuid, gen_random_uuid()::TEXT AS uuid
+        FROM (
+            SELECT DISTINCT inventory_uuid, item_preset_uuid FROM inventory_operation
+            WHERE item_preset_uuid IS NOT NULL AND item_uuid IS NULL
+        ) missing
+    ) removed
+    WHERE removed.inventory_uuid = op.inventory_uuid AND removed.item_preset_uuid = op.item_preset_uuid;
+
+-- Item snapshots get the item UUID and reference their container by its item, containers that no longer exist are dropped
+UPDATE inventory_operation op SET
+    old_value = ((op.old_value::JSONB - 'containerPresetUuid') || jsonb_build_object(
+        'uuid', op.item_uuid,
+        'containerItemUuid', (
+            SELECT c.uuid FROM inventory_item c
+            WHERE c.inventory_uuid = op.inventory_uuid AND c.item_preset_uuid = op.old_value::JSONB ->> 'containerPresetUuid'
+        )
+    ))::TEXT,
+    new_value = ((op.new_value::JSONB - 'containerPresetUuid') || jsonb_build_object(
+        'uuid', op.item_uuid,
+        'containerItemUuid', (
+            SELECT c.uuid FROM inventory_item c
+            WHERE c.inventory_uuid = op.inventory_uuid AND c.item_preset_uuid = op.new_value::JSONB ->> 'containerPresetUuid'
+        )
+    ))::TEXT
+    WHERE op.item_uuid IS NOT NULL;
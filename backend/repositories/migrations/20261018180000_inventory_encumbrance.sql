@@ -0,0 +1,5 @@
+-- Configuration for the carrying capacity of an inventory, see `EncumbranceRule`.
+-- carrying_capacity overrides the capacity derived from the strength score.
+ALTER TABLE inventory ADD COLUMN strength INTEGER CHECK (strength > 0);
+ALTER TABLE inventory ADD COLUMN carrying_capacity REAL CHECK (carrying_capacity >= 0);
+ALTER TABLE inventory ADD COLUMN count_money_weight BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,40 @@
+-- Denominations of the currency system, value is the worth of one coin in the smallest unit.
+-- Currencies without auto_convert are never broken up or handed out as change.
+CREATE TABLE currency (
+    uuid TEXT NOT NULL PRIMARY KEY,
+    name TEXT NOT NULL,
+    abbreviation TEXT NOT NULL UNIQUE,
+    value INTEGER NOT NULL CHECK (value > 0),
+    auto_convert BOOLEAN NOT NULL DEFAULT TRUE,
+    creation TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Coins held by an inventory, inventory.money is kept as their total value in the smallest unit.
+CREATE TABLE inventory_money (
+    inventory_uuid TEXT NOT NULL,
+    currency_uuid TEXT NOT NULL,
+    amount INTEGER NOT NULL CHECK (amount > 0),
+    PRIMARY KEY(inventory_uuid, currency_uuid),
+    FOREIGN KEY(inventory_uuid) REFERENCES inventory(uuid) ON DELETE CASCADE,
+    FOREIGN KEY(currency_uuid) REFERENCES currency(uuid)
+);
+
+-- The fixed denominations the frontend used so far
+INSERT INTO currency (uuid, name, abbreviation, value) VALUES
+    (gen_random_uuid()::TEXT, 'Copper', 'cp', 1),
+    (gen_random_uuid()::TEXT, 'Silver', 'sp', 10),
+    (gen_random_uuid()::TEXT, 'Gold', 'gp', 100),
+    (gen_random_uuid()::TEXT, 'Platinum', 'pp', 1000);
+
+-- Existing balances are broken down into the least number of coins
+INSERT INTO inventory_money (inventory_uuid, currency_uuid, amount)
+SELECT inventory_uuid, currency_uuid, amount FROM (
+    SELECT i.uuid AS inventory_uuid, c.uuid AS currency_uuid, CASE c.abbreviation
+            WHEN 'pp' THEN i.money / 1000
+            WHEN 'gp' THEN i.money % 1000 / 100
+            WHEN 'sp' THEN i.money % 100 / 10
+            ELSE i.money % 10
+        END AS amount
+    FROM inventory i CROSS JOIN currency c
+) coins
+WHERE amount > 0;
@@ -0,0 +1,46 @@
+-- Independent groups sharing one server, each with its own members, DMs, inventories, public presets and currencies.
+-- Members can only join a campaign by themselves while it is not locked.
+CREATE TABLE campaign (
+    uuid TEXT NOT NULL PRIMARY KEY,
+    name TEXT NOT NULL,
+    locked BOOLEAN NOT NULL DEFAULT TRUE,
+    creation TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE campaign_member (
+    campaign_uuid TEXT NOT NULL,
+    user_uuid TEXT NOT NULL,
+    dm BOOLEAN NOT NULL DEFAULT FALSE,
+    creation TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY(campaign_uuid, user_uuid),
+    FOREIGN KEY(campaign_uuid) REFERENCES campaign(uuid) ON DELETE CASCADE,
+    FOREIGN KEY(user_uuid) REFERENCES "user"(uuid) ON DELETE CASCADE
+);
+
+CREATE INDEX campaign_member_user_idx ON campaign_member(user_uuid);
+
+-- Existing data becomes the default campaign, open for everyone who registers, with the existing DMs as its DMs
+INSERT INTO campaign (uuid, name, locked)
+SELECT gen_random_uuid()::TEXT, 'Default campaign', FALSE
+WHERE EXISTS (SELECT 1 FROM "user") OR EXISTS (SELECT 1 FROM item_preset);
+
+INSERT INTO campaign_member (campaign_uuid, user_uuid, dm)
+SELECT c.uuid, u.uuid, u.dm = 1
+FROM campaign c CROSS JOIN "user" u;
+
+ALTER TABLE inventory ADD COLUMN campaign_uuid TEXT REFERENCES campaign(uuid) ON DELETE CASCADE;
+UPDATE inventory SET campaign_uuid = (SELECT uuid FROM campaign);
+ALTER TABLE inventory ALTER COLUMN campaign_uuid SET NOT NULL;
+CREATE INDEX inventory_campaign_idx ON inventory(campaign_uuid);
+
+ALTER TABLE item_preset ADD COLUMN campaign_uuid TEXT REFERENCES campaign(uuid) ON DELETE CASCADE;
+UPDATE item_preset SET campaign_uuid = (SELECT uuid FROM campaign);
+ALTER TABLE item_preset ALTER COLUMN campaign_uuid SET NOT NULL;
+
+-- Every campaign brings its own currencies, fresh installations have no campaign to keep the defaults for
+ALTER TABLE currency ADD COLUMN campaign_uuid TEXT REFERENCES campaign(uuid) ON DELETE CASCADE;
+UPDATE currency SET campaign_uuid = (SELECT uuid FROM campaign);
+DELETE FROM currency WHERE campaign_uuid IS NULL;
+ALTER TABLE currency ALTER COLUMN campaign_uuid SET NOT NULL;
+ALTER TABLE currency DROP CONSTRAINT currency_abbreviation_key;
+ALTER TABLE currency ADD UNIQUE (campaign_uuid, abbreviation);
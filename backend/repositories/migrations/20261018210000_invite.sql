@@ -0,0 +1,25 @@
+-- Invitations DMs hand out to let new users register while the server is locked.
+-- Campaign invites also make the user a player of the campaign. max_uses and expires_at are unlimited when NULL.
+CREATE TABLE invite (
+    uuid TEXT NOT NULL PRIMARY KEY,
+    code TEXT NOT NULL UNIQUE,
+    creator_uuid TEXT NOT NULL,
+    campaign_uuid TEXT,
+    max_uses INTEGER CHECK (max_uses > 0),
+    uses INTEGER NOT NULL DEFAULT 0,
+    expires_at TIMESTAMP,
+    revoked BOOLEAN NOT NULL DEFAULT FALSE,
+    creation TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(creator_uuid) REFERENCES "user"(uuid) ON DELETE CASCADE,
+    FOREIGN KEY(campaign_uuid) REFERENCES campaign(uuid) ON DELETE CASCADE
+);
+
+-- Who redeemed which invite
+CREATE TABLE invite_redemption (
+    invite_uuid TEXT NOT NULL,
+    user_uuid TEXT NOT NULL,
+    creation TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY(invite_uuid, user_uuid),
+    FOREIGN KEY(invite_uuid) REFERENCES invite(uuid) ON DELETE CASCADE,
+    FOREIGN KEY(user_uuid) REFERENCES "user"(uuid) ON DELETE CASCADE
+);
@@ -0,0 +1,10 @@
+-- Server wide settings as key value pairs, so they survive restarts and are shared between instances.
+CREATE TABLE setting (
+    key TEXT NOT NULL PRIMARY KEY,
+    value TEXT NOT NULL,
+    updated TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+-- New users can only register with an invite while the server is locked, fresh installations are open for the first DM
+INSERT INTO setting (key, value)
+SELECT 'locked', CASE WHEN EXISTS (SELECT 1 FROM "user") THEN 'true' ELSE 'false' END;
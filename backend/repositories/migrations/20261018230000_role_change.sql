@@ -0,0 +1,12 @@
+-- Audit of granted and revoked DM status, campaign_uuid is NULL for the server DM status.
+CREATE TABLE role_change (
+    id BIGSERIAL PRIMARY KEY,
+    user_uuid TEXT NOT NULL,
+    changed_by TEXT NOT NULL,
+    campaign_uuid TEXT,
+    dm BOOLEAN NOT NULL,
+    creation TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(campaign_uuid) REFERENCES campaign(uuid) ON DELETE CASCADE
+);
+
+CREATE INDEX role_change_campaign_idx ON role_change(campaign_uuid);
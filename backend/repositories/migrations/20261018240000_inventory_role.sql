@@ -0,0 +1,11 @@
+-- Roles beyond reading and writing, users with a role are also readers of the inventory.
+-- The owner holds every permission, inventory_writer keeps all permissions but sharing.
+CREATE TABLE inventory_role (
+    inventory_uuid TEXT NOT NULL,
+    user_uuid TEXT NOT NULL,
+    role TEXT NOT NULL CHECK (role IN ('viewer', 'itemEditor', 'moneyEditor', 'manager')),
+    creation TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY(inventory_uuid, user_uuid),
+    FOREIGN KEY(inventory_uuid) REFERENCES inventory(uuid) ON DELETE CASCADE,
+    FOREIGN KEY(user_uuid) REFERENCES "user"(uuid) ON DELETE CASCADE
+);
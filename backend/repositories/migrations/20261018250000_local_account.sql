@@ -0,0 +1,11 @@
+-- Username and password login for users without Discord, the user uuid of a local account is a random uuid.
+CREATE TABLE local_account (
+    user_uuid TEXT PRIMARY KEY,
+    username TEXT NOT NULL,
+    password_hash TEXT NOT NULL,
+    must_change_password BOOLEAN NOT NULL DEFAULT FALSE,
+    updated TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(user_uuid) REFERENCES "user"(uuid) ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX local_account_username_idx ON local_account (LOWER(username));
@@ -0,0 +1,15 @@
+-- Logins through identity providers such as Discord or an OpenID Connect server, a user can link several providers.
+CREATE TABLE user_identity (
+    provider TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    user_uuid TEXT NOT NULL,
+    creation TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY(provider, subject),
+    UNIQUE(provider, user_uuid),
+    FOREIGN KEY(user_uuid) REFERENCES "user"(uuid) ON DELETE CASCADE
+);
+
+-- Users that are not local accounts logged in through Discord, which used the Discord id as user uuid.
+INSERT INTO user_identity (provider, subject, user_uuid)
+SELECT 'discord', u.uuid, u.uuid FROM "user" u
+WHERE NOT EXISTS (SELECT 1 FROM local_account l WHERE l.user_uuid = u.uuid);
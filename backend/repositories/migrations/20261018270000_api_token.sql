@@ -0,0 +1,14 @@
+-- Personal API tokens sent as `Authorization: Bearer`, only the SHA-256 hash of a token is stored.
+CREATE TABLE api_token (
+    uuid TEXT PRIMARY KEY,
+    user_uuid TEXT NOT NULL,
+    name TEXT NOT NULL,
+    token_hash TEXT NOT NULL UNIQUE,
+    read_only BOOLEAN NOT NULL DEFAULT FALSE,
+    expires_at TIMESTAMP,
+    last_used TIMESTAMP,
+    creation TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(user_uuid) REFERENCES "user"(uuid) ON DELETE CASCADE
+);
+
+CREATE INDEX api_token_user_idx ON api_token(user_uuid);
@@ -0,0 +1,13 @@
+-- Login sessions, the session cookie holds a random token of which only the SHA-256 hash is stored.
+CREATE TABLE session (
+    uuid TEXT PRIMARY KEY,
+    user_uuid TEXT NOT NULL,
+    token_hash TEXT NOT NULL UNIQUE,
+    user_agent TEXT,
+    creation TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    last_seen TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    expires_at TIMESTAMP NOT NULL,
+    FOREIGN KEY(user_uuid) REFERENCES "user"(uuid) ON DELETE CASCADE
+);
+
+CREATE INDEX session_user_idx ON session(user_uuid);
@@ -0,0 +1,6 @@
+-- Only invites created by server DMs let new users register while the server is locked,
+-- invites of campaign DMs only let existing users join the campaign.
+ALTER TABLE invite ADD COLUMN allows_registration BOOLEAN NOT NULL DEFAULT FALSE;
+
+UPDATE invite SET allows_registration = TRUE
+WHERE creator_uuid IN (SELECT uuid FROM "user" WHERE dm = 1);
@@ -1,3 +1,5 @@
+use std::collections::{HashMap, HashSet};
+
 use serde::{Deserialize, Serialize};
 use time::PrimitiveDateTime;
 use utoipa::ToSchema;
@@ -17,7 +19,10 @@ pub struct User {
 pub struct FullFrontendInventory {
     pub uuid: String,
     pub owner_uuid: String,
+    pub campaign_uuid: String,
+    /// Total value of the coins in the smallest unit
     pub money: i32,
+    pub coins: Vec<CoinAmount>,
     pub name: String,
     pub items: Vec<FrontendItem>,
     pub reader: Vec<String>,
@@ -26,6 +31,59 @@ pub struct FullFrontendInventory {
     pub creation: Option<PrimitiveDateTime>,
 }
 
+/// An inventory as seen by players, its items lack the fields only DMs may read.
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayerFullFrontendInventory {
+    pub uuid: String,
+    pub owner_uuid: String,
+    pub campaign_uuid: String,
+    /// Total value of the coins in the smallest unit
+    pub money: i32,
+    pub coins: Vec<CoinAmount>,
+    pub name: String,
+    pub items: Vec<PlayerFrontendItem>,
+    pub reader: Vec<String>,
+    pub writer: Vec<String>,
+    #[schema(value_type = String, format = "date-time")]
+    pub creation: Option<PrimitiveDateTime>,
+}
+
+impl From<FullFrontendInventory> for PlayerFullFrontendInventory {
+    fn from(inventory: FullFrontendInventory) -> Self {
+        PlayerFullFrontendInventory {
+            uuid: inventory.uuid,
+            owner_uuid: inventory.owner_uuid,
+            campaign_uuid: inventory.campaign_uuid,
+            money: inventory.money,
+            coins: inventory.coins,
+            name: inventory.name,
+            items: inventory.items.into_iter().map(Into::into).collect(),
+            reader: inventory.reader,
+            writer: inventory.writer,
+            creation: inventory.creation,
+        }
+    }
+}
+
+/// An inventory as seen by the requesting user, only DMs get the DM notes.
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(untagged)]
+pub enum FrontendInventoryView {
+    Dm(FullFrontendInventory),
+    Player(PlayerFullFrontendInventory),
+}
+
+impl FrontendInventoryView {
+    pub fn for_viewer(inventory: FullFrontendInventory, is_dm: bool) -> Self {
+        if is_dm {
+            FrontendInventoryView::Dm(inventory)
+        } else {
+            FrontendInventoryView::Player(inventory.into())
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct RawInventory {
@@ -35,6 +93,145 @@ pub struct RawInventory {
     pub name: String,
     #[schema(value_type = String, format = "date-time")]
     pub creation: Option<PrimitiveDateTime>,
+    /// Strength score the carrying capacity is derived from
+    pub strength: Option<i32>,
+    /// Overrides the carrying capacity derived from the strength
+    pub carrying_capacity: Option<f32>,
+    /// Coins add to the carried weight
+    #[serde(default)]
+    pub count_money_weight: bool,
+    #[serde(default)]
+    pub campaign_uuid: String,
+}
+
+/// An independent group on the server with its own members, DMs, inventories, public presets and currencies.
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Campaign {
+    pub uuid: String,
+    pub name: String,
+    /// Users can not join by themselves
+    pub locked: bool,
+    #[schema(value_type = String, format = "date-time")]
+    pub creation: Option<PrimitiveDateTime>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CampaignMember {
+    pub campaign_uuid: String,
+    pub user_uuid: String,
+    /// The member is a DM of the campaign
+    pub dm: bool,
+    #[schema(value_type = String, format = "date-time")]
+    pub creation: Option<PrimitiveDateTime>,
+}
+
+/// Audit record of a granted or revoked DM status.
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RoleChange {
+    pub id: i64,
+    pub user_uuid: String,
+    /// The DM who changed the role
+    pub changed_by: String,
+    /// Campaign of the DM status, not set for the server DM status
+    pub campaign_uuid: Option<String>,
+    /// The user became a DM, otherwise the DM status was revoked
+    pub dm: bool,
+    #[schema(value_type = String, format = "date-time")]
+    pub creation: Option<PrimitiveDateTime>,
+}
+
+/// A login session of a user, the session token is only known to the cookie.
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Session {
+    pub uuid: String,
+    pub user_uuid: String,
+    /// Browser or client the session was started with
+    pub user_agent: Option<String>,
+    #[schema(value_type = String, format = "date-time")]
+    pub creation: Option<PrimitiveDateTime>,
+    #[schema(value_type = String, format = "date-time")]
+    pub last_seen: Option<PrimitiveDateTime>,
+    #[schema(value_type = String, format = "date-time")]
+    pub expires_at: PrimitiveDateTime,
+}
+
+/// A personal API token, the token itself is only shown on creation.
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiToken {
+    pub uuid: String,
+    pub user_uuid: String,
+    pub name: String,
+    /// The token can only be used for `GET` requests
+    pub read_only: bool,
+    #[schema(value_type = Option<String>, format = "date-time")]
+    pub expires_at: Option<PrimitiveDateTime>,
+    #[schema(value_type = Option<String>, format = "date-time")]
+    pub last_used: Option<PrimitiveDateTime>,
+    #[schema(value_type = String, format = "date-time")]
+    pub creation: Option<PrimitiveDateTime>,
+}
+
+/// Login of a user through an identity provider.
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UserIdentity {
+    /// Id of the provider, e.g. `discord`
+    pub provider: String,
+    /// Id of the user at the provider
+    pub subject: String,
+    pub user_uuid: String,
+    #[schema(value_type = String, format = "date-time")]
+    pub creation: Option<PrimitiveDateTime>,
+}
+
+/// Username and password login of a user.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalAccount {
+    pub user_uuid: String,
+    pub username: String,
+    /// Argon2 hash in the PHC string format
+    #[serde(skip_serializing)]
+    pub password_hash: String,
+    /// Set after a DM reset the password, until the user chose a new one
+    pub must_change_password: bool,
+    pub updated: Option<PrimitiveDateTime>,
+}
+
+/// An invitation into a campaign, invites of server DMs also let a new user register while the server is locked.
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Invite {
+    pub uuid: String,
+    /// The code the invited user logs in with
+    pub code: String,
+    pub creator_uuid: String,
+    /// Campaign the user joins as player on redemption
+    pub campaign_uuid: Option<String>,
+    /// Number of users that can redeem the invite, unlimited if not set
+    pub max_uses: Option<i32>,
+    pub uses: i32,
+    #[schema(value_type = Option<String>, format = "date-time")]
+    pub expires_at: Option<PrimitiveDateTime>,
+    pub revoked: bool,
+    #[schema(value_type = String, format = "date-time")]
+    pub creation: Option<PrimitiveDateTime>,
+    /// New users can register with it while the server is locked, only for invites of server DMs
+    pub allows_registration: bool,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct InviteRedemption {
+    pub invite_uuid: String,
+    pub user_uuid: String,
+    #[schema(value_type = String, format = "date-time")]
+    pub creation: Option<PrimitiveDateTime>,
 }
 
 #[derive(Serialize, Deserialize, ToSchema)]
@@ -67,24 +264,62 @@ pub struct ItemPreset {
     pub item_type: String,
     #[schema(value_type = String, format = "date-time")]
     pub creation: Option<PrimitiveDateTime>,
+    /// Items of this preset can hold other items
+    pub container: bool,
+    /// The contents do not add to the weight of the container
+    pub weightless_contents: bool,
+    /// Campaign the preset belongs to, public presets are shared with its members
+    pub campaign_uuid: String,
 }
 
 #[derive(Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct InventoryItem {
+    pub uuid: String,
     pub inventory_uuid: String,
     pub item_preset_uuid: String,
     pub dm_note: String,
     pub amount: i32,
     pub sorting: i32,
     pub inventory_item_note: String,
+    /// Set for unidentified items, see `ItemDisguise`
+    pub disguise_name: Option<String>,
+    pub disguise_description: Option<String>,
+    pub disguise_item_type: Option<String>,
     #[schema(value_type = String, format = "date-time")]
     pub creation: Option<PrimitiveDateTime>,
+    /// The container item holding this item, `None` if it lies directly in the inventory
+    pub container_item_uuid: Option<String>,
+}
+
+/// What players see of an unidentified item instead of its preset.
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemDisguise {
+    pub name: String,
+    pub description: String,
+    pub item_type: String,
+}
+
+impl ItemDisguise {
+    /// Builds the disguise from the disguise columns, `None` if the item is identified.
+    pub fn from_columns(
+        name: Option<String>,
+        description: Option<String>,
+        item_type: Option<String>,
+    ) -> Option<Self> {
+        Some(ItemDisguise {
+            name: name?,
+            description: description.unwrap_or_default(),
+            item_type: item_type.unwrap_or_default(),
+        })
+    }
 }
 
 #[derive(Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct FrontendItem {
+    pub uuid: String,
     pub name: String,
     pub amount: i32,
     pub dm_note: String,
@@ -96,4 +331,535 @@ pub struct FrontendItem {
     pub item_type: String,
     pub preset_reference: String,
     pub inventory_item_note: String,
+    /// Set for unidentified items, players only see the disguise
+    pub disguise: Option<ItemDisguise>,
+    pub container: bool,
+    pub weightless_contents: bool,
+    /// UUID of the container item holding this item
+    pub container_reference: Option<String>,
+    /// Items inside this container, only filled in the tree of a `FullFrontendInventory`
+    #[schema(no_recursion)]
+    pub contents: Vec<FrontendItem>,
+    /// Weight of the whole stack including its contents
+    pub total_weight: f32,
+}
+
+impl FrontendItem {
+    /// Nests the given flat items into their containers and sums up the weight of each container.
+    ///
+    /// Items referring to a container that is not part of the list are put on the top level.
+    pub fn into_tree(items: Vec<FrontendItem>) -> Vec<FrontendItem> {
+        let references: HashSet<String> = items.iter().map(|item| item.uuid.clone()).collect();
+        let mut children: HashMap<String, Vec<FrontendItem>> = HashMap::new();
+        let mut roots = Vec::new();
+        for item in items {
+            match &item.container_reference {
+                Some(container) if references.contains(container) => {
+                    children.entry(container.clone()).or_default().push(item)
+                }
+                _ => roots.push(item),
+            }
+        }
+        roots
+            .into_iter()
+            .map(|root| root.with_contents(&mut children))
+            .collect()
+    }
+
+    fn with_contents(mut self, children: &mut HashMap<String, Vec<FrontendItem>>) -> Self {
+        self.contents = children
+            .remove(&self.uuid)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|child| child.with_contents(children))
+            .collect();
+        self.total_weight = self.weight * self.amount as f32;
+        if !self.weightless_contents {
+            self.total_weight += self.contents.iter().map(|c| c.total_weight).sum::<f32>();
+        }
+        self
+    }
+}
+
+/// An item as seen by players, without the fields only DMs may read.
+///
+/// Unidentified items show their disguise and hide everything that would reveal the preset.
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayerFrontendItem {
+    pub uuid: String,
+    pub name: String,
+    pub amount: i32,
+    pub description: String,
+    pub price: i32,
+    pub preset_creator: String,
+    pub weight: f32,
+    pub sorting: i32,
+    pub item_type: String,
+    pub preset_reference: String,
+    pub inventory_item_note: String,
+    pub unidentified: bool,
+    pub container: bool,
+    pub weightless_contents: bool,
+    pub container_reference: Option<String>,
+    #[schema(no_recursion)]
+    pub contents: Vec<PlayerFrontendItem>,
+    pub total_weight: f32,
+}
+
+impl From<FrontendItem> for PlayerFrontendItem {
+    fn from(item: FrontendItem) -> Self {
+        if let Some(disguise) = item.disguise {
+            return PlayerFrontendItem {
+                uuid: item.uuid,
+                name: disguise.name,
+                amount: item.amount,
+                description: disguise.description,
+                price: 0,
+                preset_creator: String::new(),
+                weight: item.weight,
+                sorting: item.sorting,
+                item_type: disguise.item_type,
+                preset_reference: String::new(),
+                inventory_item_note: item.inventory_item_note,
+                unidentified: true,
+                container: item.container,
+                weightless_contents: item.weightless_contents,
+                container_reference: item.container_reference,
+                contents: item.contents.into_iter().map(Into::into).collect(),
+                total_weight: item.total_weight,
+            };
+        }
+        PlayerFrontendItem {
+            uuid: item.uuid,
+            name: item.name,
+            amount: item.amount,
+            description: item.description,
+            price: item.price,
+            preset_creator: item.preset_creator,
+            weight: item.weight,
+            sorting: item.sorting,
+            item_type: item.item_type,
+            preset_reference: item.preset_reference,
+            inventory_item_note: item.inventory_item_note,
+            unidentified: false,
+            container: item.container,
+            weightless_contents: item.weightless_contents,
+            container_reference: item.container_reference,
+            contents: item.contents.into_iter().map(Into::into).collect(),
+            total_weight: item.total_weight,
+        }
+    }
+}
+
+/// An item as seen by the requesting user, only DMs get the DM note.
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(untagged)]
+pub enum FrontendItemView {
+    Dm(FrontendItem),
+    Player(PlayerFrontendItem),
+}
+
+impl FrontendItemView {
+    pub fn for_viewer(item: FrontendItem, is_dm: bool) -> Self {
+        if is_dm {
+            FrontendItemView::Dm(item)
+        } else {
+            FrontendItemView::Player(item.into())
+        }
+    }
+}
+
+/// Rule set used to determine the encumbrance of an inventory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum EncumbranceRule {
+    /// Only exceeding the carrying capacity (15 x strength) matters
+    Standard,
+    /// Encumbered above 5 x strength, heavily encumbered above 10 x strength
+    Variant,
+}
+
+impl std::str::FromStr for EncumbranceRule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "standard" => Ok(EncumbranceRule::Standard),
+            "variant" => Ok(EncumbranceRule::Variant),
+            _ => Err(anyhow::anyhow!("unknown encumbrance rule {}", s)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum EncumbranceState {
+    Unencumbered,
+    Encumbered,
+    HeavilyEncumbered,
+    OverCapacity,
+}
+
+/// Carried weight of an inventory compared against its carrying capacity.
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Encumbrance {
+    pub rule: EncumbranceRule,
+    /// Weight of all items, without the contents of weightless containers
+    pub item_weight: f32,
+    /// Weight of the coins, 0 unless the inventory counts money weight
+    pub money_weight: f32,
+    pub total_weight: f32,
+    /// `None` if the inventory has neither a strength nor a carrying capacity
+    pub capacity: Option<f32>,
+    /// Weight above which the inventory is encumbered, only used by the variant rule
+    pub encumbered_above: Option<f32>,
+    /// Weight above which the inventory is heavily encumbered, only used by the variant rule
+    pub heavily_encumbered_above: Option<f32>,
+    /// `None` if the capacity is unknown
+    pub state: Option<EncumbranceState>,
+}
+
+impl Encumbrance {
+    /// Pounds of weight per strength point that can be carried.
+    const CAPACITY_PER_STRENGTH: f32 = 15.0;
+    /// Coins weighing one pound.
+    const COINS_PER_POUND: f32 = 50.0;
+
+    /// Determines the encumbrance of an inventory from its carried weight and configuration.
+    ///
+    /// Without a strength score the variant thresholds are a third and two thirds of the carrying capacity.
+    pub fn compute(
+        rule: EncumbranceRule,
+        item_weight: f32,
+        coins: i32,
+        inventory: &RawInventory,
+    ) -> Self {
+        let money_weight = if inventory.count_money_weight {
+            coins as f32 / Self::COINS_PER_POUND
+        } else {
+            0.0
+        };
+        let total_weight = item_weight + money_weight;
+        let capacity = inventory.carrying_capacity.or(inventory
+            .strength
+            .map(|strength| strength as f32 * Self::CAPACITY_PER_STRENGTH));
+        let (encumbered_above, heavily_encumbered_above) = match (rule, inventory.strength) {
+            (EncumbranceRule::Standard, _) => (None, None),
+            (EncumbranceRule::Variant, Some(strength)) => {
+                (Some(strength as f32 * 5.0), Some(strength as f32 * 10.0))
+            }
+            (EncumbranceRule::Variant, None) => {
+                (capacity.map(|c| c / 3.0), capacity.map(|c| c * 2.0 / 3.0))
+            }
+        };
+        let state = capacity.map(|capacity| {
+            if total_weight > capacity {
+                EncumbranceState::OverCapacity
+            } else if heavily_encumbered_above.is_some_and(|w| total_weight > w) {
+                EncumbranceState::HeavilyEncumbered
+            } else if encumbered_above.is_some_and(|w| total_weight > w) {
+                EncumbranceState::Encumbered
+            } else {
+                EncumbranceState::Unencumbered
+            }
+        });
+        Encumbrance {
+            rule,
+            item_weight,
+            money_weight,
+            total_weight,
+            capacity,
+            encumbered_above,
+            heavily_encumbered_above,
+            state,
+        }
+    }
+}
+
+/// Something a user may do with an inventory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum InventoryPermission {
+    View,
+    EditItems,
+    EditMoney,
+    /// Rename the inventory and configure its encumbrance
+    EditSettings,
+    /// Add and remove shares and roles
+    Share,
+    /// Delete the inventory
+    Own,
+}
+
+/// Role of a user in an inventory, granting a set of permissions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum InventoryRole {
+    Viewer,
+    ItemEditor,
+    MoneyEditor,
+    /// Co-owner, may do everything but delete the inventory
+    Manager,
+    Owner,
+}
+
+impl InventoryRole {
+    /// Returns the name under which the role is stored in the database.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InventoryRole::Viewer => "viewer",
+            InventoryRole::ItemEditor => "itemEditor",
+            InventoryRole::MoneyEditor => "moneyEditor",
+            InventoryRole::Manager => "manager",
+            InventoryRole::Owner => "owner",
+        }
+    }
+
+    /// Checks if the role grants a permission.
+    pub fn permits(&self, permission: InventoryPermission) -> bool {
+        match self {
+            InventoryRole::Viewer => permission == InventoryPermission::View,
+            InventoryRole::ItemEditor => matches!(
+                permission,
+                InventoryPermission::View | InventoryPermission::EditItems
+            ),
+            InventoryRole::MoneyEditor => matches!(
+                permission,
+                InventoryPermission::View | InventoryPermission::EditMoney
+            ),
+            InventoryRole::Manager => permission != InventoryPermission::Own,
+            InventoryRole::Owner => true,
+        }
+    }
+}
+
+impl std::str::FromStr for InventoryRole {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "viewer" => Ok(InventoryRole::Viewer),
+            "itemEditor" => Ok(InventoryRole::ItemEditor),
+            "moneyEditor" => Ok(InventoryRole::MoneyEditor),
+            "manager" => Ok(InventoryRole::Manager),
+            "owner" => Ok(InventoryRole::Owner),
+            _ => Err(anyhow::anyhow!("unknown inventory role {}", s)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct InventoryRoleAssignment {
+    pub user_uuid: String,
+    pub role: InventoryRole,
+    #[schema(value_type = String, format = "date-time")]
+    pub creation: Option<PrimitiveDateTime>,
+}
+
+/// The kind of change that happened on an inventory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum InventoryChangeKind {
+    InventoryCreated,
+    InventoryEdited,
+    InventoryDeleted,
+    ItemAdded,
+    ItemEdited,
+    ItemRemoved,
+    DmNoteEdited,
+    SharesChanged,
+    ItemRevealed,
+}
+
+impl InventoryChangeKind {
+    /// Returns the name under which the kind is stored in the database.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InventoryChangeKind::InventoryCreated => "inventoryCreated",
+            InventoryChangeKind::InventoryEdited => "inventoryEdited",
+            InventoryChangeKind::InventoryDeleted => "inventoryDeleted",
+            InventoryChangeKind::ItemAdded => "itemAdded",
+            InventoryChangeKind::ItemEdited => "itemEdited",
+            InventoryChangeKind::ItemRemoved => "itemRemoved",
+            InventoryChangeKind::DmNoteEdited => "dmNoteEdited",
+            InventoryChangeKind::SharesChanged => "sharesChanged",
+            InventoryChangeKind::ItemRevealed => "itemRevealed",
+        }
+    }
+}
+
+impl std::str::FromStr for InventoryChangeKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "inventoryCreated" => Ok(InventoryChangeKind::InventoryCreated),
+            "inventoryEdited" => Ok(InventoryChangeKind::InventoryEdited),
+            "inventoryDeleted" => Ok(InventoryChangeKind::InventoryDeleted),
+            "itemAdded" => Ok(InventoryChangeKind::ItemAdded),
+            "itemEdited" => Ok(InventoryChangeKind::ItemEdited),
+            "itemRemoved" => Ok(InventoryChangeKind::ItemRemoved),
+            "dmNoteEdited" => Ok(InventoryChangeKind::DmNoteEdited),
+            "sharesChanged" => Ok(InventoryChangeKind::SharesChanged),
+            "itemRevealed" => Ok(InventoryChangeKind::ItemRevealed),
+            _ => Err(anyhow::anyhow!("unknown change kind {}", s)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct InventoryChange {
+    pub id: i64,
+    pub inventory_uuid: String,
+    pub user_uuid: String,
+    pub change_kind: InventoryChangeKind,
+    pub item_uuid: Option<String>,
+    /// Preset of the affected item
+    pub item_preset_uuid: Option<String>,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+    /// Milliseconds since UNIX epoch
+    pub changed_at: i64,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct InventoryOperation {
+    pub id: i64,
+    pub inventory_uuid: String,
+    pub user_uuid: String,
+    pub change_kind: InventoryChangeKind,
+    pub item_uuid: Option<String>,
+    /// Preset of the affected item
+    pub item_preset_uuid: Option<String>,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+    pub undone: bool,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MoneyTransaction {
+    pub uuid: String,
+    pub inventory_uuid: String,
+    pub user_uuid: String,
+    pub delta: i32,
+    pub reason: Option<String>,
+    /// The other inventory if the transaction is part of a transfer
+    pub counterpart_inventory_uuid: Option<String>,
+    #[schema(value_type = String, format = "date-time")]
+    pub creation: Option<PrimitiveDateTime>,
+}
+
+/// A denomination of the currency system.
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Currency {
+    pub uuid: String,
+    pub name: String,
+    pub abbreviation: String,
+    /// Worth of one coin in the smallest unit
+    pub value: i32,
+    /// Coins may be broken up and handed out as change automatically
+    pub auto_convert: bool,
+    #[schema(value_type = String, format = "date-time")]
+    pub creation: Option<PrimitiveDateTime>,
+    pub campaign_uuid: String,
+}
+
+/// A number of coins of one currency, negative amounts withdraw coins where accepted.
+#[derive(Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CoinAmount {
+    pub currency_uuid: String,
+    pub amount: i32,
+}
+
+/// Value of all items of one item type.
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemTypeValue {
+    pub item_type: String,
+    pub amount: i64,
+    pub value: i64,
+}
+
+/// Cash and item value of an inventory.
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct InventoryValue {
+    pub inventory_uuid: String,
+    pub name: String,
+    pub money: i64,
+    /// Sum of price x amount of all items
+    pub item_value: i64,
+    pub total_value: i64,
+    pub by_item_type: Vec<ItemTypeValue>,
+}
+
+/// Cash and item value of several inventories, per inventory and in total.
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WealthReport {
+    pub inventories: Vec<InventoryValue>,
+    pub money: i64,
+    pub item_value: i64,
+    pub total_value: i64,
+    pub by_item_type: Vec<ItemTypeValue>,
+}
+
+/// State of an inventory item before and after a change, `None` meaning the item did not exist.
+pub struct ItemChange {
+    pub before: Option<InventoryItem>,
+    pub after: Option<InventoryItem>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemGift {
+    pub uuid: String,
+    pub source_inventory_uuid: String,
+    pub target_inventory_uuid: String,
+    /// Empty for disguised items, see `redacted`
+    pub item_preset_uuid: String,
+    pub sender_uuid: String,
+    pub amount: i32,
+    #[serde(skip)]
+    pub dm_note: String,
+    pub sorting: i32,
+    pub inventory_item_note: String,
+    #[serde(skip)]
+    pub disguise_name: Option<String>,
+    #[serde(skip)]
+    pub disguise_description: Option<String>,
+    #[serde(skip)]
+    pub disguise_item_type: Option<String>,
+    #[schema(value_type = String, format = "date-time")]
+    pub creation: Option<PrimitiveDateTime>,
+}
+
+impl ItemGift {
+    /// Hides the real preset of a disguised item, so the gift does not reveal it.
+    pub fn redacted(mut self) -> Self {
+        if self.disguise_name.is_some() {
+            self.item_preset_uuid = String::new();
+        }
+        self
+    }
+}
+
+/// Outcome of splitting money and items across several inventories.
+pub struct LootSplit {
+    /// Withdrawals from the source and deposits into the targets
+    pub transactions: Vec<MoneyTransaction>,
+    /// Changes of the items in the source and the target inventories
+    pub item_changes: Vec<ItemChange>,
+    /// Items held for targets that receive them as a gift
+    pub gifts: Vec<ItemGift>,
+    /// Money that could not be divided evenly and stayed in the source
+    pub remainder: i32,
 }
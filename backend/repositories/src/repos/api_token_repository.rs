@@ -0,0 +1,82 @@
+use crate::model::ApiToken;
+use anyhow::{bail, Result};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub struct ApiTokenRepository {
+    pool: PgPool,
+}
+
+impl ApiTokenRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Stores a new token by its hash, `None` lets the token never expire.
+    pub async fn create_token(
+        &self,
+        user_uuid: &str,
+        name: &str,
+        token_hash: &str,
+        read_only: bool,
+        expires_in_days: Option<i32>,
+    ) -> Result<ApiToken> {
+        if expires_in_days.is_some_and(|days| days <= 0) {
+            bail!("expires_in_days must be positive");
+        }
+        let token = sqlx::query_as!(
+            ApiToken,
+            "INSERT INTO api_token (uuid, user_uuid, name, token_hash, read_only, expires_at)
+             VALUES ($1, $2, $3, $4, $5, CURRENT_TIMESTAMP + make_interval(days => $6))
+             RETURNING uuid, user_uuid, name, read_only, expires_at, last_used, creation",
+            Uuid::new_v4().to_string(),
+            user_uuid,
+            name,
+            token_hash,
+            read_only,
+            expires_in_days
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(token)
+    }
+
+    /// Returns the tokens of a user, the newest first.
+    pub async fn get_tokens(&self, user_uuid: &str) -> Result<Vec<ApiToken>> {
+        let tokens = sqlx::query_as!(
+            ApiToken,
+            "SELECT uuid, user_uuid, name, read_only, expires_at, last_used, creation
+             FROM api_token WHERE user_uuid = $1 ORDER BY creation DESC",
+            user_uuid
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(tokens)
+    }
+
+    /// Deletes a token of a user, returns the number of deleted tokens.
+    pub async fn revoke_token(&self, uuid: &str, user_uuid: &str) -> Result<u64> {
+        let result = sqlx::query!(
+            "DELETE FROM api_token WHERE uuid = $1 AND user_uuid = $2",
+            uuid,
+            user_uuid
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Looks up a token that has not expired by its hash and records its use.
+    pub async fn authenticate(&self, token_hash: &str) -> Result<Option<ApiToken>> {
+        let token = sqlx::query_as!(
+            ApiToken,
+            "UPDATE api_token SET last_used = CURRENT_TIMESTAMP
+             WHERE token_hash = $1 AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)
+             RETURNING uuid, user_uuid, name, read_only, expires_at, last_used, creation",
+            token_hash
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(token)
+    }
+}
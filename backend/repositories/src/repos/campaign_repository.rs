@@ -0,0 +1,308 @@
+use crate::model::{Campaign, CampaignMember};
+use crate::repos::currency_repository::create_default_currencies;
+use crate::repos::role_change_repository::record_role_change;
+use anyhow::{bail, Result};
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+pub struct CampaignRepository {
+    pool: PgPool,
+}
+
+impl CampaignRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Creates a new locked campaign with the default currencies and the creator as its DM.
+    pub async fn create_campaign(&self, name: &str, creator_uuid: &str) -> Result<Campaign> {
+        let mut tx = self.pool.begin().await?;
+        let campaign = sqlx::query_as!(
+            Campaign,
+            "INSERT INTO campaign (uuid, name) VALUES ($1, $2) RETURNING *",
+            Uuid::new_v4().to_string(),
+            name
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        sqlx::query!(
+            "INSERT INTO campaign_member (campaign_uuid, user_uuid, dm) VALUES ($1, $2, TRUE)",
+            campaign.uuid,
+            creator_uuid
+        )
+        .execute(&mut *tx)
+        .await?;
+        create_default_currencies(&mut tx, &campaign.uuid).await?;
+        tx.commit().await?;
+        Ok(campaign)
+    }
+
+    /// Retrieves a campaign by its UUID.
+    pub async fn get_campaign(&self, uuid: &str) -> Result<Campaign> {
+        let campaign = sqlx::query_as!(Campaign, "SELECT * FROM campaign WHERE uuid = $1", uuid)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(campaign)
+    }
+
+    /// Returns all campaigns the user is a member of.
+    pub async fn get_campaigns_of_user(&self, user_uuid: &str) -> Result<Vec<Campaign>> {
+        let campaigns = sqlx::query_as!(
+            Campaign,
+            "SELECT c.* FROM campaign c
+             JOIN campaign_member m ON m.campaign_uuid = c.uuid
+             WHERE m.user_uuid = $1
+             ORDER BY c.name",
+            user_uuid
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(campaigns)
+    }
+
+    /// Returns all campaigns that are not locked.
+    pub async fn get_open_campaigns(&self) -> Result<Vec<Campaign>> {
+        let campaigns = sqlx::query_as!(
+            Campaign,
+            "SELECT * FROM campaign WHERE NOT locked ORDER BY name"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(campaigns)
+    }
+
+    /// Updates the name and lock state of a campaign, `None` keeps a value.
+    pub async fn update_campaign(
+        &self,
+        uuid: &str,
+        name: Option<&str>,
+        locked: Option<bool>,
+    ) -> Result<()> {
+        sqlx::query!(
+            "UPDATE campaign SET name = COALESCE($2, name), locked = COALESCE($3, locked) WHERE uuid = $1",
+            uuid,
+            name,
+            locked
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Deletes a campaign including its inventories, presets and currencies.
+    pub async fn delete_campaign(&self, uuid: &str) -> Result<()> {
+        sqlx::query!("DELETE FROM campaign WHERE uuid = $1", uuid)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Returns all members of a campaign, DMs first.
+    pub async fn get_members(&self, campaign_uuid: &str) -> Result<Vec<CampaignMember>> {
+        let members = sqlx::query_as!(
+            CampaignMember,
+            "SELECT * FROM campaign_member WHERE campaign_uuid = $1 ORDER BY dm DESC, creation",
+            campaign_uuid
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(members)
+    }
+
+    /// Adds a user as player to a campaign, existing members are kept as they are.
+    pub async fn add_member(&self, campaign_uuid: &str, user_uuid: &str) -> Result<()> {
+        sqlx::query!(
+            "INSERT INTO campaign_member (campaign_uuid, user_uuid) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+            campaign_uuid,
+            user_uuid
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Removes a user from a campaign and revokes their shares on inventories of the campaign they do not own.
+    ///
+    /// Fails if the user is the last DM of the campaign.
+    pub async fn remove_member(&self, campaign_uuid: &str, user_uuid: &str) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        let dms = sqlx::query_scalar!(
+            "SELECT user_uuid FROM campaign_member WHERE campaign_uuid = $1 AND dm FOR UPDATE",
+            campaign_uuid
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        if dms.len() == 1 && dms[0] == user_uuid {
+            bail!("the last DM can not leave the campaign");
+        }
+        unshare_campaign_inventories(&mut tx, campaign_uuid, user_uuid).await?;
+        sqlx::query!(
+            "DELETE FROM campaign_member WHERE campaign_uuid = $1 AND user_uuid = $2",
+            campaign_uuid,
+            user_uuid
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Grants or revokes DM status in a campaign and records who changed it.
+    ///
+    /// New DMs become readers and writers of all inventories of the campaign, former DMs lose their shares on
+    /// inventories they do not own. Fails if the user is not a member or the last DM of the campaign.
+    pub async fn set_dm(
+        &self,
+        campaign_uuid: &str,
+        user_uuid: &str,
+        dm: bool,
+        changed_by: &str,
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        let dms = sqlx::query_scalar!(
+            "SELECT user_uuid FROM campaign_member WHERE campaign_uuid = $1 AND dm FOR UPDATE",
+            campaign_uuid
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        let Some(member) = sqlx::query_as!(
+            CampaignMember,
+            "SELECT * FROM campaign_member WHERE campaign_uuid = $1 AND user_uuid = $2 FOR UPDATE",
+            campaign_uuid,
+            user_uuid
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        else {
+            bail!("the user is not a member of the campaign");
+        };
+        if member.dm == dm {
+            return Ok(());
+        }
+        if !dm && dms.len() == 1 {
+            bail!("the last DM of the campaign can not be revoked");
+        }
+        sqlx::query!(
+            "UPDATE campaign_member SET dm = $3 WHERE campaign_uuid = $1 AND user_uuid = $2",
+            campaign_uuid,
+            user_uuid,
+            dm
+        )
+        .execute(&mut *tx)
+        .await?;
+        if dm {
+            share_campaign_inventories(&mut tx, campaign_uuid, user_uuid).await?;
+        } else {
+            unshare_campaign_inventories(&mut tx, campaign_uuid, user_uuid).await?;
+        }
+        record_role_change(&mut tx, user_uuid, changed_by, Some(campaign_uuid), dm).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Checks if a user is a member of a campaign.
+    pub async fn is_member(&self, campaign_uuid: &str, user_uuid: &str) -> Result<bool> {
+        let exists = sqlx::query_scalar!(
+            "SELECT EXISTS(SELECT 1 FROM campaign_member WHERE campaign_uuid = $1 AND user_uuid = $2)",
+            campaign_uuid,
+            user_uuid
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(exists.unwrap_or(false))
+    }
+
+    /// Checks if a user is a DM of a campaign.
+    pub async fn is_dm(&self, campaign_uuid: &str, user_uuid: &str) -> Result<bool> {
+        let exists = sqlx::query_scalar!(
+            "SELECT EXISTS(SELECT 1 FROM campaign_member WHERE campaign_uuid = $1 AND user_uuid = $2 AND dm)",
+            campaign_uuid,
+            user_uuid
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(exists.unwrap_or(false))
+    }
+
+    /// Retrieves the IDs of all DMs of a campaign.
+    pub async fn get_dm_ids(&self, campaign_uuid: &str) -> Result<Vec<String>> {
+        let dm_ids = sqlx::query_scalar!(
+            "SELECT user_uuid FROM campaign_member WHERE campaign_uuid = $1 AND dm",
+            campaign_uuid
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(dm_ids)
+    }
+
+    /// Returns the UUIDs of all campaigns the user is a DM of.
+    pub async fn get_dm_campaign_ids(&self, user_uuid: &str) -> Result<Vec<String>> {
+        let campaign_ids = sqlx::query_scalar!(
+            "SELECT campaign_uuid FROM campaign_member WHERE user_uuid = $1 AND dm",
+            user_uuid
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(campaign_ids)
+    }
+}
+
+/// Makes a user reader and writer of all inventories of a campaign they do not own on the given connection.
+async fn share_campaign_inventories(
+    conn: &mut PgConnection,
+    campaign_uuid: &str,
+    user_uuid: &str,
+) -> Result<()> {
+    sqlx::query!(
+        "INSERT INTO inventory_reader (user_uuid, inventory_uuid)
+         SELECT $2, uuid FROM inventory WHERE campaign_uuid = $1 AND owner_uuid <> $2
+         ON CONFLICT DO NOTHING",
+        campaign_uuid,
+        user_uuid
+    )
+    .execute(&mut *conn)
+    .await?;
+    sqlx::query!(
+        "INSERT INTO inventory_writer (user_uuid, inventory_uuid)
+         SELECT $2, uuid FROM inventory WHERE campaign_uuid = $1 AND owner_uuid <> $2
+         ON CONFLICT DO NOTHING",
+        campaign_uuid,
+        user_uuid
+    )
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+/// Revokes the shares and roles of a user on all inventories of a campaign they do not own on the given connection.
+async fn unshare_campaign_inventories(
+    conn: &mut PgConnection,
+    campaign_uuid: &str,
+    user_uuid: &str,
+) -> Result<()> {
+    sqlx::query!(
+        "DELETE FROM inventory_reader r USING inventory i
+         WHERE r.inventory_uuid = i.uuid AND i.campaign_uuid = $1 AND r.user_uuid = $2 AND i.owner_uuid <> $2",
+        campaign_uuid,
+        user_uuid
+    )
+    .execute(&mut *conn)
+    .await?;
+    sqlx::query!(
+        "DELETE FROM inventory_writer w USING inventory i
+         WHERE w.inventory_uuid = i.uuid AND i.campaign_uuid = $1 AND w.user_uuid = $2 AND i.owner_uuid <> $2",
+        campaign_uuid,
+        user_uuid
+    )
+    .execute(&mut *conn)
+    .await?;
+    sqlx::query!(
+        "DELETE FROM inventory_role r USING inventory i
+         WHERE r.inventory_uuid = i.uuid AND i.campaign_uuid = $1 AND r.user_uuid = $2",
+        campaign_uuid,
+        user_uuid
+    )
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
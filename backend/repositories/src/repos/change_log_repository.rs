@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+
+use crate::model::{InventoryChange, InventoryChangeKind, InventoryItem};
+use anyhow::Result;
+use sqlx::PgPool;
+
+/// Changes recorded this many milliseconds before the cursor change are returned again by `get_changes_since`.
+///
+/// Ids are taken when a change is inserted, so a change can become visible after one with a higher id.
+const RESCAN_WINDOW_MILLIS: i64 = 5000;
+
+pub struct ChangeLogRepository {
+    pool: PgPool,
+}
+
+impl ChangeLogRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Records a change on an inventory and returns the stored log entry.
+    ///
+    /// The affected item is referenced by its UUID and its preset.
+    pub async fn record_change(
+        &self,
+        inventory_uuid: &str,
+        user_uuid: &str,
+        change_kind: InventoryChangeKind,
+        item: Option<&InventoryItem>,
+        old_value: Option<&str>,
+        new_value: Option<&str>,
+    ) -> Result<InventoryChange> {
+        let item_uuid = item.map(|i| i.uuid.clone());
+        let item_preset_uuid = item.map(|i| i.item_preset_uuid.clone());
+        let rec = sqlx::query!(
+            "INSERT INTO inventory_change_log (inventory_uuid, user_uuid, change_kind, item_uuid, item_preset_uuid, old_value, new_value)
+             VALUES ($1, $2, $3, $4, $5, $6, $7)
+             RETURNING id, changed_at",
+            inventory_uuid,
+            user_uuid,
+            change_kind.as_str(),
+            item_uuid,
+            item_preset_uuid,
+            old_value,
+            new_value
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(InventoryChange {
+            id: rec.id,
+            inventory_uuid: inventory_uuid.to_string(),
+            user_uuid: user_uuid.to_string(),
+            change_kind,
+            item_uuid,
+            item_preset_uuid,
+            old_value: old_value.map(str::to_string),
+            new_value: new_value.map(str::to_string),
+            changed_at: rec.changed_at,
+        })
+    }
+
+    /// Returns the timestamp of the latest change for each of the given inventories.
+    ///
+    /// Inventories without any recorded change are not contained in the map.
+    pub async fn get_last_changes(
+        &self,
+        inventory_uuids: &[String],
+    ) -> Result<HashMap<String, i64>> {
+        let last_changes = sqlx::query!(
+            "SELECT inventory_uuid, MAX(changed_at) AS \"changed_at!\"
+             FROM inventory_change_log
+             WHERE inventory_uuid = ANY($1)
+             GROUP BY inventory_uuid",
+            inventory_uuids
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|r| (r.inventory_uuid, r.changed_at))
+        .collect();
+        Ok(last_changes)
+    }
+
+    /// Returns the id of the latest recorded change, or `0` if nothing was recorded yet.
+    pub async fn get_latest_change_id(&self) -> Result<i64> {
+        let id = sqlx::query_scalar!("SELECT COALESCE(MAX(id), 0) FROM inventory_change_log")
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(id.unwrap_or(0))
+    }
+
+    /// Returns all changes with an id in `(after_id, up_to_id]` that happened after `after_timestamp`, oldest first.
+    ///
+    /// Changes recorded up to `RESCAN_WINDOW_MILLIS` before the change `after_id` are returned again,
+    /// so changes that were committed late are not skipped.
+    ///
+    /// Only changes on the given inventories are returned, except for deletions and share changes,
+    /// which are returned for every inventory so callers can detect lost access.
+    pub async fn get_changes_since(
+        &self,
+        after_id: i64,
+        after_timestamp: i64,
+        up_to_id: i64,
+        inventory_uuids: &[String],
+    ) -> Result<Vec<InventoryChange>> {
+        let changes = sqlx::query!(
+            "SELECT id, inventory_uuid, user_uuid, change_kind, item_uuid, item_preset_uuid, old_value, new_value, changed_at
+             FROM inventory_change_log
+             WHERE (id > $1 OR changed_at >= (SELECT changed_at FROM inventory_change_log WHERE id = $1) - $6)
+               AND changed_at > $2 AND id <= $3
+               AND (inventory_uuid = ANY($4) OR change_kind = ANY($5))
+             ORDER BY id",
+            after_id,
+            after_timestamp,
+            up_to_id,
+            inventory_uuids,
+            &[
+                InventoryChangeKind::InventoryDeleted.as_str().to_string(),
+                InventoryChangeKind::SharesChanged.as_str().to_string(),
+            ],
+            RESCAN_WINDOW_MILLIS
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|r| {
+            Ok(InventoryChange {
+                id: r.id,
+                inventory_uuid: r.inventory_uuid,
+                user_uuid: r.user_uuid,
+                change_kind: r.change_kind.parse()?,
+                item_uuid: r.item_uuid,
+                item_preset_uuid: r.item_preset_uuid,
+                old_value: r.old_value,
+                new_value: r.new_value,
+                changed_at: r.changed_at,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+        Ok(changes)
+    }
+}
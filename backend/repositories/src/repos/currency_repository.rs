@@ -0,0 +1,321 @@
+use crate::model::{CoinAmount, Currency};
+use anyhow::{bail, Result};
+use sqlx::{PgConnection, PgPool};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+pub struct CurrencyRepository {
+    pool: PgPool,
+}
+
+impl CurrencyRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Returns all currencies of a campaign, the most valuable first.
+    pub async fn get_all_currencies(&self, campaign_uuid: &str) -> Result<Vec<Currency>> {
+        let currencies = sqlx::query_as!(
+            Currency,
+            "SELECT * FROM currency WHERE campaign_uuid = $1 ORDER BY value DESC, name",
+            campaign_uuid
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(currencies)
+    }
+
+    /// Retrieves a currency by its UUID.
+    pub async fn get_currency(&self, uuid: &str) -> Result<Currency> {
+        let currency = sqlx::query_as!(Currency, "SELECT * FROM currency WHERE uuid = $1", uuid)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(currency)
+    }
+
+    /// Creates a new currency of a campaign worth the given value in the smallest unit.
+    pub async fn create_currency(
+        &self,
+        campaign_uuid: &str,
+        name: &str,
+        abbreviation: &str,
+        value: i32,
+        auto_convert: bool,
+    ) -> Result<Currency> {
+        if value <= 0 {
+            bail!("value must be positive");
+        }
+        let currency = sqlx::query_as!(
+            Currency,
+            "INSERT INTO currency (uuid, campaign_uuid, name, abbreviation, value, auto_convert) VALUES ($1, $2, $3, $4, $5, $6) RETURNING *",
+            Uuid::new_v4().to_string(),
+            campaign_uuid,
+            name,
+            abbreviation,
+            value,
+            auto_convert
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(currency)
+    }
+
+    /// Updates a currency, `None` keeps a value.
+    ///
+    /// The value can only be changed while no inventory holds coins of the currency.
+    pub async fn update_currency(
+        &self,
+        uuid: &str,
+        name: Option<&str>,
+        abbreviation: Option<&str>,
+        value: Option<i32>,
+        auto_convert: Option<bool>,
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        let currency = sqlx::query_as!(
+            Currency,
+            "SELECT * FROM currency WHERE uuid = $1 FOR UPDATE",
+            uuid
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        if let Some(value) = value.filter(|value| *value != currency.value) {
+            if value <= 0 {
+                bail!("value must be positive");
+            }
+            if is_held(&mut tx, uuid).await? {
+                bail!("the value of a currency held by inventories can not be changed");
+            }
+        }
+        sqlx::query!(
+            "UPDATE currency SET name = COALESCE($2, name), abbreviation = COALESCE($3, abbreviation),
+                value = COALESCE($4, value), auto_convert = COALESCE($5, auto_convert)
+             WHERE uuid = $1",
+            uuid,
+            name,
+            abbreviation,
+            value,
+            auto_convert
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Deletes a currency, fails while inventories hold coins of it.
+    pub async fn delete_currency(&self, uuid: &str) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        if is_held(&mut tx, uuid).await? {
+            bail!("a currency held by inventories can not be deleted");
+        }
+        sqlx::query!("DELETE FROM currency WHERE uuid = $1", uuid)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+/// Checks if any inventory holds coins of the given currency.
+async fn is_held(conn: &mut PgConnection, currency_uuid: &str) -> Result<bool> {
+    let held = sqlx::query_scalar!(
+        "SELECT EXISTS(SELECT 1 FROM inventory_money WHERE currency_uuid = $1)",
+        currency_uuid
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    Ok(held.unwrap_or(false))
+}
+
+/// Adds copper (1), silver (10), gold (100) and platinum (1000) to a new campaign on the given connection.
+pub(crate) async fn create_default_currencies(
+    conn: &mut PgConnection,
+    campaign_uuid: &str,
+) -> Result<()> {
+    sqlx::query!(
+        "INSERT INTO currency (uuid, campaign_uuid, name, abbreviation, value)
+         SELECT gen_random_uuid()::TEXT, $1, name, abbreviation, value
+         FROM (VALUES ('Copper', 'cp', 1), ('Silver', 'sp', 10), ('Gold', 'gp', 100), ('Platinum', 'pp', 1000))
+             AS defaults(name, abbreviation, value)",
+        campaign_uuid
+    )
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+/// Changes the money of an inventory by an amount in the smallest unit on the given connection.
+///
+/// Deposits are paid out in the least number of coins, withdrawals take the smallest coins first and hand out change.
+/// Only currencies that convert automatically are used.
+pub(crate) async fn change_money(
+    conn: &mut PgConnection,
+    inventory_uuid: &str,
+    delta: i32,
+) -> Result<()> {
+    let currencies = get_currencies(conn, inventory_uuid).await?;
+    let mut coins = lock_coins(conn, inventory_uuid).await?;
+    if delta >= 0 {
+        deposit(&currencies, &mut coins, delta)?;
+    } else {
+        let available: i64 = currencies
+            .iter()
+            .filter(|currency| currency.auto_convert)
+            .map(|currency| {
+                i64::from(coins.get(&currency.uuid).copied().unwrap_or(0))
+                    * i64::from(currency.value)
+            })
+            .sum();
+        if available < -i64::from(delta) {
+            bail!("insufficient money in inventory {}", inventory_uuid);
+        }
+        withdraw(&currencies, &mut coins, -delta)?;
+    }
+    store_coins(conn, inventory_uuid, &coins).await
+}
+
+/// Adds and removes coins of an inventory on the given connection without any conversion.
+///
+/// Returns the change of the inventory's money in the smallest unit.
+pub(crate) async fn change_coins(
+    conn: &mut PgConnection,
+    inventory_uuid: &str,
+    changes: &[CoinAmount],
+) -> Result<i32> {
+    let currencies = get_currencies(conn, inventory_uuid).await?;
+    let mut coins = lock_coins(conn, inventory_uuid).await?;
+    let mut delta = 0;
+    for change in changes {
+        let Some(currency) = currencies.iter().find(|c| c.uuid == change.currency_uuid) else {
+            bail!("unknown currency {}", change.currency_uuid);
+        };
+        let held = coins.entry(currency.uuid.clone()).or_insert(0);
+        *held += change.amount;
+        if *held < 0 {
+            bail!(
+                "insufficient {} in inventory {}",
+                currency.name,
+                inventory_uuid
+            );
+        }
+        delta += change.amount * currency.value;
+    }
+    store_coins(conn, inventory_uuid, &coins).await?;
+    Ok(delta)
+}
+
+/// Returns the currencies of the inventory's campaign on the given connection, the most valuable first.
+async fn get_currencies(conn: &mut PgConnection, inventory_uuid: &str) -> Result<Vec<Currency>> {
+    let currencies = sqlx::query_as!(
+        Currency,
+        "SELECT c.* FROM currency c
+         JOIN inventory i ON i.campaign_uuid = c.campaign_uuid
+         WHERE i.uuid = $1
+         ORDER BY c.value DESC, c.name",
+        inventory_uuid
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+    Ok(currencies)
+}
+
+/// Locks an inventory against concurrent money changes and returns its coins by currency.
+async fn lock_coins(conn: &mut PgConnection, inventory_uuid: &str) -> Result<HashMap<String, i32>> {
+    sqlx::query!(
+        "SELECT uuid FROM inventory WHERE uuid = $1 FOR UPDATE",
+        inventory_uuid
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    let coins = sqlx::query!(
+        "SELECT currency_uuid, amount FROM inventory_money WHERE inventory_uuid = $1",
+        inventory_uuid
+    )
+    .fetch_all(&mut *conn)
+    .await?
+    .into_iter()
+    .map(|coin| (coin.currency_uuid, coin.amount))
+    .collect();
+    Ok(coins)
+}
+
+/// Replaces the coins of an inventory and updates its money to their total value.
+async fn store_coins(
+    conn: &mut PgConnection,
+    inventory_uuid: &str,
+    coins: &HashMap<String, i32>,
+) -> Result<()> {
+    let (currency_uuids, amounts): (Vec<String>, Vec<i32>) = coins
+        .iter()
+        .filter(|(_, amount)| **amount > 0)
+        .map(|(currency_uuid, amount)| (currency_uuid.clone(), *amount))
+        .unzip();
+    sqlx::query!(
+        "DELETE FROM inventory_money WHERE inventory_uuid = $1",
+        inventory_uuid
+    )
+    .execute(&mut *conn)
+    .await?;
+    sqlx::query!(
+        "INSERT INTO inventory_money (inventory_uuid, currency_uuid, amount)
+         SELECT $1, currency_uuid, amount FROM UNNEST($2::TEXT[], $3::INTEGER[]) AS coins(currency_uuid, amount)",
+        inventory_uuid,
+        &currency_uuids,
+        &amounts
+    )
+    .execute(&mut *conn)
+    .await?;
+    sqlx::query!(
+        "UPDATE inventory SET money = COALESCE((
+            SELECT SUM(m.amount * c.value) FROM inventory_money m
+            JOIN currency c ON c.uuid = m.currency_uuid
+            WHERE m.inventory_uuid = $1
+         ), 0)::INTEGER
+         WHERE uuid = $1",
+        inventory_uuid
+    )
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+/// Adds an amount in the least number of coins that convert automatically.
+fn deposit(currencies: &[Currency], coins: &mut HashMap<String, i32>, amount: i32) -> Result<()> {
+    let mut rest = amount;
+    for currency in currencies.iter().filter(|currency| currency.auto_convert) {
+        let count = rest / currency.value;
+        if count > 0 {
+            *coins.entry(currency.uuid.clone()).or_insert(0) += count;
+            rest -= count * currency.value;
+        }
+    }
+    if rest != 0 {
+        bail!(
+            "{} can not be paid out in the currencies that convert automatically",
+            amount
+        );
+    }
+    Ok(())
+}
+
+/// Takes an amount from the coins that convert automatically, smallest first, and adds the change.
+///
+/// The coins must be worth at least the amount.
+fn withdraw(currencies: &[Currency], coins: &mut HashMap<String, i32>, amount: i32) -> Result<()> {
+    let mut rest = amount;
+    for currency in currencies
+        .iter()
+        .rev()
+        .filter(|currency| currency.auto_convert)
+    {
+        if rest <= 0 {
+            break;
+        }
+        let held = coins.entry(currency.uuid.clone()).or_insert(0);
+        let taken = (*held).min((rest + currency.value - 1) / currency.value);
+        *held -= taken;
+        rest -= taken * currency.value;
+    }
+    deposit(currencies, coins, -rest)
+}
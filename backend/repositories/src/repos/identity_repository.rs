@@ -0,0 +1,136 @@
+use crate::model::{User, UserIdentity};
+use crate::repos::invite_repository::redeem;
+use crate::repos::user_repository::insert_new_user;
+use anyhow::{bail, Result};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub struct IdentityRepository {
+    pool: PgPool,
+}
+
+impl IdentityRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Returns the user an identity of a provider is linked to.
+    pub async fn get_user_uuid(&self, provider: &str, subject: &str) -> Result<Option<String>> {
+        let user_uuid = sqlx::query_scalar!(
+            "SELECT user_uuid FROM user_identity WHERE provider = $1 AND subject = $2",
+            provider,
+            subject
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(user_uuid)
+    }
+
+    /// Returns the identities linked to a user, the oldest first.
+    pub async fn get_identities(&self, user_uuid: &str) -> Result<Vec<UserIdentity>> {
+        let identities = sqlx::query_as!(
+            UserIdentity,
+            "SELECT provider, subject, user_uuid, creation FROM user_identity WHERE user_uuid = $1 ORDER BY creation",
+            user_uuid
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(identities)
+    }
+
+    /// Creates a new user logging in through the given identity, redeeming the invite if a code is given.
+    /// The user gets a random UUID if none is given.
+    /// The first user created is assigned DM status, nothing is created if the invite is not valid.
+    pub async fn register(
+        &self,
+        provider: &str,
+        subject: &str,
+        uuid: Option<&str>,
+        name: &str,
+        avatar: &str,
+        invite_code: Option<&str>,
+    ) -> Result<User> {
+        let uuid = uuid.map_or_else(|| Uuid::new_v4().to_string(), str::to_string);
+        let mut tx = self.pool.begin().await?;
+        let user = insert_new_user(&mut tx, &uuid, name, avatar).await?;
+        sqlx::query!(
+            "INSERT INTO user_identity (provider, subject, user_uuid) VALUES ($1, $2, $3)",
+            provider,
+            subject,
+            uuid
+        )
+        .execute(&mut *tx)
+        .await?;
+        if let Some(code) = invite_code {
+            redeem(&mut tx, code, &uuid).await?;
+        }
+        tx.commit().await?;
+        Ok(user)
+    }
+
+    /// Links an identity of a provider to an existing user.
+    ///
+    /// Fails if the identity belongs to another user or the user already linked another identity of the provider.
+    pub async fn link_identity(
+        &self,
+        provider: &str,
+        subject: &str,
+        user_uuid: &str,
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        let linked = sqlx::query!(
+            "SELECT subject, user_uuid FROM user_identity WHERE provider = $1 AND (subject = $2 OR user_uuid = $3)",
+            provider,
+            subject,
+            user_uuid
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        if let Some(identity) = linked.first() {
+            if identity.subject == subject && identity.user_uuid == user_uuid {
+                return Ok(());
+            }
+            if identity.subject == subject {
+                bail!("the identity is linked to another user");
+            }
+            bail!("the user already linked an identity of this provider");
+        }
+        sqlx::query!(
+            "INSERT INTO user_identity (provider, subject, user_uuid) VALUES ($1, $2, $3)",
+            provider,
+            subject,
+            user_uuid
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Removes the identity of a provider from a user.
+    ///
+    /// Fails if the user could not log in anymore, i.e. it is their last identity and they have no local account.
+    pub async fn unlink_identity(&self, provider: &str, user_uuid: &str) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        let logins = sqlx::query_scalar!(
+            "SELECT (SELECT COUNT(*) FROM user_identity WHERE user_uuid = $1)
+                  + (SELECT COUNT(*) FROM local_account WHERE user_uuid = $1)",
+            user_uuid
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        let removed = sqlx::query!(
+            "DELETE FROM user_identity WHERE provider = $1 AND user_uuid = $2",
+            provider,
+            user_uuid
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        if removed > 0 && logins.unwrap_or(0) <= 1 {
+            bail!("the last way to log in can not be removed");
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+}
@@ -1,6 +1,11 @@
-use crate::model::{FrontendItem, FullFrontendInventory, InventoryItem, RawInventory};
-use anyhow::{self, Result};
-use sqlx::{Error, PgPool};
+use crate::model::{
+    CoinAmount, Encumbrance, EncumbranceRule, FrontendItem, FullFrontendInventory, InventoryItem,
+    InventoryRole, InventoryRoleAssignment, InventoryValue, ItemDisguise, ItemTypeValue,
+    RawInventory, WealthReport,
+};
+use anyhow::{self, bail, Result};
+use sqlx::{Error, PgConnection, PgPool};
+use std::collections::HashMap;
 use uuid::Uuid;
 pub struct InventoryRepository {
     pool: PgPool,
@@ -39,10 +44,25 @@ impl InventoryRepository {
         Ok(writers)
     }
 
+    /// Returns the coins held by the given inventory, the most valuable currency first.
+    pub async fn get_coins(&self, inventory_uuid: &str) -> Result<Vec<CoinAmount>> {
+        let coins = sqlx::query_as!(
+            CoinAmount,
+            "SELECT m.currency_uuid, m.amount FROM inventory_money m
+             JOIN currency c ON c.uuid = m.currency_uuid
+             WHERE m.inventory_uuid = $1
+             ORDER BY c.value DESC, c.name",
+            inventory_uuid
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(coins)
+    }
+
     /// Retrieves the full inventory data, including readers, writers, and items, for the given inventory UUID.
     pub async fn get_full_inventory(&self, uuid: &str) -> Result<FullFrontendInventory> {
         let inventory = sqlx::query!(
-            "SELECT uuid, owner_uuid, money, name, creation FROM inventory WHERE uuid = $1",
+            "SELECT uuid, owner_uuid, campaign_uuid, money, name, creation FROM inventory WHERE uuid = $1",
             uuid
         )
         .fetch_one(&self.pool)
@@ -50,25 +70,31 @@ impl InventoryRepository {
 
         let readers = self.get_readers(&inventory.uuid).await?;
         let writers = self.get_writers(&inventory.uuid).await?;
-        let items = self
-            .get_frontend_items_in_inventory(&inventory.uuid)
-            .await?;
+        let coins = self.get_coins(&inventory.uuid).await?;
+        let items = FrontendItem::into_tree(
+            self.get_frontend_items_in_inventory(&inventory.uuid)
+                .await?,
+        );
         Ok(FullFrontendInventory {
             uuid: inventory.uuid,
             owner_uuid: inventory.owner_uuid,
+            campaign_uuid: inventory.campaign_uuid,
             money: inventory.money,
+            coins,
             name: inventory.name,
             reader: readers,
             writer: writers,
-            items: items,
+            items,
             creation: inventory.creation,
         })
     }
 
-    /// Returns all inventory UUIDs owned by the given user.
+    /// Returns all inventory UUIDs owned by the given user in campaigns the user is a member of.
     pub async fn get_user_inventory_ids(&self, user_uuid: &str) -> Result<Vec<String>> {
         let inventory_ids = sqlx::query!(
-            "SELECT uuid FROM inventory WHERE owner_uuid = $1",
+            "SELECT i.uuid FROM inventory i
+             JOIN campaign_member m ON m.campaign_uuid = i.campaign_uuid AND m.user_uuid = i.owner_uuid
+             WHERE i.owner_uuid = $1",
             user_uuid
         )
         .fetch_all(&self.pool)
@@ -79,10 +105,40 @@ impl InventoryRepository {
         Ok(inventory_ids)
     }
 
-    /// Returns all inventory UUIDs where the user is a reader.
+    /// Returns the UUIDs of all inventories of a campaign.
+    pub async fn get_campaign_inventory_ids(&self, campaign_uuid: &str) -> Result<Vec<String>> {
+        let inventory_ids = sqlx::query_scalar!(
+            "SELECT uuid FROM inventory WHERE campaign_uuid = $1",
+            campaign_uuid
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(inventory_ids)
+    }
+
+    /// Checks if a user is a member of the campaign an inventory belongs to.
+    pub async fn is_campaign_member(&self, inventory_uuid: &str, user_uuid: &str) -> Result<bool> {
+        let exists = sqlx::query_scalar!(
+            "SELECT EXISTS(
+                SELECT 1 FROM inventory i
+                JOIN campaign_member m ON m.campaign_uuid = i.campaign_uuid
+                WHERE i.uuid = $1 AND m.user_uuid = $2
+             )",
+            inventory_uuid,
+            user_uuid
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(exists.unwrap_or(false))
+    }
+
+    /// Returns all inventory UUIDs where the user is a reader, in campaigns the user is a member of.
     pub async fn get_inventories_by_reader(&self, user_uuid: &str) -> Result<Vec<String>> {
         let inventory_ids = sqlx::query!(
-            "SELECT inventory_uuid FROM inventory_reader WHERE user_uuid = $1",
+            "SELECT r.inventory_uuid FROM inventory_reader r
+             JOIN inventory i ON i.uuid = r.inventory_uuid
+             JOIN campaign_member m ON m.campaign_uuid = i.campaign_uuid AND m.user_uuid = r.user_uuid
+             WHERE r.user_uuid = $1",
             user_uuid
         )
         .fetch_all(&self.pool)
@@ -101,13 +157,16 @@ impl InventoryRepository {
         let mut invs = self.get_user_inventory_ids(user_uuid).await?;
         let read_invs = self.get_inventories_by_reader(user_uuid).await?;
         invs.extend(read_invs);
-        return Ok(invs);
+        Ok(invs)
     }
 
-    /// Returns all inventory UUIDs where the user is a writer.
+    /// Returns all inventory UUIDs where the user is a writer, in campaigns the user is a member of.
     pub async fn get_inventories_by_writer(&self, user_uuid: &str) -> Result<Vec<String>> {
         let inventory_ids = sqlx::query!(
-            "SELECT inventory_uuid FROM inventory_writer WHERE user_uuid = $1",
+            "SELECT w.inventory_uuid FROM inventory_writer w
+             JOIN inventory i ON i.uuid = w.inventory_uuid
+             JOIN campaign_member m ON m.campaign_uuid = i.campaign_uuid AND m.user_uuid = w.user_uuid
+             WHERE w.user_uuid = $1",
             user_uuid
         )
         .fetch_all(&self.pool)
@@ -119,14 +178,21 @@ impl InventoryRepository {
         Ok(inventory_ids)
     }
 
-    /// Returns all inventories (as `FullFrontendInventory`) where the user is owner or reader.
-    pub async fn get_all_inventories(&self, user_uuid: &str) -> Result<Vec<FullFrontendInventory>> {
+    /// Returns all inventories (as `FullFrontendInventory`) where the user is owner or reader,
+    /// in campaigns the user is a member of, optionally only those of one campaign.
+    pub async fn get_all_inventories(
+        &self,
+        user_uuid: &str,
+        campaign_uuid: Option<&str>,
+    ) -> Result<Vec<FullFrontendInventory>> {
         let query = sqlx::query!(
             "SELECT DISTINCT i.uuid
              FROM inventory i
+             JOIN campaign_member m ON m.campaign_uuid = i.campaign_uuid AND m.user_uuid = $1
              LEFT JOIN inventory_reader ir ON i.uuid = ir.inventory_uuid
-             WHERE i.owner_uuid = $1 OR ir.user_uuid = $1",
-            user_uuid
+             WHERE (i.owner_uuid = $1 OR ir.user_uuid = $1) AND ($2::TEXT IS NULL OR i.campaign_uuid = $2)",
+            user_uuid,
+            campaign_uuid
         );
 
         let inventories = query.fetch_all(&self.pool).await?;
@@ -140,17 +206,17 @@ impl InventoryRepository {
         Ok(full_inventories)
     }
 
-    /// Creates a new inventory and adds the owner as reader and writer.
+    /// Creates a new inventory without money in a campaign and adds the owner as reader and writer.
     pub async fn create_inventory(
         &self,
         owner_uuid: &str,
-        money: i32,
+        campaign_uuid: &str,
         name: &str,
     ) -> Result<RawInventory> {
         let uuid = Uuid::new_v4().to_string();
         let rec = sqlx::query_as!(RawInventory,
-            "INSERT INTO inventory (uuid, owner_uuid, money, name) VALUES ($1, $2, $3, $4) RETURNING *",
-            uuid, owner_uuid, money, name
+            "INSERT INTO inventory (uuid, owner_uuid, campaign_uuid, money, name) VALUES ($1, $2, $3, 0, $4) RETURNING *",
+            uuid, owner_uuid, campaign_uuid, name
         )
         .fetch_one(&self.pool)
         .await?;
@@ -171,16 +237,12 @@ impl InventoryRepository {
         Ok(inventory)
     }
 
-    /// Updates the money and/or name of an inventory.
-    pub async fn update_inventory(
-        &self,
-        uuid: &str,
-        money: Option<i32>,
-        name: Option<&str>,
-    ) -> Result<(), Error> {
+    /// Updates the name of an inventory.
+    ///
+    /// The money is only changed through the ledger, see `MoneyRepository::post_transaction`.
+    pub async fn update_inventory(&self, uuid: &str, name: Option<&str>) -> Result<(), Error> {
         sqlx::query!(
-            "UPDATE inventory SET money = COALESCE($1, money), name = COALESCE($2, name) WHERE uuid = $3",
-            money,
+            "UPDATE inventory SET name = COALESCE($1, name) WHERE uuid = $2",
             name,
             uuid
         )
@@ -189,6 +251,131 @@ impl InventoryRepository {
         Ok(())
     }
 
+    /// Sets the encumbrance configuration of an inventory, `None` clears a value.
+    pub async fn set_encumbrance_config(
+        &self,
+        uuid: &str,
+        strength: Option<i32>,
+        carrying_capacity: Option<f32>,
+        count_money_weight: bool,
+    ) -> Result<()> {
+        sqlx::query!(
+            "UPDATE inventory SET strength = $2, carrying_capacity = $3, count_money_weight = $4 WHERE uuid = $1",
+            uuid,
+            strength,
+            carrying_capacity,
+            count_money_weight
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Sums up the weight carried in an inventory and compares it against its carrying capacity.
+    pub async fn get_encumbrance(&self, uuid: &str, rule: EncumbranceRule) -> Result<Encumbrance> {
+        let inventory = self.get_raw_inventory(uuid).await?;
+        let items = FrontendItem::into_tree(self.get_frontend_items_in_inventory(uuid).await?);
+        let item_weight = items.iter().map(|item| item.total_weight).sum();
+        let coins = sqlx::query_scalar!(
+            "SELECT COALESCE(SUM(amount), 0)::INTEGER FROM inventory_money WHERE inventory_uuid = $1",
+            uuid
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(Encumbrance::compute(
+            rule,
+            item_weight,
+            coins.unwrap_or(0),
+            &inventory,
+        ))
+    }
+
+    /// Sums up the cash and the item value of the given inventories, per inventory, per item type and in total.
+    ///
+    /// Unless `reveal_disguised` is set, unidentified items count with their disguised item type and without value.
+    pub async fn get_wealth_report(
+        &self,
+        inventory_uuids: &[String],
+        reveal_disguised: bool,
+    ) -> Result<WealthReport> {
+        let inventories = sqlx::query!(
+            "SELECT i.uuid, i.name, i.money::BIGINT AS \"money!\",
+                    COALESCE(SUM(CASE WHEN $2 OR ii.disguise_name IS NULL THEN ip.price::BIGINT * ii.amount ELSE 0 END), 0)::BIGINT AS \"item_value!\"
+             FROM inventory i
+             LEFT JOIN inventory_item ii ON ii.inventory_uuid = i.uuid
+             LEFT JOIN item_preset ip ON ip.uuid = ii.item_preset_uuid
+             WHERE i.uuid = ANY($1)
+             GROUP BY i.uuid
+             ORDER BY i.name, i.uuid",
+            inventory_uuids,
+            reveal_disguised
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        let inventory_types = sqlx::query!(
+            "SELECT ii.inventory_uuid,
+                    CASE WHEN $2 OR ii.disguise_name IS NULL THEN ip.item_type ELSE COALESCE(ii.disguise_item_type, '') END AS \"item_type!\",
+                    SUM(ii.amount)::BIGINT AS \"amount!\",
+                    SUM(CASE WHEN $2 OR ii.disguise_name IS NULL THEN ip.price::BIGINT * ii.amount ELSE 0 END)::BIGINT AS \"value!\"
+             FROM inventory_item ii
+             INNER JOIN item_preset ip ON ip.uuid = ii.item_preset_uuid
+             WHERE ii.inventory_uuid = ANY($1)
+             GROUP BY ii.inventory_uuid, 2
+             ORDER BY 2",
+            inventory_uuids,
+            reveal_disguised
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        let by_item_type = sqlx::query_as!(
+            ItemTypeValue,
+            "SELECT CASE WHEN $2 OR ii.disguise_name IS NULL THEN ip.item_type ELSE COALESCE(ii.disguise_item_type, '') END AS \"item_type!\",
+                    SUM(ii.amount)::BIGINT AS \"amount!\",
+                    SUM(CASE WHEN $2 OR ii.disguise_name IS NULL THEN ip.price::BIGINT * ii.amount ELSE 0 END)::BIGINT AS \"value!\"
+             FROM inventory_item ii
+             INNER JOIN item_preset ip ON ip.uuid = ii.item_preset_uuid
+             WHERE ii.inventory_uuid = ANY($1)
+             GROUP BY 1
+             ORDER BY 1",
+            inventory_uuids,
+            reveal_disguised
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut types_by_inventory: HashMap<String, Vec<ItemTypeValue>> = HashMap::new();
+        for t in inventory_types {
+            types_by_inventory
+                .entry(t.inventory_uuid)
+                .or_default()
+                .push(ItemTypeValue {
+                    item_type: t.item_type,
+                    amount: t.amount,
+                    value: t.value,
+                });
+        }
+        let inventories: Vec<InventoryValue> = inventories
+            .into_iter()
+            .map(|inv| InventoryValue {
+                total_value: inv.money + inv.item_value,
+                by_item_type: types_by_inventory.remove(&inv.uuid).unwrap_or_default(),
+                inventory_uuid: inv.uuid,
+                name: inv.name,
+                money: inv.money,
+                item_value: inv.item_value,
+            })
+            .collect();
+        let money = inventories.iter().map(|inv| inv.money).sum();
+        let item_value = inventories.iter().map(|inv| inv.item_value).sum();
+        Ok(WealthReport {
+            inventories,
+            money,
+            item_value,
+            total_value: money + item_value,
+            by_item_type,
+        })
+    }
+
     /// Deletes an inventory by UUID.
     pub async fn delete_inventory(&self, uuid: &str) -> Result<()> {
         sqlx::query!("DELETE FROM inventory WHERE uuid = $1", uuid)
@@ -197,6 +384,21 @@ impl InventoryRepository {
         Ok(())
     }
 
+    /// Makes another member of the inventory's campaign its owner.
+    ///
+    /// See `transfer_ownership` for how the shares change.
+    pub async fn transfer_inventory(
+        &self,
+        inventory_uuid: &str,
+        new_owner_uuid: &str,
+        keep_access: bool,
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        transfer_ownership(&mut tx, inventory_uuid, new_owner_uuid, keep_access).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
     /// Adds a user as a reader to an inventory.
     pub async fn add_reader(&self, inventory_uuid: &str, user_uuid: &str) -> Result<()> {
         sqlx::query!(
@@ -209,13 +411,107 @@ impl InventoryRepository {
         Ok(())
     }
 
-    /// Removes a user as a reader from an inventory.
+    /// Removes a user as a reader from an inventory, including their role.
     pub async fn remove_reader(&self, inventory_uuid: &str, user_uuid: &str) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
         sqlx::query!(
             "DELETE FROM inventory_reader WHERE user_uuid = $1 AND inventory_uuid = $2",
             user_uuid,
             inventory_uuid
         )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            "DELETE FROM inventory_role WHERE user_uuid = $1 AND inventory_uuid = $2",
+            user_uuid,
+            inventory_uuid
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Returns the role a user was given in an inventory, the owner and plain readers and writers have none.
+    pub async fn get_role(
+        &self,
+        inventory_uuid: &str,
+        user_uuid: &str,
+    ) -> Result<Option<InventoryRole>> {
+        let role = sqlx::query_scalar!(
+            "SELECT role FROM inventory_role WHERE inventory_uuid = $1 AND user_uuid = $2",
+            inventory_uuid,
+            user_uuid
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        role.map(|role| role.parse()).transpose()
+    }
+
+    /// Returns all roles given in an inventory.
+    pub async fn get_roles(&self, inventory_uuid: &str) -> Result<Vec<InventoryRoleAssignment>> {
+        sqlx::query!(
+            "SELECT user_uuid, role, creation FROM inventory_role WHERE inventory_uuid = $1 ORDER BY creation",
+            inventory_uuid
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|r| {
+            Ok(InventoryRoleAssignment {
+                user_uuid: r.user_uuid,
+                role: r.role.parse()?,
+                creation: r.creation,
+            })
+        })
+        .collect()
+    }
+
+    /// Gives a user a role in an inventory, which makes them a reader and replaces their writer share.
+    pub async fn set_role(
+        &self,
+        inventory_uuid: &str,
+        user_uuid: &str,
+        role: InventoryRole,
+    ) -> Result<()> {
+        if role == InventoryRole::Owner {
+            bail!("the owner can only be changed by transferring the inventory");
+        }
+        let mut tx = self.pool.begin().await?;
+        sqlx::query!(
+            "INSERT INTO inventory_reader (user_uuid, inventory_uuid) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+            user_uuid,
+            inventory_uuid
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            "DELETE FROM inventory_writer WHERE inventory_uuid = $1 AND user_uuid = $2",
+            inventory_uuid,
+            user_uuid
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            "INSERT INTO inventory_role (inventory_uuid, user_uuid, role) VALUES ($1, $2, $3)
+             ON CONFLICT (inventory_uuid, user_uuid) DO UPDATE SET role = EXCLUDED.role",
+            inventory_uuid,
+            user_uuid,
+            role.as_str()
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Removes the role of a user in an inventory, who stays a reader without further permissions.
+    pub async fn remove_role(&self, inventory_uuid: &str, user_uuid: &str) -> Result<()> {
+        sqlx::query!(
+            "DELETE FROM inventory_role WHERE inventory_uuid = $1 AND user_uuid = $2",
+            inventory_uuid,
+            user_uuid
+        )
         .execute(&self.pool)
         .await?;
         Ok(())
@@ -245,7 +541,7 @@ impl InventoryRepository {
         Ok(())
     }
 
-    /// Adds an item to an inventory.
+    /// Adds a new stack of an item to an inventory and returns it.
     pub async fn add_inventory_item(
         &self,
         inventory_uuid: &str,
@@ -254,53 +550,186 @@ impl InventoryRepository {
         amount: i32,
         sorting: i32,
         inventory_item_note: &str,
-    ) -> Result<()> {
-        sqlx::query!("INSERT INTO inventory_item (inventory_uuid, item_preset_uuid, dm_note, amount, sorting, inventory_item_note) VALUES ($1, $2, $3, $4, $5, $6)",
-            inventory_uuid, item_preset_uuid, dm_note, amount, sorting, inventory_item_note)
-            .execute(&self.pool)
-            .await?;
-        Ok(())
+    ) -> Result<InventoryItem> {
+        let item = sqlx::query_as!(
+            InventoryItem,
+            "INSERT INTO inventory_item (uuid, inventory_uuid, item_preset_uuid, dm_note, amount, sorting, inventory_item_note)
+             VALUES ($1, $2, $3, $4, $5, $6, $7)
+             RETURNING uuid, inventory_uuid, item_preset_uuid, dm_note, amount, sorting, inventory_item_note, disguise_name, disguise_description, disguise_item_type, creation, container_item_uuid",
+            Uuid::new_v4().to_string(),
+            inventory_uuid,
+            item_preset_uuid,
+            dm_note,
+            amount,
+            sorting,
+            inventory_item_note
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(item)
     }
 
     /// Updates an item in an inventory.
     pub async fn update_inventory_item(
         &self,
         inventory_uuid: &str,
-        item_preset_uuid: &str,
+        item_uuid: &str,
         dm_note: Option<&str>,
         amount: Option<i32>,
         sorting: Option<i32>,
         inventory_item_note: Option<&str>,
     ) -> Result<()> {
-        sqlx::query!("UPDATE inventory_item SET dm_note = COALESCE($3, dm_note), amount = COALESCE($4, amount), sorting = COALESCE($5, sorting), inventory_item_note = COALESCE($6, inventory_item_note) WHERE inventory_uuid = $1 AND item_preset_uuid = $2",
-            inventory_uuid, item_preset_uuid, dm_note, amount, sorting, inventory_item_note)
+        sqlx::query!("UPDATE inventory_item SET dm_note = COALESCE($3, dm_note), amount = COALESCE($4, amount), sorting = COALESCE($5, sorting), inventory_item_note = COALESCE($6, inventory_item_note) WHERE inventory_uuid = $1 AND uuid = $2",
+            inventory_uuid, item_uuid, dm_note, amount, sorting, inventory_item_note)
             .execute(&self.pool)
             .await?;
         Ok(())
     }
 
-    /// Removes an item from an inventory.
-    pub async fn remove_inventory_item(
+    /// Restores an item of an inventory to the given state, creating it if it does not exist.
+    ///
+    /// If `keep_dm_fields` is set, the DM note and the disguise of an existing item are left untouched.
+    /// The item is put on the top level if its container can no longer hold it.
+    pub async fn restore_inventory_item(
+        &self,
+        item: &InventoryItem,
+        keep_dm_fields: bool,
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        let container_item_uuid = match &item.container_item_uuid {
+            Some(container)
+                if can_hold(&mut tx, &item.inventory_uuid, &item.uuid, container).await? =>
+            {
+                Some(container.as_str())
+            }
+            _ => None,
+        };
+        sqlx::query!(
+            "INSERT INTO inventory_item (uuid, inventory_uuid, item_preset_uuid, dm_note, amount, sorting, inventory_item_note, creation, disguise_name, disguise_description, disguise_item_type, container_item_uuid)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $10, $11, $12, $13)
+             ON CONFLICT (uuid) DO UPDATE SET
+                dm_note = CASE WHEN $9 THEN inventory_item.dm_note ELSE EXCLUDED.dm_note END,
+                amount = EXCLUDED.amount,
+                sorting = EXCLUDED.sorting,
+                inventory_item_note = EXCLUDED.inventory_item_note,
+                disguise_name = CASE WHEN $9 THEN inventory_item.disguise_name ELSE EXCLUDED.disguise_name END,
+                disguise_description = CASE WHEN $9 THEN inventory_item.disguise_description ELSE EXCLUDED.disguise_description END,
+                disguise_item_type = CASE WHEN $9 THEN inventory_item.disguise_item_type ELSE EXCLUDED.disguise_item_type END,
+                container_item_uuid = EXCLUDED.container_item_uuid",
+            item.uuid,
+            item.inventory_uuid,
+            item.item_preset_uuid,
+            item.dm_note,
+            item.amount,
+            item.sorting,
+            item.inventory_item_note,
+            item.creation,
+            keep_dm_fields,
+            item.disguise_name,
+            item.disguise_description,
+            item.disguise_item_type,
+            container_item_uuid
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Adds an unidentified item to an inventory and returns it, players only see the given disguise.
+    pub async fn add_disguised_inventory_item(
         &self,
         inventory_uuid: &str,
         item_preset_uuid: &str,
-    ) -> Result<()> {
+        amount: i32,
+        sorting: i32,
+        disguise: &ItemDisguise,
+    ) -> Result<InventoryItem> {
+        let item = sqlx::query_as!(
+            InventoryItem,
+            "INSERT INTO inventory_item (uuid, inventory_uuid, item_preset_uuid, dm_note, amount, sorting, inventory_item_note, disguise_name, disguise_description, disguise_item_type)
+             VALUES ($1, $2, $3, '', $4, $5, '', $6, $7, $8)
+             RETURNING uuid, inventory_uuid, item_preset_uuid, dm_note, amount, sorting, inventory_item_note, disguise_name, disguise_description, disguise_item_type, creation, container_item_uuid",
+            Uuid::new_v4().to_string(),
+            inventory_uuid,
+            item_preset_uuid,
+            amount,
+            sorting,
+            disguise.name,
+            disguise.description,
+            disguise.item_type
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(item)
+    }
+
+    /// Identifies an item by removing its disguise.
+    pub async fn reveal_inventory_item(&self, inventory_uuid: &str, item_uuid: &str) -> Result<()> {
         sqlx::query!(
-            "DELETE FROM inventory_item WHERE inventory_uuid = $1 AND item_preset_uuid = $2",
+            "UPDATE inventory_item SET disguise_name = NULL, disguise_description = NULL, disguise_item_type = NULL
+             WHERE inventory_uuid = $1 AND uuid = $2",
             inventory_uuid,
-            item_preset_uuid
+            item_uuid
         )
         .execute(&self.pool)
         .await?;
         Ok(())
     }
 
+    /// Removes an item from an inventory.
+    ///
+    /// The contents of a container are moved into the container holding it.
+    pub async fn remove_inventory_item(&self, inventory_uuid: &str, item_uuid: &str) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        empty_container(&mut tx, inventory_uuid, item_uuid).await?;
+        sqlx::query!(
+            "DELETE FROM inventory_item WHERE inventory_uuid = $1 AND uuid = $2",
+            inventory_uuid,
+            item_uuid
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Moves an item into a container item of the same inventory, or to the top level if `container_item_uuid` is `None`.
+    ///
+    /// Fails if the container is no container item of the inventory or lies inside the moved item.
+    pub async fn move_inventory_item(
+        &self,
+        inventory_uuid: &str,
+        item_uuid: &str,
+        container_item_uuid: Option<&str>,
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        if let Some(container) = container_item_uuid {
+            if !can_hold(&mut tx, inventory_uuid, item_uuid, container).await? {
+                bail!("item {} can not be put into {}", item_uuid, container);
+            }
+        }
+        let result = sqlx::query!(
+            "UPDATE inventory_item SET container_item_uuid = $3 WHERE inventory_uuid = $1 AND uuid = $2",
+            inventory_uuid,
+            item_uuid,
+            container_item_uuid
+        )
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            bail!("item {} is not in inventory {}", item_uuid, inventory_uuid);
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
     /// Checks if an item exists in an inventory.
-    pub async fn item_exists(&self, inventory_uuid: &str, item_preset_uuid: &str) -> Result<bool> {
+    pub async fn item_exists(&self, inventory_uuid: &str, item_uuid: &str) -> Result<bool> {
         let result = sqlx::query!(
-            "SELECT EXISTS(SELECT 1 FROM inventory_item WHERE inventory_uuid = $1 AND item_preset_uuid = $2) AS exists",
+            "SELECT EXISTS(SELECT 1 FROM inventory_item WHERE inventory_uuid = $1 AND uuid = $2) AS exists",
             inventory_uuid,
-            item_preset_uuid
+            item_uuid
         )
         .fetch_one(&self.pool)
         .await?;
@@ -308,12 +737,32 @@ impl InventoryRepository {
         Ok(result.exists.unwrap_or(false))
     }
 
+    /// Retrieves a single item of an inventory.
+    pub async fn get_inventory_item(
+        &self,
+        inventory_uuid: &str,
+        item_uuid: &str,
+    ) -> Result<InventoryItem> {
+        let item = sqlx::query_as!(
+            InventoryItem,
+            "SELECT uuid, inventory_uuid, item_preset_uuid, dm_note, amount, sorting, inventory_item_note, disguise_name, disguise_description, disguise_item_type, creation, container_item_uuid
+             FROM inventory_item
+             WHERE inventory_uuid = $1 AND uuid = $2",
+            inventory_uuid,
+            item_uuid
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(item)
+    }
+
     /// Returns all items in an inventory as `InventoryItem`.
     pub async fn get_items_in_inventory(&self, inventory_uuid: &str) -> Result<Vec<InventoryItem>> {
         let items = sqlx::query_as!(
             InventoryItem,
-            "SELECT inventory_uuid, item_preset_uuid, dm_note, amount, sorting, inventory_item_note, creation 
-             FROM inventory_item 
+            "SELECT uuid, inventory_uuid, item_preset_uuid, dm_note, amount, sorting, inventory_item_note, disguise_name, disguise_description, disguise_item_type, creation, container_item_uuid
+             FROM inventory_item
              WHERE inventory_uuid = $1",
             inventory_uuid
         )
@@ -327,14 +776,37 @@ impl InventoryRepository {
     pub async fn get_frontend_items_in_inventory(
         &self,
         inventory_uuid: &str,
+    ) -> Result<Vec<FrontendItem>> {
+        self.query_frontend_items(inventory_uuid, None).await
+    }
+
+    /// Returns the given items of an inventory as `FrontendItem`.
+    ///
+    /// Items that are not part of the inventory are skipped.
+    pub async fn get_frontend_items_by_uuids(
+        &self,
+        inventory_uuid: &str,
+        item_uuids: &[String],
+    ) -> Result<Vec<FrontendItem>> {
+        self.query_frontend_items(inventory_uuid, Some(item_uuids))
+            .await
+    }
+
+    async fn query_frontend_items(
+        &self,
+        inventory_uuid: &str,
+        item_uuids: Option<&[String]>,
     ) -> Result<Vec<FrontendItem>> {
         let items = sqlx::query!(
-            "SELECT ii.inventory_uuid, ii.item_preset_uuid, ii.dm_note, ii.amount, ii.sorting, ii.inventory_item_note, ii.creation,
-                    ip.name, ip.description, ip.price, ip.creator AS preset_creator, ip.weight, ip.item_type
+            "SELECT ii.uuid, ii.inventory_uuid, ii.item_preset_uuid, ii.dm_note, ii.amount, ii.sorting, ii.inventory_item_note, ii.creation,
+                    ii.disguise_name, ii.disguise_description, ii.disguise_item_type, ii.container_item_uuid,
+                    ip.name, ip.description, ip.price, ip.creator AS preset_creator, ip.weight, ip.item_type,
+                    ip.container, ip.weightless_contents
              FROM inventory_item ii
              INNER JOIN item_preset ip ON ii.item_preset_uuid = ip.uuid
-             WHERE ii.inventory_uuid = $1",
-            inventory_uuid
+             WHERE ii.inventory_uuid = $1 AND ($2::TEXT[] IS NULL OR ii.uuid = ANY($2))",
+            inventory_uuid,
+            item_uuids as Option<&[String]>
         )
         .fetch_all(&self.pool)
         .await?;
@@ -342,6 +814,7 @@ impl InventoryRepository {
         let frontend_items = items
             .into_iter()
             .map(|item| FrontendItem {
+                uuid: item.uuid,
                 name: item.name,
                 amount: item.amount,
                 dm_note: item.dm_note,
@@ -353,9 +826,145 @@ impl InventoryRepository {
                 item_type: item.item_type,
                 preset_reference: item.item_preset_uuid,
                 inventory_item_note: item.inventory_item_note,
+                disguise: ItemDisguise::from_columns(
+                    item.disguise_name,
+                    item.disguise_description,
+                    item.disguise_item_type,
+                ),
+                container: item.container,
+                weightless_contents: item.weightless_contents,
+                container_reference: item.container_item_uuid,
+                contents: Vec::new(),
+                total_weight: item.weight * item.amount as f32,
             })
             .collect();
 
         Ok(frontend_items)
     }
 }
+
+/// Checks if a container item of an inventory can hold an item without creating a cycle.
+async fn can_hold(
+    conn: &mut PgConnection,
+    inventory_uuid: &str,
+    item_uuid: &str,
+    container_item_uuid: &str,
+) -> Result<bool> {
+    let result = sqlx::query!(
+        "WITH RECURSIVE ancestors(item) AS (
+            SELECT $3::TEXT
+            UNION
+            SELECT ii.container_item_uuid
+            FROM inventory_item ii
+            INNER JOIN ancestors a ON ii.uuid = a.item
+            WHERE ii.inventory_uuid = $1 AND ii.container_item_uuid IS NOT NULL
+         )
+         SELECT EXISTS(
+                SELECT 1 FROM inventory_item ii
+                INNER JOIN item_preset ip ON ii.item_preset_uuid = ip.uuid
+                WHERE ii.inventory_uuid = $1 AND ii.uuid = $3 AND ip.container
+            ) AS \"is_container!\",
+            EXISTS(SELECT 1 FROM ancestors WHERE item = $2) AS \"cycle!\"",
+        inventory_uuid,
+        item_uuid,
+        container_item_uuid
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    Ok(result.is_container && !result.cycle)
+}
+
+/// Makes a member of the inventory's campaign its owner on the given connection.
+///
+/// The new owner becomes reader and writer and loses their role, the previous owner keeps read and write access only if `keep_access` is set.
+pub(crate) async fn transfer_ownership(
+    conn: &mut PgConnection,
+    inventory_uuid: &str,
+    new_owner_uuid: &str,
+    keep_access: bool,
+) -> Result<()> {
+    let inventory = sqlx::query_as!(
+        RawInventory,
+        "SELECT * FROM inventory WHERE uuid = $1 FOR UPDATE",
+        inventory_uuid
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    if inventory.owner_uuid == new_owner_uuid {
+        return Ok(());
+    }
+    let member = sqlx::query_scalar!(
+        "SELECT EXISTS(SELECT 1 FROM campaign_member WHERE campaign_uuid = $1 AND user_uuid = $2)",
+        inventory.campaign_uuid,
+        new_owner_uuid
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    if !member.unwrap_or(false) {
+        bail!("the new owner is not a member of the campaign");
+    }
+    sqlx::query!(
+        "UPDATE inventory SET owner_uuid = $2 WHERE uuid = $1",
+        inventory_uuid,
+        new_owner_uuid
+    )
+    .execute(&mut *conn)
+    .await?;
+    if !keep_access {
+        sqlx::query!(
+            "DELETE FROM inventory_reader WHERE inventory_uuid = $1 AND user_uuid = $2",
+            inventory_uuid,
+            inventory.owner_uuid
+        )
+        .execute(&mut *conn)
+        .await?;
+        sqlx::query!(
+            "DELETE FROM inventory_writer WHERE inventory_uuid = $1 AND user_uuid = $2",
+            inventory_uuid,
+            inventory.owner_uuid
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+    sqlx::query!(
+        "DELETE FROM inventory_role WHERE inventory_uuid = $1 AND user_uuid = $2",
+        inventory_uuid,
+        new_owner_uuid
+    )
+    .execute(&mut *conn)
+    .await?;
+    sqlx::query!(
+        "INSERT INTO inventory_reader (user_uuid, inventory_uuid) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+        new_owner_uuid,
+        inventory_uuid
+    )
+    .execute(&mut *conn)
+    .await?;
+    sqlx::query!(
+        "INSERT INTO inventory_writer (user_uuid, inventory_uuid) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+        new_owner_uuid,
+        inventory_uuid
+    )
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+/// Moves the contents of a container item into the container holding it, or to the top level.
+pub(crate) async fn empty_container(
+    conn: &mut PgConnection,
+    inventory_uuid: &str,
+    item_uuid: &str,
+) -> Result<()> {
+    sqlx::query!(
+        "UPDATE inventory_item SET container_item_uuid = (
+            SELECT container_item_uuid FROM inventory_item WHERE inventory_uuid = $1 AND uuid = $2
+         )
+         WHERE inventory_uuid = $1 AND container_item_uuid = $2",
+        inventory_uuid,
+        item_uuid
+    )
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
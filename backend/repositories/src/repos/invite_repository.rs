@@ -0,0 +1,175 @@
+use crate::model::{Invite, InviteRedemption};
+use anyhow::{bail, Result};
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+pub struct InviteRepository {
+    pool: PgPool,
+}
+
+impl InviteRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Creates a new invite with a random code, `None` leaves the uses or the lifetime unlimited.
+    pub async fn create_invite(
+        &self,
+        creator_uuid: &str,
+        campaign_uuid: Option<&str>,
+        max_uses: Option<i32>,
+        expires_in_hours: Option<i32>,
+        allows_registration: bool,
+    ) -> Result<Invite> {
+        if max_uses.is_some_and(|max_uses| max_uses <= 0) {
+            bail!("max_uses must be positive");
+        }
+        if expires_in_hours.is_some_and(|hours| hours <= 0) {
+            bail!("expires_in_hours must be positive");
+        }
+        let invite = sqlx::query_as!(
+            Invite,
+            "INSERT INTO invite (uuid, code, creator_uuid, campaign_uuid, max_uses, expires_at, allows_registration)
+             VALUES ($1, $2, $3, $4, $5, CURRENT_TIMESTAMP + make_interval(hours => $6), $7)
+             RETURNING *",
+            Uuid::new_v4().to_string(),
+            Uuid::new_v4().simple().to_string(),
+            creator_uuid,
+            campaign_uuid,
+            max_uses,
+            expires_in_hours,
+            allows_registration
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(invite)
+    }
+
+    /// Checks if an invite code lets a new user register while the server is locked, `false` for unknown codes.
+    pub async fn allows_registration(&self, code: &str) -> Result<bool> {
+        let allows_registration = sqlx::query_scalar!(
+            "SELECT allows_registration FROM invite WHERE code = $1",
+            code
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(allows_registration.unwrap_or(false))
+    }
+
+    /// Retrieves an invite by its UUID.
+    pub async fn get_invite(&self, uuid: &str) -> Result<Invite> {
+        let invite = sqlx::query_as!(Invite, "SELECT * FROM invite WHERE uuid = $1", uuid)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(invite)
+    }
+
+    /// Returns all invites, the newest first.
+    pub async fn get_all_invites(&self) -> Result<Vec<Invite>> {
+        let invites = sqlx::query_as!(Invite, "SELECT * FROM invite ORDER BY creation DESC")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(invites)
+    }
+
+    /// Returns all invites into the given campaigns, the newest first.
+    pub async fn get_campaign_invites(&self, campaign_uuids: &[String]) -> Result<Vec<Invite>> {
+        let invites = sqlx::query_as!(
+            Invite,
+            "SELECT * FROM invite WHERE campaign_uuid = ANY($1) ORDER BY creation DESC",
+            campaign_uuids
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(invites)
+    }
+
+    /// Returns who redeemed an invite, the earliest first.
+    pub async fn get_redemptions(&self, invite_uuid: &str) -> Result<Vec<InviteRedemption>> {
+        let redemptions = sqlx::query_as!(
+            InviteRedemption,
+            "SELECT * FROM invite_redemption WHERE invite_uuid = $1 ORDER BY creation",
+            invite_uuid
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(redemptions)
+    }
+
+    /// Revokes an invite, the redemptions are kept.
+    pub async fn revoke_invite(&self, uuid: &str) -> Result<()> {
+        sqlx::query!("UPDATE invite SET revoked = TRUE WHERE uuid = $1", uuid)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Redeems an invite for an existing user, who joins the campaign of the invite.
+    pub async fn redeem_invite(&self, code: &str, user_uuid: &str) -> Result<Invite> {
+        let mut tx = self.pool.begin().await?;
+        let invite = redeem(&mut tx, code, user_uuid).await?;
+        tx.commit().await?;
+        Ok(invite)
+    }
+}
+
+/// Checks and counts an invite for a user on the given connection and adds them to its campaign.
+///
+/// Redeeming the same invite again does not count as another use.
+pub(crate) async fn redeem(conn: &mut PgConnection, code: &str, user_uuid: &str) -> Result<Invite> {
+    let Some(invite) = sqlx::query_as!(
+        Invite,
+        "SELECT * FROM invite WHERE code = $1 FOR UPDATE",
+        code
+    )
+    .fetch_optional(&mut *conn)
+    .await?
+    else {
+        bail!("invalid invite code");
+    };
+    let expired = sqlx::query_scalar!(
+        "SELECT expires_at <= CURRENT_TIMESTAMP FROM invite WHERE uuid = $1",
+        invite.uuid
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    if invite.revoked {
+        bail!("the invite has been revoked");
+    }
+    if expired.unwrap_or(false) {
+        bail!("the invite has expired");
+    }
+    let redeemed = sqlx::query!(
+        "INSERT INTO invite_redemption (invite_uuid, user_uuid) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+        invite.uuid,
+        user_uuid
+    )
+    .execute(&mut *conn)
+    .await?
+    .rows_affected()
+        > 0;
+    if redeemed {
+        if invite
+            .max_uses
+            .is_some_and(|max_uses| invite.uses >= max_uses)
+        {
+            bail!("the invite has been used up");
+        }
+        sqlx::query!(
+            "UPDATE invite SET uses = uses + 1 WHERE uuid = $1",
+            invite.uuid
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+    if let Some(campaign_uuid) = &invite.campaign_uuid {
+        sqlx::query!(
+            "INSERT INTO campaign_member (campaign_uuid, user_uuid) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+            campaign_uuid,
+            user_uuid
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(invite)
+}
@@ -12,19 +12,25 @@ impl ItemPresetRepository {
         Self { pool }
     }
 
-    /// Creates a new item preset with the given name and owner, using default values for other fields.
-    pub async fn create_from_name(&self, name: &str, owner: &str) -> Result<String> {
+    /// Creates a new item preset of a campaign with the given name and owner, using default values for other fields.
+    pub async fn create_from_name(
+        &self,
+        name: &str,
+        owner: &str,
+        campaign_uuid: &str,
+    ) -> Result<String> {
         let id = Uuid::new_v4().to_string();
         sqlx::query!(
-            "INSERT INTO item_preset (uuid, name, price, weight, description, creator, item_type) 
-             VALUES ($1, $2, $3, $4, $5, $6, $7)",
+            "INSERT INTO item_preset (uuid, name, price, weight, description, creator, item_type, campaign_uuid) 
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
             id,
             name,
             0,
             0.0,
             "",
             owner,
-            ""
+            "",
+            campaign_uuid
         )
         .execute(&self.pool)
         .await?;
@@ -35,9 +41,9 @@ impl ItemPresetRepository {
     pub async fn create(&self, item: &ItemPreset) -> Result<(), Error> {
         let id = Uuid::new_v4().to_string();
         sqlx::query!(
-            "INSERT INTO item_preset (uuid, name, price, weight, description, creator, item_type, creation)
-             VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
-            id, item.name, item.price, item.weight, item.description, item.creator, item.item_type, item.creation
+            "INSERT INTO item_preset (uuid, name, price, weight, description, creator, item_type, creation, container, weightless_contents, campaign_uuid)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)",
+            id, item.name, item.price, item.weight, item.description, item.creator, item.item_type, item.creation, item.container, item.weightless_contents, item.campaign_uuid
         )
         .execute(&self.pool)
         .await?;
@@ -57,6 +63,7 @@ impl ItemPresetRepository {
     }
 
     /// Updates an item preset's fields by UUID. Only non-`None` fields are updated.
+    #[allow(clippy::too_many_arguments)]
     pub async fn update_item_preset(
         &self,
         uuid: &str,
@@ -65,6 +72,8 @@ impl ItemPresetRepository {
         weight: Option<f32>,
         description: Option<&str>,
         item_type: Option<&str>,
+        container: Option<bool>,
+        weightless_contents: Option<bool>,
     ) -> Result<(), Error> {
         sqlx::query!(
             "UPDATE item_preset SET 
@@ -72,20 +81,39 @@ impl ItemPresetRepository {
                 price = COALESCE($2, price), 
                 weight = COALESCE($3, weight), 
                 description = COALESCE($4, description), 
-                item_type = COALESCE($5, item_type) 
+                item_type = COALESCE($5, item_type),
+                container = COALESCE($7, container),
+                weightless_contents = COALESCE($8, weightless_contents)
              WHERE uuid = $6",
             name,
             price,
             weight,
             description,
             item_type,
-            uuid
+            uuid,
+            container,
+            weightless_contents
         )
         .execute(&self.pool)
         .await?;
         Ok(())
     }
 
+    /// Checks if any item of the preset holds other items.
+    pub async fn has_contents(&self, uuid: &str) -> Result<bool, Error> {
+        let has_contents = sqlx::query_scalar!(
+            "SELECT EXISTS (
+                SELECT 1 FROM inventory_item c
+                JOIN inventory_item content ON content.inventory_uuid = c.inventory_uuid AND content.container_item_uuid = c.uuid
+                WHERE c.item_preset_uuid = $1
+             ) AS \"exists!\"",
+            uuid
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(has_contents)
+    }
+
     /// Deletes an item preset by UUID.
     pub async fn delete(&self, uuid: &str) -> Result<(), Error> {
         sqlx::query!("DELETE FROM item_preset WHERE uuid = $1", uuid)
@@ -94,11 +122,15 @@ impl ItemPresetRepository {
         Ok(())
     }
 
-    /// Retrieves all public item presets (where the creator starts with "public").
-    pub async fn get_public_presets(&self) -> Result<Vec<ItemPreset>, Error> {
+    /// Retrieves all public item presets (where the creator starts with "public") of the given campaigns.
+    pub async fn get_public_presets(
+        &self,
+        campaign_uuids: &[String],
+    ) -> Result<Vec<ItemPreset>, Error> {
         let presets = sqlx::query_as!(
             ItemPreset,
-            "SELECT * FROM item_preset WHERE creator LIKE 'public%'"
+            "SELECT * FROM item_preset WHERE creator LIKE 'public%' AND campaign_uuid = ANY($1)",
+            campaign_uuids
         )
         .fetch_all(&self.pool)
         .await?;
@@ -106,14 +138,20 @@ impl ItemPresetRepository {
     }
 
     /// Retrieves all item presets present in a specific inventory.
-    pub async fn get_presets_in_inventory(&self, inventory_uuid: &str) -> Result<Vec<ItemPreset>> {
+    /// The presets of disguised items are only included with `include_disguised`, for DMs.
+    pub async fn get_presets_in_inventory(
+        &self,
+        inventory_uuid: &str,
+        include_disguised: bool,
+    ) -> Result<Vec<ItemPreset>> {
         let presets = sqlx::query_as!(
             ItemPreset,
-            "SELECT ip.uuid, ip.name, ip.price, ip.weight, ip.description, ip.creator, ip.item_type, ip.creation
+            "SELECT DISTINCT ip.uuid, ip.name, ip.price, ip.weight, ip.description, ip.creator, ip.item_type, ip.creation, ip.container, ip.weightless_contents, ip.campaign_uuid
              FROM item_preset ip
              INNER JOIN inventory_item ii ON ip.uuid = ii.item_preset_uuid
-             WHERE ii.inventory_uuid = $1",
-            inventory_uuid
+             WHERE ii.inventory_uuid = $1 AND ($2 OR ii.disguise_name IS NULL)",
+            inventory_uuid,
+            include_disguised
         )
         .fetch_all(&self.pool)
         .await?;
@@ -0,0 +1,415 @@
+use crate::model::{InventoryItem, ItemChange, ItemGift};
+use crate::repos::inventory_repository::empty_container;
+use crate::repos::undo_repository::clear_history;
+use anyhow::{bail, Result};
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+pub struct ItemTransferRepository {
+    pool: PgPool,
+}
+
+impl ItemTransferRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Moves an amount of an item from one inventory to another in a single transaction.
+    ///
+    /// The stack in the source is split if only a part is moved and merged into a matching
+    /// stack in the target, see `put_item`. Returns the changes of the source and the target item.
+    pub async fn transfer_item(
+        &self,
+        source_inventory_uuid: &str,
+        target_inventory_uuid: &str,
+        item_uuid: &str,
+        amount: i32,
+    ) -> Result<(ItemChange, ItemChange)> {
+        if source_inventory_uuid == target_inventory_uuid {
+            bail!("source and target inventory are the same");
+        }
+        let mut tx = self.pool.begin().await?;
+        let source_change = take_item(&mut tx, source_inventory_uuid, item_uuid, amount).await?;
+        let template = source_change.before.as_ref().expect("taken item exists");
+        let target_change = put_item(&mut tx, target_inventory_uuid, amount, template).await?;
+        tx.commit().await?;
+        Ok((source_change, target_change))
+    }
+
+    /// Removes an amount of an item from the source inventory and holds it as a gift for the target inventory.
+    pub async fn create_gift(
+        &self,
+        source_inventory_uuid: &str,
+        target_inventory_uuid: &str,
+        item_uuid: &str,
+        amount: i32,
+        sender_uuid: &str,
+    ) -> Result<(ItemGift, ItemChange)> {
+        if source_inventory_uuid == target_inventory_uuid {
+            bail!("source and target inventory are the same");
+        }
+        let mut tx = self.pool.begin().await?;
+        let source_change = take_item(&mut tx, source_inventory_uuid, item_uuid, amount).await?;
+        let template = source_change.before.as_ref().expect("taken item exists");
+        let gift = insert_gift(
+            &mut tx,
+            target_inventory_uuid,
+            sender_uuid,
+            amount,
+            template,
+        )
+        .await?;
+        tx.commit().await?;
+        Ok((gift, source_change))
+    }
+
+    /// Retrieves a pending gift.
+    pub async fn get_gift(&self, uuid: &str) -> Result<ItemGift> {
+        let gift = sqlx::query_as!(
+            ItemGift,
+            "SELECT uuid, source_inventory_uuid, target_inventory_uuid, item_preset_uuid, sender_uuid, amount, dm_note, sorting, inventory_item_note, disguise_name, disguise_description, disguise_item_type, creation
+             FROM item_gift
+             WHERE uuid = $1",
+            uuid
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(gift)
+    }
+
+    /// Returns all pending gifts sent from or to one of the given inventories, oldest first.
+    pub async fn get_gifts_of_inventories(
+        &self,
+        inventory_uuids: &[String],
+    ) -> Result<Vec<ItemGift>> {
+        let gifts = sqlx::query_as!(
+            ItemGift,
+            "SELECT uuid, source_inventory_uuid, target_inventory_uuid, item_preset_uuid, sender_uuid, amount, dm_note, sorting, inventory_item_note, disguise_name, disguise_description, disguise_item_type, creation
+             FROM item_gift
+             WHERE source_inventory_uuid = ANY($1) OR target_inventory_uuid = ANY($1)
+             ORDER BY creation, uuid",
+            inventory_uuids
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(gifts)
+    }
+
+    /// Moves the items of a gift into its target inventory and removes the gift.
+    pub async fn accept_gift(&self, uuid: &str) -> Result<(ItemGift, ItemChange)> {
+        self.resolve_gift(uuid, true).await
+    }
+
+    /// Returns the items of a gift to its source inventory and removes the gift.
+    pub async fn decline_gift(&self, uuid: &str) -> Result<(ItemGift, ItemChange)> {
+        self.resolve_gift(uuid, false).await
+    }
+
+    async fn resolve_gift(&self, uuid: &str, accept: bool) -> Result<(ItemGift, ItemChange)> {
+        let mut tx = self.pool.begin().await?;
+        let gift = sqlx::query_as!(
+            ItemGift,
+            "DELETE FROM item_gift WHERE uuid = $1
+             RETURNING uuid, source_inventory_uuid, target_inventory_uuid, item_preset_uuid, sender_uuid, amount, dm_note, sorting, inventory_item_note, disguise_name, disguise_description, disguise_item_type, creation",
+            uuid
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(gift) = gift else {
+            bail!("gift {} does not exist", uuid);
+        };
+        let inventory_uuid = if accept {
+            &gift.target_inventory_uuid
+        } else {
+            &gift.source_inventory_uuid
+        };
+        let template = InventoryItem {
+            uuid: gift.uuid.clone(),
+            inventory_uuid: gift.source_inventory_uuid.clone(),
+            item_preset_uuid: gift.item_preset_uuid.clone(),
+            dm_note: gift.dm_note.clone(),
+            amount: gift.amount,
+            sorting: gift.sorting,
+            inventory_item_note: gift.inventory_item_note.clone(),
+            disguise_name: gift.disguise_name.clone(),
+            disguise_description: gift.disguise_description.clone(),
+            disguise_item_type: gift.disguise_item_type.clone(),
+            creation: gift.creation,
+            container_item_uuid: None,
+        };
+        let change = put_item(&mut tx, inventory_uuid, gift.amount, &template).await?;
+        tx.commit().await?;
+        Ok((gift, change))
+    }
+
+    /// Moves an amount of an item into a new stack of the same inventory, which takes over its notes and container.
+    ///
+    /// Returns the changes of the split item and the new stack.
+    pub async fn split_item(
+        &self,
+        inventory_uuid: &str,
+        item_uuid: &str,
+        amount: i32,
+    ) -> Result<(ItemChange, ItemChange)> {
+        let mut tx = self.pool.begin().await?;
+        let source_change = take_item(&mut tx, inventory_uuid, item_uuid, amount).await?;
+        let Some(template) = &source_change.after else {
+            bail!("at least one item has to stay in the stack");
+        };
+        let new_item = insert_item(
+            &mut tx,
+            inventory_uuid,
+            amount,
+            template,
+            template.container_item_uuid.as_deref(),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok((
+            source_change,
+            ItemChange {
+                before: None,
+                after: Some(new_item),
+            },
+        ))
+    }
+
+    /// Merges a stack into another stack of the same preset and inventory, the target keeps its notes.
+    ///
+    /// Stacks with different disguises can not be merged. Returns the changes of the removed source and the target.
+    pub async fn merge_items(
+        &self,
+        inventory_uuid: &str,
+        source_item_uuid: &str,
+        target_item_uuid: &str,
+    ) -> Result<(ItemChange, ItemChange)> {
+        if source_item_uuid == target_item_uuid {
+            bail!("an item can not be merged into itself");
+        }
+        let mut tx = self.pool.begin().await?;
+        let source = lock_item(&mut tx, inventory_uuid, source_item_uuid).await?;
+        let target = lock_item(&mut tx, inventory_uuid, target_item_uuid).await?;
+        let (Some(source), Some(target)) = (source, target) else {
+            bail!("both items have to be in inventory {}", inventory_uuid);
+        };
+        // A single message for both cases, so players can not tell disguised presets apart
+        if source.item_preset_uuid != target.item_preset_uuid
+            || source.disguise_name != target.disguise_name
+        {
+            bail!("the items can not be merged");
+        }
+        let source_change =
+            take_item(&mut tx, inventory_uuid, source_item_uuid, source.amount).await?;
+        let after = sqlx::query_as!(
+            InventoryItem,
+            "UPDATE inventory_item SET amount = amount + $3
+             WHERE inventory_uuid = $1 AND uuid = $2
+             RETURNING uuid, inventory_uuid, item_preset_uuid, dm_note, amount, sorting, inventory_item_note, disguise_name, disguise_description, disguise_item_type, creation, container_item_uuid",
+            inventory_uuid,
+            target_item_uuid,
+            source.amount
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok((
+            source_change,
+            ItemChange {
+                before: Some(target),
+                after: Some(after),
+            },
+        ))
+    }
+}
+
+/// Locks an item of an inventory for the rest of the transaction.
+async fn lock_item(
+    conn: &mut PgConnection,
+    inventory_uuid: &str,
+    item_uuid: &str,
+) -> Result<Option<InventoryItem>> {
+    let item = sqlx::query_as!(
+        InventoryItem,
+        "SELECT uuid, inventory_uuid, item_preset_uuid, dm_note, amount, sorting, inventory_item_note, disguise_name, disguise_description, disguise_item_type, creation, container_item_uuid
+         FROM inventory_item
+         WHERE inventory_uuid = $1 AND uuid = $2
+         FOR UPDATE",
+        inventory_uuid,
+        item_uuid
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+    Ok(item)
+}
+
+/// Removes an amount of an item from an inventory, deleting the item if nothing is left.
+///
+/// Fails if the inventory does not hold enough of the item. Discards the undo history of the inventory, see `clear_history`.
+pub(crate) async fn take_item(
+    conn: &mut PgConnection,
+    inventory_uuid: &str,
+    item_uuid: &str,
+    amount: i32,
+) -> Result<ItemChange> {
+    if amount <= 0 {
+        bail!("amount must be positive");
+    }
+    let Some(before) = lock_item(conn, inventory_uuid, item_uuid).await? else {
+        bail!("item {} is not in inventory {}", item_uuid, inventory_uuid);
+    };
+    if before.amount < amount {
+        bail!(
+            "inventory {} holds only {} of item {}",
+            inventory_uuid,
+            before.amount,
+            item_uuid
+        );
+    }
+    clear_history(conn, inventory_uuid).await?;
+    if before.amount == amount {
+        empty_container(conn, inventory_uuid, item_uuid).await?;
+        sqlx::query!(
+            "DELETE FROM inventory_item WHERE inventory_uuid = $1 AND uuid = $2",
+            inventory_uuid,
+            item_uuid
+        )
+        .execute(&mut *conn)
+        .await?;
+        return Ok(ItemChange {
+            before: Some(before),
+            after: None,
+        });
+    }
+    let after = sqlx::query_as!(
+        InventoryItem,
+        "UPDATE inventory_item SET amount = amount - $3
+         WHERE inventory_uuid = $1 AND uuid = $2
+         RETURNING uuid, inventory_uuid, item_preset_uuid, dm_note, amount, sorting, inventory_item_note, disguise_name, disguise_description, disguise_item_type, creation, container_item_uuid",
+        inventory_uuid,
+        item_uuid,
+        amount
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    Ok(ItemChange {
+        before: Some(before),
+        after: Some(after),
+    })
+}
+
+/// Holds `amount` items like `template`, which were taken from its inventory, as a gift to the target inventory.
+pub(crate) async fn insert_gift(
+    conn: &mut PgConnection,
+    target_inventory_uuid: &str,
+    sender_uuid: &str,
+    amount: i32,
+    template: &InventoryItem,
+) -> Result<ItemGift> {
+    let gift = sqlx::query_as!(
+        ItemGift,
+        "INSERT INTO item_gift (uuid, source_inventory_uuid, target_inventory_uuid, item_preset_uuid, sender_uuid, amount, dm_note, sorting, inventory_item_note, disguise_name, disguise_description, disguise_item_type)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+         RETURNING uuid, source_inventory_uuid, target_inventory_uuid, item_preset_uuid, sender_uuid, amount, dm_note, sorting, inventory_item_note, disguise_name, disguise_description, disguise_item_type, creation",
+        Uuid::new_v4().to_string(),
+        template.inventory_uuid,
+        target_inventory_uuid,
+        template.item_preset_uuid,
+        sender_uuid,
+        amount,
+        template.dm_note,
+        template.sorting,
+        template.inventory_item_note,
+        template.disguise_name,
+        template.disguise_description,
+        template.disguise_item_type
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    Ok(gift)
+}
+
+/// Adds an amount of an item to an inventory, merging it into a top level stack of the same preset with the same notes and disguise.
+///
+/// Without such a stack a new one is created on the top level, taking over the notes, sorting and disguise of the template item.
+/// Discards the undo history of the inventory, see `clear_history`.
+pub(crate) async fn put_item(
+    conn: &mut PgConnection,
+    inventory_uuid: &str,
+    amount: i32,
+    template: &InventoryItem,
+) -> Result<ItemChange> {
+    let before = sqlx::query_as!(
+        InventoryItem,
+        "SELECT uuid, inventory_uuid, item_preset_uuid, dm_note, amount, sorting, inventory_item_note, disguise_name, disguise_description, disguise_item_type, creation, container_item_uuid
+         FROM inventory_item
+         WHERE inventory_uuid = $1 AND item_preset_uuid = $2 AND container_item_uuid IS NULL
+           AND dm_note = $3 AND inventory_item_note = $4
+           AND disguise_name IS NOT DISTINCT FROM $5
+           AND disguise_description IS NOT DISTINCT FROM $6
+           AND disguise_item_type IS NOT DISTINCT FROM $7
+         ORDER BY creation, uuid
+         LIMIT 1
+         FOR UPDATE",
+        inventory_uuid,
+        template.item_preset_uuid,
+        template.dm_note,
+        template.inventory_item_note,
+        template.disguise_name,
+        template.disguise_description,
+        template.disguise_item_type
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+    clear_history(conn, inventory_uuid).await?;
+    let Some(before) = before else {
+        let after = insert_item(conn, inventory_uuid, amount, template, None).await?;
+        return Ok(ItemChange {
+            before: None,
+            after: Some(after),
+        });
+    };
+    let after = sqlx::query_as!(
+        InventoryItem,
+        "UPDATE inventory_item SET amount = amount + $2
+         WHERE uuid = $1
+         RETURNING uuid, inventory_uuid, item_preset_uuid, dm_note, amount, sorting, inventory_item_note, disguise_name, disguise_description, disguise_item_type, creation, container_item_uuid",
+        before.uuid,
+        amount
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    Ok(ItemChange {
+        before: Some(before),
+        after: Some(after),
+    })
+}
+
+/// Creates a new stack of an item, taking over the notes, sorting and disguise of the template item.
+async fn insert_item(
+    conn: &mut PgConnection,
+    inventory_uuid: &str,
+    amount: i32,
+    template: &InventoryItem,
+    container_item_uuid: Option<&str>,
+) -> Result<InventoryItem> {
+    let item = sqlx::query_as!(
+        InventoryItem,
+        "INSERT INTO inventory_item (uuid, inventory_uuid, item_preset_uuid, dm_note, amount, sorting, inventory_item_note, disguise_name, disguise_description, disguise_item_type, container_item_uuid)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+         RETURNING uuid, inventory_uuid, item_preset_uuid, dm_note, amount, sorting, inventory_item_note, disguise_name, disguise_description, disguise_item_type, creation, container_item_uuid",
+        Uuid::new_v4().to_string(),
+        inventory_uuid,
+        template.item_preset_uuid,
+        template.dm_note,
+        amount,
+        template.sorting,
+        template.inventory_item_note,
+        template.disguise_name,
+        template.disguise_description,
+        template.disguise_item_type,
+        container_item_uuid
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    Ok(item)
+}
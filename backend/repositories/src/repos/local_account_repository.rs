@@ -0,0 +1,100 @@
+use crate::model::{LocalAccount, User};
+use crate::repos::invite_repository::redeem;
+use crate::repos::user_repository::insert_new_user;
+use anyhow::{bail, Result};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub struct LocalAccountRepository {
+    pool: PgPool,
+}
+
+impl LocalAccountRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Creates a new user with a local account, redeeming the invite if a code is given.
+    /// The first user created is assigned DM status, nothing is created if the username is taken or the invite is not valid.
+    pub async fn register(
+        &self,
+        username: &str,
+        password_hash: &str,
+        invite_code: Option<&str>,
+    ) -> Result<User> {
+        let mut tx = self.pool.begin().await?;
+        let taken = sqlx::query_scalar!(
+            "SELECT EXISTS(SELECT 1 FROM local_account WHERE LOWER(username) = LOWER($1))",
+            username
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        if taken.unwrap_or(false) {
+            bail!("the username is already taken");
+        }
+        let uuid = Uuid::new_v4().to_string();
+        let user = insert_new_user(&mut tx, &uuid, username, "").await?;
+        sqlx::query!(
+            "INSERT INTO local_account (user_uuid, username, password_hash) VALUES ($1, $2, $3)",
+            uuid,
+            username,
+            password_hash
+        )
+        .execute(&mut *tx)
+        .await?;
+        if let Some(code) = invite_code {
+            redeem(&mut tx, code, &uuid).await?;
+        }
+        tx.commit().await?;
+        Ok(user)
+    }
+
+    /// Retrieves the local account with the given username, ignoring case.
+    pub async fn get_account_by_username(&self, username: &str) -> Result<Option<LocalAccount>> {
+        let account = sqlx::query_as!(
+            LocalAccount,
+            "SELECT * FROM local_account WHERE LOWER(username) = LOWER($1)",
+            username
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(account)
+    }
+
+    /// Retrieves the local account of a user, users logging in through Discord have none.
+    pub async fn get_account(&self, user_uuid: &str) -> Result<Option<LocalAccount>> {
+        let account = sqlx::query_as!(
+            LocalAccount,
+            "SELECT * FROM local_account WHERE user_uuid = $1",
+            user_uuid
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(account)
+    }
+
+    /// Replaces the password hash of a local account.
+    ///
+    /// Fails if the user has no local account.
+    pub async fn set_password(
+        &self,
+        user_uuid: &str,
+        password_hash: &str,
+        must_change_password: bool,
+    ) -> Result<()> {
+        let updated = sqlx::query!(
+            "UPDATE local_account SET password_hash = $2, must_change_password = $3, updated = CURRENT_TIMESTAMP
+             WHERE user_uuid = $1",
+            user_uuid,
+            password_hash,
+            must_change_password
+        )
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+        if updated == 0 {
+            bail!("the user has no local account");
+        }
+        Ok(())
+    }
+}
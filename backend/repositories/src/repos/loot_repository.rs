@@ -0,0 +1,117 @@
+use crate::model::{ItemChange, LootSplit};
+use crate::repos::item_transfer_repository::{insert_gift, put_item, take_item};
+use crate::repos::money_repository::apply_transfer;
+use anyhow::{bail, Result};
+use sqlx::PgPool;
+use std::collections::HashSet;
+
+pub struct LootRepository {
+    pool: PgPool,
+}
+
+impl LootRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Divides an amount of money and the given items of the source evenly across the target inventories.
+    ///
+    /// Everything that can not be divided evenly stays in the source. Either the whole split is applied or nothing.
+    /// The items of the targets in `gift_target_uuids` are held as gifts until the recipients accept them.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn split_loot(
+        &self,
+        source_inventory_uuid: &str,
+        target_inventory_uuids: &[String],
+        gift_target_uuids: &[String],
+        user_uuid: &str,
+        amount: i32,
+        item_uuids: &[String],
+        reason: Option<&str>,
+    ) -> Result<LootSplit> {
+        if target_inventory_uuids.is_empty() {
+            bail!("no target inventories given");
+        }
+        if amount < 0 {
+            bail!("amount must not be negative");
+        }
+        let unique_targets: HashSet<&String> = target_inventory_uuids.iter().collect();
+        if unique_targets.len() != target_inventory_uuids.len() {
+            bail!("target inventories must be distinct");
+        }
+        if unique_targets.contains(&source_inventory_uuid.to_string()) {
+            bail!("the source inventory can not be a target");
+        }
+        let targets = target_inventory_uuids.len() as i32;
+
+        let mut tx = self.pool.begin().await?;
+        let mut transactions = Vec::new();
+        let share = amount / targets;
+        if share > 0 {
+            for target in target_inventory_uuids {
+                let (withdrawal, deposit) = apply_transfer(
+                    &mut tx,
+                    source_inventory_uuid,
+                    target,
+                    user_uuid,
+                    share,
+                    reason,
+                )
+                .await?;
+                transactions.push(withdrawal);
+                transactions.push(deposit);
+            }
+        }
+
+        let mut item_changes = Vec::new();
+        let mut gifts = Vec::new();
+        for item_uuid in item_uuids {
+            let stack = sqlx::query_scalar!(
+                "SELECT amount FROM inventory_item WHERE inventory_uuid = $1 AND uuid = $2",
+                source_inventory_uuid,
+                item_uuid
+            )
+            .fetch_optional(&mut *tx)
+            .await?;
+            let Some(stack) = stack else {
+                bail!(
+                    "item {} is not in inventory {}",
+                    item_uuid,
+                    source_inventory_uuid
+                );
+            };
+            let item_share = stack / targets;
+            if item_share == 0 {
+                continue;
+            }
+            let mut source_change: Option<ItemChange> = None;
+            for target in target_inventory_uuids {
+                let taken =
+                    take_item(&mut tx, source_inventory_uuid, item_uuid, item_share).await?;
+                let template = taken.before.as_ref().expect("taken item exists");
+                if gift_target_uuids.contains(target) {
+                    gifts
+                        .push(insert_gift(&mut tx, target, user_uuid, item_share, template).await?);
+                } else {
+                    item_changes.push(put_item(&mut tx, target, item_share, template).await?);
+                }
+                source_change = Some(match source_change {
+                    Some(first) => ItemChange {
+                        before: first.before,
+                        after: taken.after,
+                    },
+                    None => taken,
+                });
+            }
+            item_changes.extend(source_change);
+        }
+        tx.commit().await?;
+
+        Ok(LootSplit {
+            transactions,
+            item_changes,
+            gifts,
+            remainder: amount - share * targets,
+        })
+    }
+}
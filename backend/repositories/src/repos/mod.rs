@@ -1,3 +1,17 @@
+pub mod api_token_repository;
+pub mod campaign_repository;
+pub mod change_log_repository;
+pub mod currency_repository;
+pub mod identity_repository;
 pub mod inventory_repository;
+pub mod invite_repository;
 pub mod item_preset_repository;
+pub mod item_transfer_repository;
+pub mod local_account_repository;
+pub mod loot_repository;
+pub mod money_repository;
+pub mod role_change_repository;
+pub mod session_repository;
+pub mod setting_repository;
+pub mod undo_repository;
 pub mod user_repository;
@@ -0,0 +1,196 @@
+use crate::model::{CoinAmount, MoneyTransaction};
+use crate::repos::currency_repository::{change_coins, change_money};
+use crate::repos::undo_repository::clear_history;
+use anyhow::{bail, Result};
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+pub struct MoneyRepository {
+    pool: PgPool,
+}
+
+impl MoneyRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Posts a transaction to the ledger of an inventory and applies its delta to the inventory's money.
+    ///
+    /// Fails without any change if the balance would become negative.
+    pub async fn post_transaction(
+        &self,
+        inventory_uuid: &str,
+        user_uuid: &str,
+        delta: i32,
+        reason: Option<&str>,
+    ) -> Result<MoneyTransaction> {
+        let mut tx = self.pool.begin().await?;
+        let transaction =
+            apply_transaction(&mut tx, inventory_uuid, user_uuid, delta, reason, None).await?;
+        tx.commit().await?;
+        Ok(transaction)
+    }
+
+    /// Posts a transaction adding and removing the given coins of an inventory, without converting between currencies.
+    ///
+    /// The transaction's delta is the change in value. Fails without any change if coins of a currency would become negative.
+    /// Discards the undo history of the inventory, see `clear_history`.
+    pub async fn post_coins(
+        &self,
+        inventory_uuid: &str,
+        user_uuid: &str,
+        coins: &[CoinAmount],
+        reason: Option<&str>,
+    ) -> Result<MoneyTransaction> {
+        let mut tx = self.pool.begin().await?;
+        let delta = change_coins(&mut tx, inventory_uuid, coins).await?;
+        clear_history(&mut tx, inventory_uuid).await?;
+        let transaction =
+            book_transaction(&mut tx, inventory_uuid, user_uuid, delta, reason, None).await?;
+        tx.commit().await?;
+        Ok(transaction)
+    }
+
+    /// Moves money from one inventory to another, booking a transaction in both ledgers.
+    ///
+    /// Returns the withdrawal from the source and the deposit into the target.
+    pub async fn transfer_money(
+        &self,
+        source_inventory_uuid: &str,
+        target_inventory_uuid: &str,
+        user_uuid: &str,
+        amount: i32,
+        reason: Option<&str>,
+    ) -> Result<(MoneyTransaction, MoneyTransaction)> {
+        if amount <= 0 {
+            bail!("amount must be positive");
+        }
+        if source_inventory_uuid == target_inventory_uuid {
+            bail!("source and target inventory are the same");
+        }
+        let mut tx = self.pool.begin().await?;
+        let transfer = apply_transfer(
+            &mut tx,
+            source_inventory_uuid,
+            target_inventory_uuid,
+            user_uuid,
+            amount,
+            reason,
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(transfer)
+    }
+
+    /// Returns a page of the ledger of the given inventories, newest first, and the total number of transactions.
+    pub async fn get_ledger(
+        &self,
+        inventory_uuids: &[String],
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<MoneyTransaction>, i64)> {
+        let transactions = sqlx::query_as!(
+            MoneyTransaction,
+            "SELECT uuid, inventory_uuid, user_uuid, delta, reason, counterpart_inventory_uuid, creation
+             FROM money_transaction
+             WHERE inventory_uuid = ANY($1)
+             ORDER BY creation DESC, uuid
+             LIMIT $2 OFFSET $3",
+            inventory_uuids,
+            limit,
+            offset
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        let total = sqlx::query_scalar!(
+            "SELECT COUNT(*) FROM money_transaction WHERE inventory_uuid = ANY($1)",
+            inventory_uuids
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok((transactions, total.unwrap_or(0)))
+    }
+}
+
+/// Inserts a transaction and applies its delta to the inventory's money on the given connection.
+///
+/// The delta is converted into coins, see `change_money`.
+pub(crate) async fn apply_transaction(
+    conn: &mut PgConnection,
+    inventory_uuid: &str,
+    user_uuid: &str,
+    delta: i32,
+    reason: Option<&str>,
+    counterpart_inventory_uuid: Option<&str>,
+) -> Result<MoneyTransaction> {
+    change_money(conn, inventory_uuid, delta).await?;
+    book_transaction(
+        conn,
+        inventory_uuid,
+        user_uuid,
+        delta,
+        reason,
+        counterpart_inventory_uuid,
+    )
+    .await
+}
+
+/// Inserts a transaction into the ledger without touching the inventory's money.
+async fn book_transaction(
+    conn: &mut PgConnection,
+    inventory_uuid: &str,
+    user_uuid: &str,
+    delta: i32,
+    reason: Option<&str>,
+    counterpart_inventory_uuid: Option<&str>,
+) -> Result<MoneyTransaction> {
+    let transaction = sqlx::query_as!(
+        MoneyTransaction,
+        "INSERT INTO money_transaction (uuid, inventory_uuid, user_uuid, delta, reason, counterpart_inventory_uuid)
+         VALUES ($1, $2, $3, $4, $5, $6)
+         RETURNING uuid, inventory_uuid, user_uuid, delta, reason, counterpart_inventory_uuid, creation",
+        Uuid::new_v4().to_string(),
+        inventory_uuid,
+        user_uuid,
+        delta,
+        reason,
+        counterpart_inventory_uuid
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    Ok(transaction)
+}
+
+/// Moves money from one inventory to another on the given connection.
+///
+/// Discards the undo history of both inventories, see `clear_history`.
+pub(crate) async fn apply_transfer(
+    conn: &mut PgConnection,
+    source_inventory_uuid: &str,
+    target_inventory_uuid: &str,
+    user_uuid: &str,
+    amount: i32,
+    reason: Option<&str>,
+) -> Result<(MoneyTransaction, MoneyTransaction)> {
+    clear_history(conn, source_inventory_uuid).await?;
+    clear_history(conn, target_inventory_uuid).await?;
+    let withdrawal = apply_transaction(
+        conn,
+        source_inventory_uuid,
+        user_uuid,
+        -amount,
+        reason,
+        Some(target_inventory_uuid),
+    )
+    .await?;
+    let deposit = apply_transaction(
+        conn,
+        target_inventory_uuid,
+        user_uuid,
+        amount,
+        reason,
+        Some(source_inventory_uuid),
+    )
+    .await?;
+    Ok((withdrawal, deposit))
+}
@@ -0,0 +1,45 @@
+use crate::model::RoleChange;
+use anyhow::Result;
+use sqlx::{PgConnection, PgPool};
+
+pub struct RoleChangeRepository {
+    pool: PgPool,
+}
+
+impl RoleChangeRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Returns the DM role changes of a campaign, or of the server for `None`, the newest first.
+    pub async fn get_role_changes(&self, campaign_uuid: Option<&str>) -> Result<Vec<RoleChange>> {
+        let changes = sqlx::query_as!(
+            RoleChange,
+            "SELECT * FROM role_change WHERE campaign_uuid IS NOT DISTINCT FROM $1 ORDER BY id DESC",
+            campaign_uuid
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(changes)
+    }
+}
+
+/// Records that a user was granted or revoked DM status on the given connection.
+pub(crate) async fn record_role_change(
+    conn: &mut PgConnection,
+    user_uuid: &str,
+    changed_by: &str,
+    campaign_uuid: Option<&str>,
+    dm: bool,
+) -> Result<()> {
+    sqlx::query!(
+        "INSERT INTO role_change (user_uuid, changed_by, campaign_uuid, dm) VALUES ($1, $2, $3, $4)",
+        user_uuid,
+        changed_by,
+        campaign_uuid,
+        dm
+    )
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
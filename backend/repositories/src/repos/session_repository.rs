@@ -0,0 +1,110 @@
+use crate::model::Session;
+use anyhow::Result;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub struct SessionRepository {
+    pool: PgPool,
+}
+
+impl SessionRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Starts a session for a user that expires after the given number of hours.
+    pub async fn create_session(
+        &self,
+        user_uuid: &str,
+        token_hash: &str,
+        user_agent: Option<&str>,
+        lifetime_hours: i32,
+    ) -> Result<Session> {
+        let session = sqlx::query_as!(
+            Session,
+            "INSERT INTO session (uuid, user_uuid, token_hash, user_agent, expires_at)
+             VALUES ($1, $2, $3, $4, CURRENT_TIMESTAMP + make_interval(hours => $5))
+             RETURNING uuid, user_uuid, user_agent, creation, last_seen, expires_at",
+            Uuid::new_v4().to_string(),
+            user_uuid,
+            token_hash,
+            user_agent,
+            lifetime_hours
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(session)
+    }
+
+    /// Looks up a session that has not expired by the hash of its token and records its use.
+    ///
+    /// With `renew_hours` set the session is extended to expire that many hours from now.
+    pub async fn authenticate(
+        &self,
+        token_hash: &str,
+        renew_hours: Option<i32>,
+    ) -> Result<Option<Session>> {
+        let session = sqlx::query_as!(
+            Session,
+            "UPDATE session SET last_seen = CURRENT_TIMESTAMP,
+                 expires_at = COALESCE(CURRENT_TIMESTAMP + make_interval(hours => $2), expires_at)
+             WHERE token_hash = $1 AND expires_at > CURRENT_TIMESTAMP
+             RETURNING uuid, user_uuid, user_agent, creation, last_seen, expires_at",
+            token_hash,
+            renew_hours
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(session)
+    }
+
+    /// Returns the sessions of a user that have not expired, the most recently used first.
+    pub async fn get_sessions(&self, user_uuid: &str) -> Result<Vec<Session>> {
+        let sessions = sqlx::query_as!(
+            Session,
+            "SELECT uuid, user_uuid, user_agent, creation, last_seen, expires_at FROM session
+             WHERE user_uuid = $1 AND expires_at > CURRENT_TIMESTAMP
+             ORDER BY last_seen DESC",
+            user_uuid
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(sessions)
+    }
+
+    /// Ends a session of a user, returns the number of ended sessions.
+    pub async fn revoke_session(&self, uuid: &str, user_uuid: &str) -> Result<u64> {
+        let result = sqlx::query!(
+            "DELETE FROM session WHERE uuid = $1 AND user_uuid = $2",
+            uuid,
+            user_uuid
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Ends all sessions of a user but the given one, returns the number of ended sessions.
+    pub async fn revoke_all_sessions(
+        &self,
+        user_uuid: &str,
+        except_uuid: Option<&str>,
+    ) -> Result<u64> {
+        let result = sqlx::query!(
+            "DELETE FROM session WHERE user_uuid = $1 AND uuid IS DISTINCT FROM $2",
+            user_uuid,
+            except_uuid
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Removes all expired sessions.
+    pub async fn delete_expired_sessions(&self) -> Result<u64> {
+        let result = sqlx::query!("DELETE FROM session WHERE expires_at <= CURRENT_TIMESTAMP")
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+}
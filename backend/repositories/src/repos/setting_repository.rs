@@ -0,0 +1,81 @@
+use anyhow::Result;
+use sqlx::PgPool;
+
+/// Key of the registration lock setting.
+const LOCKED_KEY: &str = "locked";
+
+/// Key of the secret the private cookies are encrypted with.
+const COOKIE_SECRET_KEY: &str = "cookie_secret";
+
+pub struct SettingRepository {
+    pool: PgPool,
+}
+
+impl SettingRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Retrieves the value of a setting, `None` if it was never set.
+    pub async fn get_setting(&self, key: &str) -> Result<Option<String>> {
+        let value = sqlx::query_scalar!("SELECT value FROM setting WHERE key = $1", key)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(value)
+    }
+
+    /// Sets the value of a setting.
+    pub async fn set_setting(&self, key: &str, value: &str) -> Result<()> {
+        sqlx::query!(
+            "INSERT INTO setting (key, value) VALUES ($1, $2)
+             ON CONFLICT (key) DO UPDATE SET value = EXCLUDED.value, updated = CURRENT_TIMESTAMP",
+            key,
+            value
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Checks if the server is locked, new users can then only register with an invite.
+    pub async fn is_locked(&self) -> Result<bool> {
+        Ok(self
+            .get_setting(LOCKED_KEY)
+            .await?
+            .is_none_or(|value| value == "true"))
+    }
+
+    /// Locks or unlocks the server for new users.
+    pub async fn set_locked(&self, locked: bool) -> Result<()> {
+        self.set_setting(LOCKED_KEY, &locked.to_string()).await
+    }
+
+    /// Flips the lock state of the server and returns the new state.
+    pub async fn toggle_locked(&self) -> Result<bool> {
+        let value = sqlx::query_scalar!(
+            "INSERT INTO setting (key, value) VALUES ($1, 'false')
+             ON CONFLICT (key) DO UPDATE SET
+                 value = CASE WHEN setting.value = 'true' THEN 'false' ELSE 'true' END,
+                 updated = CURRENT_TIMESTAMP
+             RETURNING value",
+            LOCKED_KEY
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(value == "true")
+    }
+
+    /// Returns the stored cookie secret, storing the given one first if there is none yet.
+    pub async fn get_or_init_cookie_secret(&self, secret: &str) -> Result<String> {
+        let value = sqlx::query_scalar!(
+            "INSERT INTO setting (key, value) VALUES ($1, $2)
+             ON CONFLICT (key) DO UPDATE SET value = setting.value
+             RETURNING value",
+            COOKIE_SECRET_KEY,
+            secret
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(value)
+    }
+}
@@ -0,0 +1,152 @@
+use crate::model::{InventoryChangeKind, InventoryItem, InventoryOperation};
+use anyhow::Result;
+use sqlx::{PgConnection, PgPool};
+
+/// Number of operations kept per inventory, older ones can no longer be undone.
+const UNDO_HISTORY_LENGTH: i64 = 100;
+
+pub struct UndoRepository {
+    pool: PgPool,
+}
+
+impl UndoRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Records a reversible operation on an inventory.
+    ///
+    /// Discards all undone operations of the inventory, as they can no longer be redone,
+    /// and trims the history to the last `UNDO_HISTORY_LENGTH` operations.
+    pub async fn record_operation(
+        &self,
+        inventory_uuid: &str,
+        user_uuid: &str,
+        change_kind: InventoryChangeKind,
+        item: Option<&InventoryItem>,
+        old_value: Option<&str>,
+        new_value: Option<&str>,
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query!(
+            "DELETE FROM inventory_operation WHERE inventory_uuid = $1 AND undone",
+            inventory_uuid
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            "INSERT INTO inventory_operation (inventory_uuid, user_uuid, change_kind, item_uuid, item_preset_uuid, old_value, new_value)
+             VALUES ($1, $2, $3, $4, $5, $6, $7)",
+            inventory_uuid,
+            user_uuid,
+            change_kind.as_str(),
+            item.map(|i| i.uuid.as_str()),
+            item.map(|i| i.item_preset_uuid.as_str()),
+            old_value,
+            new_value
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            "DELETE FROM inventory_operation WHERE inventory_uuid = $1 AND id NOT IN (
+                SELECT id FROM inventory_operation WHERE inventory_uuid = $1 ORDER BY id DESC LIMIT $2
+             )",
+            inventory_uuid,
+            UNDO_HISTORY_LENGTH
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Returns the latest operation of an inventory that was not undone yet.
+    pub async fn get_undo_candidate(
+        &self,
+        inventory_uuid: &str,
+    ) -> Result<Option<InventoryOperation>> {
+        let rec = sqlx::query!(
+            "SELECT id, inventory_uuid, user_uuid, change_kind, item_uuid, item_preset_uuid, old_value, new_value, undone
+             FROM inventory_operation
+             WHERE inventory_uuid = $1 AND NOT undone
+             ORDER BY id DESC
+             LIMIT 1",
+            inventory_uuid
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        rec.map(|r| {
+            Ok(InventoryOperation {
+                id: r.id,
+                inventory_uuid: r.inventory_uuid,
+                user_uuid: r.user_uuid,
+                change_kind: r.change_kind.parse()?,
+                item_uuid: r.item_uuid,
+                item_preset_uuid: r.item_preset_uuid,
+                old_value: r.old_value,
+                new_value: r.new_value,
+                undone: r.undone,
+            })
+        })
+        .transpose()
+    }
+
+    /// Returns the earliest undone operation of an inventory, which is the next one to redo.
+    pub async fn get_redo_candidate(
+        &self,
+        inventory_uuid: &str,
+    ) -> Result<Option<InventoryOperation>> {
+        let rec = sqlx::query!(
+            "SELECT id, inventory_uuid, user_uuid, change_kind, item_uuid, item_preset_uuid, old_value, new_value, undone
+             FROM inventory_operation
+             WHERE inventory_uuid = $1 AND undone
+             ORDER BY id ASC
+             LIMIT 1",
+            inventory_uuid
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        rec.map(|r| {
+            Ok(InventoryOperation {
+                id: r.id,
+                inventory_uuid: r.inventory_uuid,
+                user_uuid: r.user_uuid,
+                change_kind: r.change_kind.parse()?,
+                item_uuid: r.item_uuid,
+                item_preset_uuid: r.item_preset_uuid,
+                old_value: r.old_value,
+                new_value: r.new_value,
+                undone: r.undone,
+            })
+        })
+        .transpose()
+    }
+
+    /// Marks an operation as undone or redone.
+    pub async fn set_undone(&self, id: i64, undone: bool) -> Result<()> {
+        sqlx::query!(
+            "UPDATE inventory_operation SET undone = $1 WHERE id = $2",
+            undone,
+            id
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
+
+/// Discards the undo and redo history of an inventory on the given connection.
+///
+/// Used by changes that are not recorded as operations, like transfers, gifts, splits, merges and coin posts.
+/// Undo restores the snapshots of an operation, which would silently revert such changes.
+pub(crate) async fn clear_history(conn: &mut PgConnection, inventory_uuid: &str) -> Result<()> {
+    sqlx::query!(
+        "DELETE FROM inventory_operation WHERE inventory_uuid = $1",
+        inventory_uuid
+    )
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
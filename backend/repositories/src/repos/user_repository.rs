@@ -1,6 +1,8 @@
 use crate::model::User;
-use anyhow::Result;
-use sqlx::PgPool;
+use crate::repos::inventory_repository::transfer_ownership;
+use crate::repos::role_change_repository::record_role_change;
+use anyhow::{bail, Result};
+use sqlx::{PgConnection, PgPool};
 
 pub struct UserRepository {
     pool: PgPool,
@@ -15,18 +17,8 @@ impl UserRepository {
     /// The first user created is assigned DM status.
     pub async fn create_user(&self, uuid: &str, name: &str, avatar: &str) -> Result<User> {
         let dm: i32 = if self.dm_exists().await? { 0 } else { 1 };
-        let user = sqlx::query_as!(
-            User,
-            "INSERT INTO \"user\" (uuid, name, avatar, dm) VALUES ($1, $2, $3, $4) RETURNING *",
-            uuid,
-            name,
-            avatar,
-            dm
-        )
-        .fetch_one(&self.pool)
-        .await?;
-
-        Ok(user)
+        let mut conn = self.pool.acquire().await?;
+        insert_user(&mut conn, uuid, name, avatar, dm).await
     }
 
     /// Retrieves a user by their UUID.
@@ -48,10 +40,50 @@ impl UserRepository {
     }
 
     /// Deletes a user by their UUID.
-    pub async fn delete_user(&self, uuid: &str) -> Result<u64> {
+    ///
+    /// Owned inventories are deleted with the user unless they are reassigned to another user,
+    /// who has to be a member of the campaign of every reassigned inventory.
+    /// Fails if the user is the last server DM or the last DM of a campaign.
+    pub async fn delete_user(&self, uuid: &str, reassign_to: Option<&str>) -> Result<u64> {
+        if reassign_to == Some(uuid) {
+            bail!("the inventories can not be reassigned to the deleted user");
+        }
+        let mut tx = self.pool.begin().await?;
+        let dms = sqlx::query_scalar!("SELECT uuid FROM \"user\" WHERE dm = 1 FOR UPDATE")
+            .fetch_all(&mut *tx)
+            .await?;
+        if dms == [uuid] {
+            bail!("the last DM can not be deleted");
+        }
+        let last_dm = sqlx::query_scalar!(
+            "SELECT EXISTS(
+                SELECT 1 FROM campaign_member m
+                WHERE m.user_uuid = $1 AND m.dm
+                AND NOT EXISTS(
+                    SELECT 1 FROM campaign_member o
+                    WHERE o.campaign_uuid = m.campaign_uuid AND o.dm AND o.user_uuid <> $1
+                )
+            )",
+            uuid
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        if last_dm.unwrap_or(false) {
+            bail!("the last DM of a campaign can not be deleted");
+        }
+        if let Some(new_owner_uuid) = reassign_to {
+            let inventory_uuids =
+                sqlx::query_scalar!("SELECT uuid FROM inventory WHERE owner_uuid = $1", uuid)
+                    .fetch_all(&mut *tx)
+                    .await?;
+            for inventory_uuid in inventory_uuids {
+                transfer_ownership(&mut tx, &inventory_uuid, new_owner_uuid, false).await?;
+            }
+        }
         let result = sqlx::query!("DELETE FROM \"user\" WHERE uuid = $1", uuid)
-            .execute(&self.pool)
+            .execute(&mut *tx)
             .await?;
+        tx.commit().await?;
 
         Ok(result.rows_affected())
     }
@@ -72,6 +104,39 @@ impl UserRepository {
         Ok(user)
     }
 
+    /// Grants or revokes the server DM status of a user and records who changed it.
+    ///
+    /// Fails if the user is the last server DM.
+    pub async fn set_dm(&self, uuid: &str, dm: bool, changed_by: &str) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        let dms = sqlx::query_scalar!("SELECT uuid FROM \"user\" WHERE dm = 1 FOR UPDATE")
+            .fetch_all(&mut *tx)
+            .await?;
+        let user = sqlx::query_as!(
+            User,
+            "SELECT * FROM \"user\" WHERE uuid = $1 FOR UPDATE",
+            uuid
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        if (user.dm == 1) == dm {
+            return Ok(());
+        }
+        if !dm && dms.len() == 1 {
+            bail!("the last DM can not be revoked");
+        }
+        sqlx::query!(
+            "UPDATE \"user\" SET dm = $2 WHERE uuid = $1",
+            uuid,
+            i32::from(dm)
+        )
+        .execute(&mut *tx)
+        .await?;
+        record_role_change(&mut tx, uuid, changed_by, None, dm).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
     /// Checks if a user with the given UUID exists.
     pub async fn user_exists(&self, uuid: &str) -> Result<bool> {
         let exists = sqlx::query_scalar!(
@@ -111,3 +176,39 @@ impl UserRepository {
         Ok(result.exists.unwrap_or(false))
     }
 }
+
+/// Inserts a new user on the given connection, who becomes DM if there is none yet.
+pub(crate) async fn insert_new_user(
+    conn: &mut PgConnection,
+    uuid: &str,
+    name: &str,
+    avatar: &str,
+) -> Result<User> {
+    let dm_exists = sqlx::query_scalar!("SELECT EXISTS(SELECT 1 FROM \"user\" WHERE dm = 1)")
+        .fetch_one(&mut *conn)
+        .await?;
+    let dm: i32 = if dm_exists.unwrap_or(false) { 0 } else { 1 };
+    insert_user(conn, uuid, name, avatar, dm).await
+}
+
+/// Inserts a new user on the given connection.
+pub(crate) async fn insert_user(
+    conn: &mut PgConnection,
+    uuid: &str,
+    name: &str,
+    avatar: &str,
+    dm: i32,
+) -> Result<User> {
+    let user = sqlx::query_as!(
+        User,
+        "INSERT INTO \"user\" (uuid, name, avatar, dm) VALUES ($1, $2, $3, $4) RETURNING *",
+        uuid,
+        name,
+        avatar,
+        dm
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(user)
+}